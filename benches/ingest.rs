@@ -0,0 +1,71 @@
+//! Compares bulk ingestion of a sorted recording with inserting its transforms one by one.
+//!
+//! Run with `cargo bench --bench ingest`.
+
+use std::time::{Duration, Instant};
+
+use tf_rosrust::{
+    transforms::{geometry_msgs::TransformStamped, tf2_msgs::TFMessage},
+    StorageStrategy, TfBuffer,
+};
+
+const RATE_HZ: i64 = 1000;
+const SECONDS: i64 = 60;
+
+fn recording() -> Vec<(TransformStamped, bool)> {
+    let period = 1_000_000_000 / RATE_HZ;
+    (1..=RATE_HZ * SECONDS)
+        .map(|i| {
+            let mut tf = TransformStamped::default();
+            tf.header.frame_id = "map".to_string();
+            tf.header.stamp = rosrust::Time::from_nanos(i * period);
+            tf.child_frame_id = "base_link".to_string();
+            tf.transform.rotation.w = 1.0;
+            tf.transform.translation.x = i as f64 * 1e-3;
+            (tf, false)
+        })
+        .collect()
+}
+
+fn build(storage: StorageStrategy) -> TfBuffer {
+    TfBuffer::builder()
+        .cache_duration(rosrust::Duration::from_seconds(10))
+        .storage(storage)
+        .build()
+}
+
+fn one_by_one(storage: StorageStrategy, recording: &[(TransformStamped, bool)]) -> Duration {
+    let mut buffer = build(storage);
+    let start = Instant::now();
+    for (tf, static_tf) in recording {
+        buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![tf.clone()],
+            },
+            *static_tf,
+        );
+    }
+    start.elapsed()
+}
+
+fn bulk(storage: StorageStrategy, recording: &[(TransformStamped, bool)]) -> Duration {
+    let mut buffer = build(storage);
+    let start = Instant::now();
+    buffer.extend_sorted(recording);
+    start.elapsed()
+}
+
+fn main() {
+    let recording = recording();
+    for storage in [
+        StorageStrategy::ReadOptimized,
+        StorageStrategy::WriteOptimized,
+    ] {
+        println!(
+            "{storage:?}, {} samples: one by one {:?}, extend_sorted {:?}",
+            recording.len(),
+            one_by_one(storage, &recording),
+            bulk(storage, &recording)
+        );
+    }
+}
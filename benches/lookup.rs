@@ -0,0 +1,122 @@
+//! Compares `lookup_transform` with the allocation-reusing `lookup_transform_into` on a static chain, and batch
+//! lookups at a shared time with and without the interpolation memo.
+//!
+//! Run with `cargo bench --bench lookup`.
+
+use std::time::{Duration, Instant};
+
+use tf_rosrust::{
+    transforms::{geometry_msgs::TransformStamped, tf2_msgs::TFMessage},
+    TfBuffer,
+};
+
+const CHAIN_LENGTH: usize = 10;
+const LOOKUPS: usize = 200_000;
+const TARGETS: usize = 50;
+const CYCLES: usize = 2_000;
+
+fn frame(index: usize) -> String {
+    format!("link_{index}")
+}
+
+fn static_chain() -> TfBuffer {
+    let mut buffer = TfBuffer::new_with_duration(rosrust::Duration::from_seconds(10));
+    let transforms = (1..=CHAIN_LENGTH)
+        .map(|index| {
+            let mut tf = TransformStamped::default();
+            tf.header.frame_id = frame(index - 1);
+            tf.child_frame_id = frame(index);
+            tf.transform.rotation.w = 1.0;
+            tf.transform.translation.x = 0.1;
+            tf
+        })
+        .collect();
+    buffer.handle_incoming_transforms(TFMessage { transforms }, true);
+    buffer
+}
+
+fn cloning(buffer: &TfBuffer) -> Duration {
+    let (from, to) = (frame(0), frame(CHAIN_LENGTH));
+    let start = Instant::now();
+    for _ in 0..LOOKUPS {
+        buffer
+            .lookup_transform(&from, &to, rosrust::Time::new())
+            .unwrap();
+    }
+    start.elapsed()
+}
+
+fn borrowing(buffer: &TfBuffer) -> Duration {
+    let (from, to) = (frame(0), frame(CHAIN_LENGTH));
+    let mut out = TransformStamped::default();
+    let start = Instant::now();
+    for _ in 0..LOOKUPS {
+        buffer
+            .lookup_transform_into(&from, &to, rosrust::Time::new(), &mut out)
+            .unwrap();
+    }
+    start.elapsed()
+}
+
+/// A dynamic chain of `CHAIN_LENGTH` edges with `TARGETS` static leaves at its end.
+fn dynamic_tree(interpolation_memo: bool) -> TfBuffer {
+    let mut buffer = TfBuffer::builder()
+        .interpolation_memo(interpolation_memo)
+        .build();
+    for step in 0..100_i64 {
+        let transforms = (1..=CHAIN_LENGTH)
+            .map(|index| {
+                let mut tf = TransformStamped::default();
+                tf.header.frame_id = frame(index - 1);
+                tf.header.stamp = rosrust::Time::from_nanos(step * 10_000_000);
+                tf.child_frame_id = frame(index);
+                tf.transform.rotation.z = (step as f64 * 0.01).sin();
+                tf.transform.rotation.w = (step as f64 * 0.01).cos();
+                tf.transform.translation.x = step as f64 * 0.01;
+                tf
+            })
+            .collect();
+        buffer.handle_incoming_transforms(TFMessage { transforms }, false);
+    }
+    let transforms = (0..TARGETS)
+        .map(|index| {
+            let mut tf = TransformStamped::default();
+            tf.header.frame_id = frame(CHAIN_LENGTH);
+            tf.child_frame_id = format!("target_{index}");
+            tf.transform.rotation.w = 1.0;
+            tf
+        })
+        .collect();
+    buffer.handle_incoming_transforms(TFMessage { transforms }, true);
+    buffer
+}
+
+/// Looks up every target at a shared time per cycle.
+fn batch(buffer: &TfBuffer) -> Duration {
+    let from = frame(0);
+    let targets: Vec<_> = (0..TARGETS)
+        .map(|index| format!("target_{index}"))
+        .collect();
+    let start = Instant::now();
+    for cycle in 0..CYCLES {
+        let time = rosrust::Time::from_nanos(5_000_000 + (cycle as i64 * 7_000_000) % 980_000_000);
+        for target in &targets {
+            buffer.lookup_transform(&from, target, time).unwrap();
+        }
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let buffer = static_chain();
+    println!(
+        "static chain of {CHAIN_LENGTH}: lookup_transform {:?}, lookup_transform_into {:?}",
+        cloning(&buffer),
+        borrowing(&buffer)
+    );
+    println!(
+        "{TARGETS} targets behind a dynamic chain of {CHAIN_LENGTH}: without memo {:?}, with memo {:?}",
+        batch(&dynamic_tree(false)),
+        batch(&dynamic_tree(true))
+    );
+}
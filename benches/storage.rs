@@ -0,0 +1,72 @@
+//! Compares the storage strategies under read-heavy and write-heavy workloads.
+//!
+//! Run with `cargo bench --bench storage`.
+
+use std::time::{Duration, Instant};
+
+use tf_rosrust::{
+    transforms::{geometry_msgs::TransformStamped, tf2_msgs::TFMessage},
+    StorageStrategy, TfBuffer,
+};
+
+const RATE_HZ: u32 = 1000;
+
+fn sample(nanos: i64) -> TFMessage {
+    let mut tf = TransformStamped::default();
+    tf.header.frame_id = "map".to_string();
+    tf.header.stamp = rosrust::Time::from_nanos(nanos);
+    tf.child_frame_id = "base_link".to_string();
+    tf.transform.rotation.w = 1.0;
+    tf.transform.translation.x = nanos as f64 * 1e-9;
+    TFMessage {
+        transforms: vec![tf],
+    }
+}
+
+fn build(storage: StorageStrategy) -> TfBuffer {
+    TfBuffer::builder().storage(storage).build()
+}
+
+/// Fills the cache once, then performs many lookups.
+fn read_heavy(storage: StorageStrategy) -> Duration {
+    let mut buffer = build(storage);
+    let period = 1_000_000_000 / i64::from(RATE_HZ);
+    for i in 1..=i64::from(RATE_HZ) * 10 {
+        buffer.handle_incoming_transforms(sample(i * period), false);
+    }
+    let start = Instant::now();
+    for i in 0..200_000_i64 {
+        let time = rosrust::Time::from_nanos(1_000_000_000 + (i * 37_000) % 8_000_000_000);
+        buffer.lookup_transform("map", "base_link", time).unwrap();
+    }
+    start.elapsed()
+}
+
+/// Streams samples well past the cache duration, evicting continuously, with occasional lookups.
+fn write_heavy(storage: StorageStrategy) -> Duration {
+    let mut buffer = build(storage);
+    let period = 1_000_000_000 / i64::from(RATE_HZ);
+    let start = Instant::now();
+    for i in 1..=i64::from(RATE_HZ) * 60 {
+        buffer.handle_incoming_transforms(sample(i * period), false);
+        if i % 1000 == 0 {
+            buffer
+                .lookup_transform("map", "base_link", rosrust::Time::new())
+                .unwrap();
+        }
+    }
+    start.elapsed()
+}
+
+fn main() {
+    for storage in [
+        StorageStrategy::ReadOptimized,
+        StorageStrategy::WriteOptimized,
+    ] {
+        println!(
+            "{storage:?}: read-heavy {:?}, write-heavy {:?}",
+            read_heavy(storage),
+            write_heavy(storage)
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Shares a buffer between a writer and a reader process through a snapshot file.
+//!
+//! Run `cargo run --example snapshot_file --features snapshot_file -- writer` in one terminal, and the same command
+//! with `reader` in another.
+
+use std::{thread, time::Duration};
+
+use tf_rosrust::{
+    snapshot_file::{SnapshotFileReader, SnapshotFileWriter},
+    transforms::{geometry_msgs::TransformStamped, tf2_msgs::TFMessage},
+    TfBuffer,
+};
+
+const PATH: &str = "/dev/shm/tf_rosrust_example";
+
+fn writer() {
+    let writer = SnapshotFileWriter::new(PATH);
+    let mut buffer = TfBuffer::new_with_duration(rosrust::Duration::from_seconds(10));
+    let mut tf = TransformStamped::default();
+    tf.header.frame_id = "map".to_string();
+    tf.child_frame_id = "base_link".to_string();
+    tf.transform.rotation.w = 1.0;
+    for step in 1_i64.. {
+        tf.header.stamp = rosrust::Time::from_nanos(step * 100_000_000);
+        tf.transform.translation.x = (step as f64 * 0.1).sin();
+        buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![tf.clone()],
+            },
+            false,
+        );
+        writer.publish(&buffer).unwrap();
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn reader() {
+    let mut reader = SnapshotFileReader::open(PATH).unwrap();
+    loop {
+        reader.refresh().unwrap();
+        let tf = reader.lookup_transform("map", "base_link", rosrust::Time::new());
+        println!("{tf:?}");
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+fn main() {
+    match std::env::args().nth(1).as_deref() {
+        Some("writer") => writer(),
+        Some("reader") => reader(),
+        _ => eprintln!("usage: snapshot_file writer|reader"),
+    }
+}
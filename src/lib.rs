@@ -17,15 +17,29 @@
 //! }
 //!```
 
+#[cfg(feature = "serde")]
+pub mod snapshot;
+#[cfg(feature = "snapshot_file")]
+pub mod snapshot_file;
+#[cfg(feature = "yaml")]
+mod static_yaml;
 mod tf_broadcaster;
 mod tf_buffer;
 mod tf_error;
 mod tf_graph_node;
 mod tf_individual_transform_chain;
 pub mod transforms;
-pub use transforms::geometry_msgs::TransformStamped;
+pub mod utils;
+pub use transforms::{
+    chain_transforms, geometry_msgs::TransformStamped, get_inverse, interpolate,
+    to_transform_stamped, TransformStampedBuilder,
+};
 mod tf_listener;
-pub use tf_broadcaster::TfBroadcaster;
-pub use tf_buffer::TfBuffer;
+pub use tf_broadcaster::{TfBroadcaster, TfSink, TfStaticBroadcaster};
+pub use tf_buffer::{
+    FrameStats, IngestStats, MultipleParents, MultipleParentsCallback, TfBuffer, TfBufferBuilder,
+    TransformSource,
+};
 pub use tf_error::TfError;
-pub use tf_listener::TfListener;
+pub use tf_individual_transform_chain::{Extrapolation, InterpolationMode, StorageStrategy};
+pub use tf_listener::{TfListener, TfListenerOptions};
@@ -0,0 +1,74 @@
+//! Serializable copies of the transforms held by a buffer, e.g. to record it to disk and replay it in tests.
+//!
+//! The message types generated by rosrust do not implement serde, so transforms are converted to
+//! [`SerializableTransformStamped`], a flat representation with the stamp in nanoseconds. See
+//! [`TfBuffer::export_snapshot`](crate::TfBuffer::export_snapshot) and
+//! [`TfBuffer::import_snapshot`](crate::TfBuffer::import_snapshot).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    transforms::geometry_msgs::TransformStamped,
+    utils::{time_as_ns_i64, time_from_nanosec},
+};
+
+/// A [`TransformStamped`] that can be serialized.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SerializableTransformStamped {
+    /// The parent frame.
+    pub parent: String,
+    /// The child frame.
+    pub child: String,
+    /// Nanoseconds since the epoch.
+    pub stamp: i64,
+    /// `x, y, z`.
+    pub translation: [f64; 3],
+    /// `x, y, z, w`.
+    pub rotation: [f64; 4],
+}
+
+impl From<&TransformStamped> for SerializableTransformStamped {
+    fn from(transform: &TransformStamped) -> Self {
+        let translation = &transform.transform.translation;
+        let rotation = &transform.transform.rotation;
+        Self {
+            parent: transform.header.frame_id.clone(),
+            child: transform.child_frame_id.clone(),
+            stamp: time_as_ns_i64(&transform.header.stamp),
+            translation: [translation.x, translation.y, translation.z],
+            rotation: [rotation.x, rotation.y, rotation.z, rotation.w],
+        }
+    }
+}
+
+impl From<SerializableTransformStamped> for TransformStamped {
+    fn from(transform: SerializableTransformStamped) -> Self {
+        let mut stamped = TransformStamped::default();
+        stamped.header.frame_id = transform.parent;
+        stamped.header.stamp = time_from_nanosec(transform.stamp);
+        stamped.child_frame_id = transform.child;
+        let [x, y, z] = transform.translation;
+        stamped.transform.translation.x = x;
+        stamped.transform.translation.y = y;
+        stamped.transform.translation.z = z;
+        let [x, y, z, w] = transform.rotation;
+        stamped.transform.rotation.x = x;
+        stamped.transform.rotation.y = y;
+        stamped.transform.rotation.z = z;
+        stamped.transform.rotation.w = w;
+        stamped
+    }
+}
+
+/// A stored sample and whether it was received as a static transform.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotSample {
+    pub transform: SerializableTransformStamped,
+    pub is_static: bool,
+}
+
+/// Every sample stored in a buffer, sorted by stamp, parent and child. Only published edges are included.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BufferSnapshot {
+    pub samples: Vec<SnapshotSample>,
+}
@@ -0,0 +1,309 @@
+//! Read-only sharing of a [`TfBuffer`] between processes through a snapshot file.
+//!
+//! One writer process publishes snapshots of its buffer with [`SnapshotFileWriter`], and any number of reader
+//! processes load them with [`SnapshotFileReader`] to look up transforms without subscribing to `/tf` themselves.
+//! Readers decode each snapshot into a buffer of their own, so a refresh costs about as much as receiving every
+//! sample again. Placing the file on a RAM-backed file system such as `/dev/shm` avoids touching the disk.
+//!
+//! Snapshots use a flat, pointer-free little-endian layout:
+//!
+//! | Field          | Type        | Notes                             |
+//! |----------------|-------------|-----------------------------------|
+//! | magic          | `[u8; 7]`   | `b"TFSHM\0\0"`                    |
+//! | version        | `u8`        | currently 1                       |
+//! | cache duration | `i64`       | nanoseconds                       |
+//! | sample count   | `u64`       |                                   |
+//! | samples        | see below   | `sample count` times              |
+//!
+//! Each sample is a `u8` static flag, the parent and child frame ids as a `u32` length followed by UTF-8 bytes, the
+//! stamp as `i64` nanoseconds, then the translation `x, y, z` and rotation `x, y, z, w` as `f64`. Like in the
+//! buffer itself, only published edges are stored.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    tf_buffer::TfBuffer,
+    tf_error::TfError,
+    transforms::geometry_msgs::TransformStamped,
+    utils::{time_as_ns_i64, time_from_nanosec, TimePoint},
+};
+
+const MAGIC: &[u8; 7] = b"TFSHM\0\0";
+const VERSION: u8 = 1;
+
+/// Serializes the samples of `buffer` into the flat snapshot layout.
+pub fn encode(buffer: &TfBuffer) -> Vec<u8> {
+    let samples: Vec<_> = buffer.published_samples().collect();
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&(buffer.cache_duration().nanos()).to_le_bytes());
+    bytes.extend_from_slice(&(samples.len() as u64).to_le_bytes());
+    for (sample, is_static) in samples {
+        bytes.push(u8::from(is_static));
+        for frame in [&sample.header.frame_id, &sample.child_frame_id] {
+            bytes.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(frame.as_bytes());
+        }
+        bytes.extend_from_slice(&time_as_ns_i64(&sample.header.stamp).to_le_bytes());
+        let translation = &sample.transform.translation;
+        let rotation = &sample.transform.rotation;
+        for value in [
+            translation.x,
+            translation.y,
+            translation.z,
+            rotation.x,
+            rotation.y,
+            rotation.z,
+            rotation.w,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+/// Rebuilds a buffer from a snapshot produced by [`encode`].
+pub fn decode(bytes: &[u8]) -> Result<TfBuffer, TfError> {
+    let mut reader = ByteReader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(invalid("not a tf buffer snapshot"));
+    }
+    let version = reader.take(1)?[0];
+    if version != VERSION {
+        return Err(invalid(&format!("unsupported version {version}")));
+    }
+    let cache_duration = rosrust::Duration::from_nanos(reader.i64()?);
+    let count = reader.u64()?;
+    let mut samples = Vec::new();
+    for _ in 0..count {
+        let is_static = reader.take(1)?[0] != 0;
+        let mut sample = TransformStamped::default();
+        sample.header.frame_id = reader.string()?;
+        sample.child_frame_id = reader.string()?;
+        sample.header.stamp = time_from_nanosec(reader.i64()?);
+        let translation = &mut sample.transform.translation;
+        translation.x = reader.f64()?;
+        translation.y = reader.f64()?;
+        translation.z = reader.f64()?;
+        let rotation = &mut sample.transform.rotation;
+        rotation.x = reader.f64()?;
+        rotation.y = reader.f64()?;
+        rotation.z = reader.f64()?;
+        rotation.w = reader.f64()?;
+        samples.push((sample, is_static));
+    }
+    if !reader.bytes.is_empty() {
+        return Err(invalid("trailing bytes"));
+    }
+    samples.sort_by_key(|(sample, _)| sample.header.stamp);
+    let mut buffer = TfBuffer::builder()
+        .cache_duration(cache_duration)
+        .retain_all_static(true)
+        .build();
+    buffer.extend_sorted(&samples);
+    Ok(buffer)
+}
+
+fn invalid(reason: &str) -> TfError {
+    TfError::SnapshotFile(format!("invalid snapshot: {reason}"))
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], TfError> {
+        if self.bytes.len() < len {
+            return Err(invalid("truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], TfError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn i64(&mut self) -> Result<i64, TfError> {
+        self.array().map(i64::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, TfError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Result<f64, TfError> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, TfError> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("frame id is not UTF-8"))
+    }
+}
+
+/// Publishes snapshots of a buffer for [`SnapshotFileReader`]s in other processes.
+#[derive(Clone, Debug)]
+pub struct SnapshotFileWriter {
+    path: PathBuf,
+}
+
+impl SnapshotFileWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Replaces the shared snapshot with the current content of `buffer`.
+    ///
+    /// The snapshot is written next to the shared file and renamed over it, so readers never see a partial write. The
+    /// staging file is named after the process, so that several writers of the same file do not clobber each other.
+    pub fn publish(&self, buffer: &TfBuffer) -> Result<(), TfError> {
+        let mut staging = self.path.clone().into_os_string();
+        staging.push(format!(".{}.tmp", std::process::id()));
+        fs::write(&staging, encode(buffer)).map_err(io_error)?;
+        fs::rename(&staging, &self.path).map_err(io_error)
+    }
+}
+
+/// A read-only view of a buffer published by a [`SnapshotFileWriter`] in another process.
+#[derive(Debug)]
+pub struct SnapshotFileReader {
+    path: PathBuf,
+    buffer: TfBuffer,
+}
+
+impl SnapshotFileReader {
+    /// Loads the snapshot at `path`.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, TfError> {
+        let path = path.into();
+        let buffer = Self::load(&path)?;
+        Ok(Self { path, buffer })
+    }
+
+    /// Loads the latest snapshot published by the writer.
+    pub fn refresh(&mut self) -> Result<(), TfError> {
+        self.buffer = Self::load(&self.path)?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<TfBuffer, TfError> {
+        decode(&fs::read(path).map_err(io_error)?)
+    }
+
+    /// The buffer as of the last [`SnapshotFileReader::refresh`].
+    pub fn buffer(&self) -> &TfBuffer {
+        &self.buffer
+    }
+
+    /// Looks up a transform in the buffer as of the last [`SnapshotFileReader::refresh`].
+    pub fn lookup_transform(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        self.buffer.lookup_transform(from, to, time)
+    }
+}
+
+fn io_error(err: std::io::Error) -> TfError {
+    TfError::SnapshotFile(err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transforms::tf2_msgs::TFMessage;
+
+    fn test_buffer() -> TfBuffer {
+        let mut buffer = TfBuffer::new_with_duration(rosrust::Duration::from_seconds(3));
+        let mut transform = TransformStamped::default();
+        transform.header.frame_id = "map".to_string();
+        transform.child_frame_id = "base".to_string();
+        transform.transform.rotation.w = 1.0;
+        for sec in [1, 2] {
+            transform.header.stamp = rosrust::Time { sec, nsec: 0 };
+            transform.transform.translation.x = f64::from(sec);
+            buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform.clone()],
+                },
+                false,
+            );
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_layout() {
+        let bytes = encode(&test_buffer());
+        assert_eq!(&bytes[..8], b"TFSHM\0\0\x01");
+        assert_eq!(bytes[8..16], 3_000_000_000_i64.to_le_bytes());
+        assert_eq!(bytes[16..24], 2_u64.to_le_bytes());
+        let sample_len = 1 + (4 + 3) + (4 + 4) + 8 + 7 * 8;
+        assert_eq!(bytes.len(), 24 + 2 * sample_len);
+        let first = &bytes[24..24 + sample_len];
+        assert_eq!(first[0], 0);
+        assert_eq!(first[1..5], 3_u32.to_le_bytes());
+        assert_eq!(&first[5..8], b"map");
+        assert_eq!(first[8..12], 4_u32.to_le_bytes());
+        assert_eq!(&first[12..16], b"base");
+        assert_eq!(first[16..24], 1_000_000_000_i64.to_le_bytes());
+        assert_eq!(first[24..32], 1.0_f64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let buffer = test_buffer();
+        let decoded = decode(&encode(&buffer)).unwrap();
+        assert!(decoded.validate().is_ok());
+        for time in [1.0, 1.5, 2.0] {
+            assert_eq!(
+                decoded.lookup_transform("base", "map", time).unwrap(),
+                buffer.lookup_transform("base", "map", time).unwrap()
+            );
+        }
+
+        let bytes = encode(&buffer);
+        assert!(matches!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(TfError::SnapshotFile(_))
+        ));
+        assert!(matches!(
+            decode(b"not a snapshot"),
+            Err(TfError::SnapshotFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_writer_and_reader() {
+        let path = std::env::temp_dir().join(format!("tf_rosrust_{}.snapshot", std::process::id()));
+        let writer = SnapshotFileWriter::new(&path);
+        let mut buffer = test_buffer();
+        writer.publish(&buffer).unwrap();
+        let mut reader = SnapshotFileReader::open(&path).unwrap();
+        assert!(reader.lookup_transform("map", "base", 2.5).is_err());
+
+        let mut transform = TransformStamped::default();
+        transform.header.frame_id = "map".to_string();
+        transform.header.stamp = rosrust::Time { sec: 3, nsec: 0 };
+        transform.child_frame_id = "base".to_string();
+        transform.transform.rotation.w = 1.0;
+        buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![transform],
+            },
+            false,
+        );
+        writer.publish(&buffer).unwrap();
+        reader.refresh().unwrap();
+        assert!(reader.lookup_transform("map", "base", 2.5).is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+}
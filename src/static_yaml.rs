@@ -0,0 +1,134 @@
+//! Parsing and writing of static transforms in YAML, for [`TfBuffer::load_static_yaml`](crate::TfBuffer::load_static_yaml)
+//! and [`TfBuffer::static_tree_to_yaml`](crate::TfBuffer::static_tree_to_yaml).
+//!
+//! The document is a list of entries such as:
+//!
+//! ```yaml
+//! - parent: base_link
+//!   child: camera
+//!   translation: [0.1, 0.0, 0.5]
+//!   rotation: [0.0, 0.0, 0.0, 1.0]
+//! - parent: base_link
+//!   child: lidar
+//!   translation: [0.0, 0.0, 0.8]
+//!   rpy: [0.0, 0.0, 3.14159]
+//! ```
+//!
+//! `rotation` is a quaternion `x, y, z, w` and `rpy` roll, pitch and yaw in radians. Exactly one of them must be given.
+
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{
+    tf_error::TfError,
+    transforms::{
+        geometry_msgs::{Quaternion, TransformStamped},
+        quaternion_from_euler,
+    },
+};
+
+/// Parses every entry of `yaml` into a transform stamped at time zero.
+pub(crate) fn parse(yaml: &str) -> Result<Vec<TransformStamped>, TfError> {
+    let documents = YamlLoader::load_from_str(yaml)
+        .map_err(|err| TfError::InvalidArgument(format!("invalid static transform YAML: {err}")))?;
+    let Some(document) = documents.into_iter().next() else {
+        return Ok(Vec::new());
+    };
+    let entries = match document {
+        Yaml::Array(entries) => entries,
+        Yaml::Null => return Ok(Vec::new()),
+        _ => {
+            return Err(TfError::InvalidArgument(
+                "static transform YAML must be a list of entries".to_string(),
+            ))
+        }
+    };
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            parse_entry(entry).map_err(|reason| {
+                TfError::InvalidArgument(format!("static transform entry {index}: {reason}"))
+            })
+        })
+        .collect()
+}
+
+fn parse_entry(entry: &Yaml) -> Result<TransformStamped, String> {
+    if entry.as_hash().is_none() {
+        return Err("expected a mapping".to_string());
+    }
+    let mut transform = TransformStamped::default();
+    transform.header.frame_id = string_field(entry, "parent")?;
+    transform.child_frame_id = string_field(entry, "child")?;
+    let [x, y, z] = float_array(entry, "translation")?;
+    transform.transform.translation.x = x;
+    transform.transform.translation.y = y;
+    transform.transform.translation.z = z;
+    transform.transform.rotation = match (&entry["rotation"], &entry["rpy"]) {
+        (Yaml::BadValue, Yaml::BadValue) => return Err("missing `rotation` or `rpy`".to_string()),
+        (Yaml::BadValue, _) => {
+            let [roll, pitch, yaw] = float_array(entry, "rpy")?;
+            quaternion_from_euler(roll, pitch, yaw)
+        }
+        (_, Yaml::BadValue) => {
+            let [x, y, z, w] = float_array(entry, "rotation")?;
+            Quaternion { x, y, z, w }
+        }
+        _ => return Err("both `rotation` and `rpy` given".to_string()),
+    };
+    Ok(transform)
+}
+
+fn string_field(entry: &Yaml, key: &str) -> Result<String, String> {
+    match &entry[key] {
+        Yaml::String(value) => Ok(value.clone()),
+        Yaml::BadValue => Err(format!("missing `{key}`")),
+        _ => Err(format!("`{key}` must be a string")),
+    }
+}
+
+fn float_array<const N: usize>(entry: &Yaml, key: &str) -> Result<[f64; N], String> {
+    let values = match &entry[key] {
+        Yaml::Array(values) => values,
+        Yaml::BadValue => return Err(format!("missing `{key}`")),
+        _ => return Err(format!("`{key}` must be a list of {N} numbers")),
+    };
+    let values: Option<Vec<f64>> = values
+        .iter()
+        .map(|value| match value {
+            Yaml::Integer(value) => Some(*value as f64),
+            Yaml::Real(_) => value.as_f64(),
+            _ => None,
+        })
+        .collect();
+    values
+        .and_then(|values| values.try_into().ok())
+        .ok_or_else(|| format!("`{key}` must be a list of {N} numbers"))
+}
+
+/// Writes `transforms` in the format read by [`parse`], with the rotation as a quaternion.
+pub(crate) fn write(transforms: &[TransformStamped]) -> String {
+    let mut yaml = String::new();
+    for transform in transforms {
+        let translation = &transform.transform.translation;
+        let rotation = &transform.transform.rotation;
+        yaml.push_str(&format!(
+            "- parent: {}\n  child: {}\n  translation: [{:?}, {:?}, {:?}]\n  rotation: [{:?}, {:?}, {:?}, {:?}]\n",
+            quoted(&transform.header.frame_id),
+            quoted(&transform.child_frame_id),
+            translation.x,
+            translation.y,
+            translation.z,
+            rotation.x,
+            rotation.y,
+            rotation.z,
+            rotation.w,
+        ));
+    }
+    yaml
+}
+
+/// Quotes a frame id as a single-quoted YAML scalar.
+fn quoted(frame: &str) -> String {
+    format!("'{}'", frame.replace('\'', "''"))
+}
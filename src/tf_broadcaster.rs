@@ -1,6 +1,9 @@
+use std::{collections::HashMap, sync::Mutex};
+
 use crate::{
-    tf_error::TfError,
+    tf_error::{validate_frame_ids, TfError},
     transforms::{geometry_msgs::TransformStamped, tf2_msgs::TFMessage},
+    utils::duration_from_secs_f64,
 };
 
 /// Broadcast tf messages
@@ -29,27 +32,179 @@ use crate::{
 ///     rate.sleep();
 /// }
 /// ```
-pub struct TfBroadcaster {
-    publisher: rosrust::Publisher<TFMessage>,
+pub struct TfBroadcaster<S = rosrust::Publisher<TFMessage>> {
+    publisher: S,
+    rate_limit: Option<RateLimit>,
+}
+
+/// Drops sends for a child frame arriving sooner than `min_period` after the last one sent for it.
+struct RateLimit {
+    min_period: rosrust::Duration,
+    clock: Box<dyn Fn() -> rosrust::Time + Send + Sync>,
+    last_sent: Mutex<HashMap<String, rosrust::Time>>,
+}
+
+impl RateLimit {
+    /// Whether a transform for `child_frame_id` may be sent now, recording the send if so.
+    fn allow(&self, child_frame_id: &str) -> bool {
+        let now = (self.clock)();
+        let Ok(mut last_sent) = self.last_sent.lock() else {
+            return true;
+        };
+        match last_sent.get_mut(child_frame_id) {
+            Some(last) if now - *last < self.min_period => false,
+            Some(last) => {
+                *last = now;
+                true
+            }
+            None => {
+                last_sent.insert(child_frame_id.to_string(), now);
+                true
+            }
+        }
+    }
+}
+
+/// Where a [`TfBroadcaster`] sends its messages. Implemented by the `/tf` publisher, and by test doubles.
+pub trait TfSink {
+    fn send(&self, tf_message: TFMessage) -> Result<(), TfError>;
+}
+
+impl TfSink for rosrust::Publisher<TFMessage> {
+    fn send(&self, tf_message: TFMessage) -> Result<(), TfError> {
+        // TODO: handle error correctly
+        rosrust::Publisher::send(self, tf_message)
+            .map_err(|err| TfError::Rosrust(err.description().to_string()))
+    }
 }
 
 impl TfBroadcaster {
     /// Create a new TfBroadcaster
     pub fn new() -> Self {
+        Self::with_sink(rosrust::publish("/tf", 1000).unwrap())
+    }
+}
+
+impl<S: TfSink> TfBroadcaster<S> {
+    /// Create a TfBroadcaster sending its messages to `sink` instead of `/tf`
+    pub fn with_sink(sink: S) -> Self {
         Self {
-            publisher: rosrust::publish("/tf", 1000).unwrap(),
+            publisher: sink,
+            rate_limit: None,
         }
     }
 
+    /// Limit sends to `max_hz` per child frame, measured with the node clock
+    ///
+    /// Transforms sent faster than that are dropped, which protects `/tf` from a node sending in a hot loop.
+    ///
+    /// Panics if `max_hz` is not a finite, positive rate.
+    pub fn with_max_hz(self, max_hz: f64) -> Self {
+        self.with_max_hz_and_clock(max_hz, rosrust::now)
+    }
+
+    /// Limit sends to `max_hz` per child frame, measured with `clock`
+    ///
+    /// Panics if `max_hz` is not a finite, positive rate.
+    pub fn with_max_hz_and_clock(
+        mut self,
+        max_hz: f64,
+        clock: impl Fn() -> rosrust::Time + Send + Sync + 'static,
+    ) -> Self {
+        assert!(
+            max_hz.is_finite() && max_hz > 0.0,
+            "max_hz must be finite and positive, got {max_hz}"
+        );
+        self.rate_limit = Some(RateLimit {
+            min_period: duration_from_secs_f64(1.0 / max_hz),
+            clock: Box::new(clock),
+            last_sent: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
     /// Broadcast transform
+    ///
+    /// Transforms with an empty frame id, or from a frame to itself, are rejected with [`TfError::InvalidFrameId`]
+    /// since they would corrupt every buffer receiving them.
+    ///
+    /// With a rate limit, transforms sent too soon after the previous one for the same child frame are dropped.
     pub fn send_transform(&self, tf: TransformStamped) -> Result<(), TfError> {
-        let tf_message = TFMessage {
-            transforms: vec![tf],
-        };
-        // TODO: handle error correctly
-        self.publisher
-            .send(tf_message)
-            .map_err(|err| TfError::Rosrust(err.description().to_string()))
+        self.send_transforms(vec![tf])
+    }
+
+    /// Broadcast several transforms in a single message, e.g. a whole kinematic chain
+    ///
+    /// If any transform has invalid frame ids, nothing is sent. With a rate limit, the transforms sent too soon are
+    /// dropped from the message, and nothing is sent if none remains.
+    pub fn send_transforms(
+        &self,
+        tfs: impl IntoIterator<Item = TransformStamped>,
+    ) -> Result<(), TfError> {
+        let mut transforms: Vec<_> = tfs.into_iter().collect();
+        transforms.iter().try_for_each(validate_frame_ids)?;
+        if let Some(rate_limit) = &self.rate_limit {
+            transforms.retain(|tf| rate_limit.allow(&tf.child_frame_id));
+            if transforms.is_empty() {
+                return Ok(());
+            }
+        }
+        self.publisher.send(TFMessage { transforms })
+    }
+}
+
+/// Broadcast static tf messages on `/tf_static`, like `tf2_ros::StaticTransformBroadcaster`
+///
+/// Every message sent contains all the transforms sent so far, so that late-joining listeners receive the complete
+/// set from the latched `/tf_static` publisher rather than only the last call. Sending a transform again for the same
+/// parent and child frames replaces it.
+pub struct TfStaticBroadcaster<S = rosrust::Publisher<TFMessage>> {
+    publisher: S,
+    transforms: Mutex<Vec<TransformStamped>>,
+}
+
+impl TfStaticBroadcaster {
+    /// Create a new TfStaticBroadcaster
+    pub fn new() -> Self {
+        let mut publisher = rosrust::publish("/tf_static", 1000).unwrap();
+        publisher.set_latching(true);
+        Self::with_sink(publisher)
+    }
+}
+
+impl<S: TfSink> TfStaticBroadcaster<S> {
+    /// Create a TfStaticBroadcaster sending its messages to `sink` instead of `/tf_static`
+    pub fn with_sink(sink: S) -> Self {
+        Self {
+            publisher: sink,
+            transforms: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Broadcast a static transform, along with all the ones sent before
+    ///
+    /// Transforms with invalid frame ids are rejected like in [`TfBroadcaster::send_transform`].
+    pub fn send_transform(&self, tf: TransformStamped) -> Result<(), TfError> {
+        validate_frame_ids(&tf)?;
+        let mut transforms = self
+            .transforms
+            .lock()
+            .map_err(|_| TfError::CouldNotAcquireLock)?;
+        match transforms.iter_mut().find(|sent| {
+            sent.header.frame_id == tf.header.frame_id && sent.child_frame_id == tf.child_frame_id
+        }) {
+            Some(sent) => *sent = tf,
+            None => transforms.push(tf),
+        }
+        self.publisher.send(TFMessage {
+            transforms: transforms.clone(),
+        })
+    }
+}
+
+impl Default for TfStaticBroadcaster {
+    fn default() -> Self {
+        TfStaticBroadcaster::new()
     }
 }
 
@@ -58,3 +213,148 @@ impl Default for TfBroadcaster {
         TfBroadcaster::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink(Mutex<Vec<TFMessage>>);
+
+    impl TfSink for &RecordingSink {
+        fn send(&self, tf_message: TFMessage) -> Result<(), TfError> {
+            self.0.lock().unwrap().push(tf_message);
+            Ok(())
+        }
+    }
+
+    fn transform(parent: &str, child: &str) -> TransformStamped {
+        let mut tf = TransformStamped::default();
+        tf.header.frame_id = parent.to_string();
+        tf.child_frame_id = child.to_string();
+        tf.transform.rotation.w = 1.0;
+        tf
+    }
+
+    #[test]
+    fn test_send_transform() {
+        let sink = RecordingSink::default();
+        let broadcaster = TfBroadcaster::with_sink(&sink);
+        broadcaster
+            .send_transform(transform("map", "base_link"))
+            .unwrap();
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_send_transforms() {
+        let sink = RecordingSink::default();
+        let broadcaster = TfBroadcaster::with_sink(&sink);
+        broadcaster
+            .send_transforms([
+                transform("map", "base_link"),
+                transform("base_link", "camera"),
+            ])
+            .unwrap();
+        assert!(matches!(
+            broadcaster.send_transforms([transform("map", "odom"), transform("odom", "odom")]),
+            Err(TfError::InvalidFrameId(_))
+        ));
+        let sent = sink.0.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].transforms.len(), 2);
+    }
+
+    #[test]
+    fn test_reject_invalid_frame_ids() {
+        let sink = RecordingSink::default();
+        let broadcaster = TfBroadcaster::with_sink(&sink);
+        for tf in [
+            transform("", "base_link"),
+            transform("map", ""),
+            transform("map", "map"),
+        ] {
+            assert!(matches!(
+                broadcaster.send_transform(tf),
+                Err(TfError::InvalidFrameId(_))
+            ));
+        }
+        assert!(sink.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_static_broadcaster_accumulates() {
+        let sink = RecordingSink::default();
+        let broadcaster = TfStaticBroadcaster::with_sink(&sink);
+        broadcaster
+            .send_transform(transform("base_link", "camera"))
+            .unwrap();
+        broadcaster
+            .send_transform(transform("base_link", "lidar"))
+            .unwrap();
+        assert_eq!(broadcaster.transforms.lock().unwrap().len(), 2);
+
+        let mut updated = transform("base_link", "camera");
+        updated.transform.translation.x = 0.5;
+        broadcaster.send_transform(updated.clone()).unwrap();
+        assert!(broadcaster
+            .send_transform(transform("lidar", "lidar"))
+            .is_err());
+
+        let sent = sink.0.lock().unwrap();
+        let lengths: Vec<_> = sent
+            .iter()
+            .map(|message| message.transforms.len())
+            .collect();
+        assert_eq!(lengths, vec![1, 2, 2]);
+        assert_eq!(sent[2].transforms[0], updated);
+        assert_eq!(sent[2].transforms[1].child_frame_id, "lidar");
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let sink = RecordingSink::default();
+        let now = Arc::new(AtomicI64::new(0));
+        let clock = {
+            let now = now.clone();
+            move || rosrust::Time::from_nanos(now.load(Ordering::SeqCst))
+        };
+        let broadcaster = TfBroadcaster::with_sink(&sink).with_max_hz_and_clock(10.0, clock);
+
+        // 100 sends for each of two frames over one second, at 100 Hz.
+        for step in 0..100 {
+            now.store(step * 10_000_000, Ordering::SeqCst);
+            broadcaster
+                .send_transform(transform("map", "base_link"))
+                .unwrap();
+            broadcaster
+                .send_transform(transform("base_link", "camera"))
+                .unwrap();
+        }
+        let sent = sink.0.lock().unwrap();
+        for child in ["base_link", "camera"] {
+            let count = sent
+                .iter()
+                .filter(|message| message.transforms[0].child_frame_id == child)
+                .count();
+            assert_eq!(count, 10);
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_rejects_invalid_rates() {
+        let sink = RecordingSink::default();
+        for max_hz in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let result = std::panic::catch_unwind(|| {
+                TfBroadcaster::with_sink(&sink)
+                    .with_max_hz_and_clock(max_hz, || rosrust::Time::from_nanos(0))
+            });
+            assert!(result.is_err(), "{max_hz}");
+        }
+    }
+}
@@ -1,430 +1,4986 @@
-use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    fmt,
+    sync::{mpsc, Arc, Mutex},
+};
 
-use rosrust::Duration;
+use rosrust::{Duration, Time};
 
 use crate::{
-    tf_error::TfError,
+    tf_error::{validate_frame_ids, TfError},
     tf_graph_node::TfGraphNode,
-    tf_individual_transform_chain::TfIndividualTransformChain,
+    tf_individual_transform_chain::{
+        Bracket, Extrapolation, InterpolationMode, StorageStrategy, TfIndividualTransformChain,
+    },
     transforms::{
-        chain_transforms,
-        geometry_msgs::{Transform, TransformStamped},
-        get_inverse,
+        chain_transforms, chain_transforms_f32, enu_to_ned, extrapolate,
+        geometry_msgs::{
+            PointStamped, PoseStamped, PoseWithCovarianceStamped, Transform, TransformStamped,
+            Twist, Vector3, Vector3Stamped,
+        },
+        get_inverse, interpolate_f32, isometry_from_transform, isometry_to_transform,
+        nalgebra::{Isometry3, Quaternion as Quaternion4D, UnitQuaternion, Vector3 as Vector3D},
         std_msgs::Header,
         tf2_msgs::TFMessage,
-        to_transform_stamped,
+        to_transform_stamped, transform_point, transform_pose, transform_pose_with_covariance,
+        transform_to_matrix, Convention,
     },
+    utils::{duration_as_ns_i64, time_as_ns_i64, TimePoint},
 };
 
+/// The stored samples of one edge that contributed to a lookup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransformSource {
+    pub parent: String,
+    pub child: String,
+    /// Stamp of the sample at or before the requested time.
+    pub earlier: Time,
+    /// Stamp of the sample at or after the requested time. Equal to `earlier` when no interpolation was needed.
+    pub later: Time,
+    /// Whether the edge was received as a static transform.
+    pub is_static: bool,
+}
+
+/// Counters describing the quality of the ingested data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    /// Samples that arrived at or before the newest sample of their edge and were sorted into place.
+    pub reordered: u64,
+}
+
+/// Called with the child frame, its existing parent and its new parent. See [`MultipleParents::Warn`].
+pub type MultipleParentsCallback = Arc<dyn Fn(&str, &str, &str) + Send + Sync>;
+
+/// How a buffer handles a frame published with a new parent while it already has another one.
+///
+/// tf2 requires each frame to have a single parent. With several, lookups may go through any of them.
+#[derive(Clone, Default)]
+pub enum MultipleParents {
+    /// The new edge is added.
+    #[default]
+    Allow,
+    /// The new edge is added, and the callback is called with the child frame, the existing parent and the new parent.
+    /// It runs while the buffer is being modified, so it must not access the buffer, e.g. through a [`TfListener`].
+    ///
+    /// [`TfListener`]: crate::TfListener
+    Warn(MultipleParentsCallback),
+    /// The new edge is rejected with [`TfError::MultipleParents`].
+    Reject,
+}
+
+impl fmt::Debug for MultipleParents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow => f.write_str("Allow"),
+            Self::Warn(_) => f.write_str("Warn(..)"),
+            Self::Reject => f.write_str("Reject"),
+        }
+    }
+}
+
+/// Diagnostics of a published edge, see [`TfBuffer::frame_statistics`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameStats {
+    /// The child frame of the edge.
+    pub frame_id: String,
+    /// The parent frame of the edge.
+    pub parent: String,
+    /// Whether the edge only holds static samples.
+    pub is_static: bool,
+    /// The number of stored samples.
+    pub buffer_length: usize,
+    /// The stamp of the oldest stored sample.
+    pub oldest_stamp: Time,
+    /// The stamp of the newest stored sample.
+    pub newest_stamp: Time,
+    /// The number of sample intervals per second between the oldest and newest stamps, zero if they coincide.
+    pub average_rate_hz: f64,
+}
+
+/// Formats a stamp in seconds, with nanosecond precision.
+fn format_stamp(stamp: &Time) -> String {
+    format!("{}.{:09}", stamp.sec, stamp.nsec)
+}
+
+/// Strips the slashes around a tf prefix, treating an empty prefix as none.
+fn normalize_prefix(tf_prefix: &str) -> Option<String> {
+    let tf_prefix = tf_prefix.trim_matches('/');
+    (!tf_prefix.is_empty()).then(|| tf_prefix.to_string())
+}
+
+fn is_well_formed(transform: &TransformStamped) -> bool {
+    let rotation = &transform.transform.rotation;
+    !transform.header.frame_id.is_empty()
+        && !transform.child_frame_id.is_empty()
+        && transform.header.frame_id != transform.child_frame_id
+        && is_finite(transform)
+        && rotation.x * rotation.x
+            + rotation.y * rotation.y
+            + rotation.z * rotation.z
+            + rotation.w * rotation.w
+            > f64::EPSILON
+}
+
+fn is_finite(transform: &TransformStamped) -> bool {
+    let translation = &transform.transform.translation;
+    let rotation = &transform.transform.rotation;
+    [
+        translation.x,
+        translation.y,
+        translation.z,
+        rotation.x,
+        rotation.y,
+        rotation.z,
+        rotation.w,
+    ]
+    .iter()
+    .all(|value| value.is_finite())
+}
+
+/// Fails like [`TfBuffer::set_transform`] for invalid frame ids or non-finite values.
+fn check_values(transform: &TransformStamped) -> Result<(), TfError> {
+    validate_frame_ids(transform)?;
+    if !is_finite(transform) {
+        return Err(TfError::InvalidArgument(format!(
+            "non-finite transform from {:?} to {:?}",
+            transform.header.frame_id, transform.child_frame_id
+        )));
+    }
+    Ok(())
+}
+
+/// Fails with [`TfError::TransformTreeCycle`] if `transform` would close a loop in the tree described by `parents`,
+/// the parents of each frame.
+fn check_acyclic_in(
+    parents: &HashMap<String, HashSet<String>>,
+    transform: &TransformStamped,
+) -> Result<(), TfError> {
+    let parent = &transform.header.frame_id;
+    let child = &transform.child_frame_id;
+    let known = parents
+        .get(child)
+        .is_some_and(|parents| parents.contains(parent));
+    if !known {
+        let mut frontier = vec![parent];
+        let mut visited = HashSet::from([parent]);
+        while let Some(frame) = frontier.pop() {
+            if frame == child {
+                return Err(TfError::TransformTreeCycle(parent.clone(), child.clone()));
+            }
+            for ancestor in parents.get(frame).into_iter().flatten() {
+                if visited.insert(ancestor) {
+                    frontier.push(ancestor);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The existing parent of the child frame of `transform` in `parents`, if `transform` gives it another one.
+fn other_parent<'a>(
+    parents: &'a HashMap<String, HashSet<String>>,
+    transform: &TransformStamped,
+) -> Option<&'a String> {
+    let parents = parents.get(&transform.child_frame_id)?;
+    if parents.contains(&transform.header.frame_id) {
+        return None;
+    }
+    parents.iter().min()
+}
+
 #[derive(Clone, Debug)]
 pub struct TfBuffer {
     child_transform_index: HashMap<String, HashSet<String>>,
+    /// Parents of each frame, to traverse edges against their published direction.
+    parent_transform_index: HashMap<String, HashSet<String>>,
+    /// The samples of each edge, in its published direction only. Inverses are computed during lookups.
     transform_data: HashMap<TfGraphNode, TfIndividualTransformChain>,
     cache_duration: Duration,
+    storage: StorageStrategy,
+    past_lookups: PastLookupRecord,
+    slashed_frames: SlashedFrames,
+    interpolation_memo: Option<InterpolationMemo>,
+    path_memo: Option<PathMemo>,
+    gravity: Option<(String, Vector3)>,
+    frame_conventions: HashMap<String, Convention>,
+    edge_metadata: HashMap<TfGraphNode, HashMap<String, String>>,
+    max_path_length: Option<usize>,
+    retain_all_static: bool,
+    past_tolerance: Duration,
+    future_tolerance: Duration,
+    extrapolation: Extrapolation,
+    min_quaternion_norm: f64,
+    time_jump_threshold: Option<Duration>,
+    multiple_parents: MultipleParents,
+    interpolation_mode: InterpolationMode,
+    /// Interpolation modes of single edges, overriding `interpolation_mode`.
+    edge_interpolation_modes: HashMap<TfGraphNode, InterpolationMode>,
+    tf_prefix: Option<String>,
+    ingest_stats: IngestStats,
+    topology_revision: u64,
+    topology_watchers: TopologyWatchers,
+    #[cfg(feature = "metrics")]
+    used_frames: UsedFrameRecord,
 }
 
 const DEFAULT_CACHE_DURATION_SECONDS: i32 = 10;
+const MAX_RECORDED_PAST_LOOKUPS: usize = 1000;
+const AVERAGED_LOOKUP_SAMPLES: i64 = 11;
+#[cfg(feature = "metrics")]
+const MAX_RECORDED_USED_FRAMES: usize = 64;
 
-impl TfBuffer {
-    pub(crate) fn new() -> Self {
-        Self::new_with_duration(Duration::from_seconds(DEFAULT_CACHE_DURATION_SECONDS))
+/// Senders notified of topology changes. A cloned buffer evolves on its own, so clones start without watchers.
+#[derive(Debug, Default)]
+struct TopologyWatchers(Vec<mpsc::Sender<u64>>);
+
+impl TopologyWatchers {
+    fn notify(&mut self, revision: u64) {
+        self.0.retain(|sender| sender.send(revision).is_ok());
+    }
+}
+
+impl Clone for TopologyWatchers {
+    fn clone(&self) -> Self {
+        Self::default()
     }
+}
 
-    pub fn new_with_duration(cache_duration: Duration) -> Self {
-        TfBuffer {
-            child_transform_index: HashMap::new(),
-            transform_data: HashMap::new(),
-            cache_duration,
+/// Records how far before the oldest cached sample failed lookups asked for.
+///
+/// Lookups only borrow the buffer immutably, so the record lives behind a lock.
+#[derive(Default)]
+struct PastLookupRecord {
+    shortfalls: Mutex<VecDeque<Duration>>,
+}
+
+impl PastLookupRecord {
+    fn record(&self, shortfall: Duration) {
+        let Ok(mut shortfalls) = self.shortfalls.lock() else {
+            return;
+        };
+        if shortfalls.len() == MAX_RECORDED_PAST_LOOKUPS {
+            shortfalls.pop_front();
         }
+        shortfalls.push_back(shortfall);
     }
 
-    pub(crate) fn handle_incoming_transforms(&mut self, transforms: TFMessage, static_tf: bool) {
-        for transform in transforms.transforms {
-            self.add_transform(&transform, static_tf);
-            self.add_transform(&get_inverse(&transform), static_tf);
+    fn snapshot(&self) -> VecDeque<Duration> {
+        self.shortfalls
+            .lock()
+            .map(|shortfalls| shortfalls.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Clone for PastLookupRecord {
+    fn clone(&self) -> Self {
+        Self {
+            shortfalls: Mutex::new(self.snapshot()),
         }
     }
+}
 
-    fn add_transform(&mut self, transform: &TransformStamped, static_tf: bool) {
-        //TODO: Detect is new transform will create a loop
-        self.child_transform_index
-            .entry(transform.header.frame_id.clone())
-            .or_default()
-            .insert(transform.child_frame_id.clone());
+impl fmt::Debug for PastLookupRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PastLookupRecord")
+            .field("shortfalls", &self.snapshot().len())
+            .finish()
+    }
+}
 
-        let key = TfGraphNode {
-            child: transform.child_frame_id.clone(),
-            parent: transform.header.frame_id.clone(),
+/// The frame ids seen with a leading slash, so that each is only warned about once.
+///
+/// Lookups only borrow the buffer immutably, so the record lives behind a lock.
+#[derive(Default)]
+struct SlashedFrames {
+    frames: Mutex<HashSet<String>>,
+}
+
+impl SlashedFrames {
+    /// `frame` without its leading slash, warning the first time a frame is seen with one.
+    fn strip<'a>(&self, frame: &'a str) -> &'a str {
+        let Some(stripped) = frame
+            .strip_prefix('/')
+            .filter(|stripped| !stripped.is_empty())
+        else {
+            return frame;
         };
+        let first = self
+            .frames
+            .lock()
+            .is_ok_and(|mut frames| frames.insert(stripped.to_string()));
+        if first && rosrust::is_initialized() {
+            rosrust::ros_warn!("tf_rosrust: ignoring the leading slash of frame id {frame:?}");
+        }
+        stripped
+    }
 
-        match self.transform_data.entry(key) {
-            Entry::Occupied(e) => e.into_mut(),
-            Entry::Vacant(e) => e.insert(TfIndividualTransformChain::new(
-                static_tf,
-                self.cache_duration,
-            )),
+    fn snapshot(&self) -> HashSet<String> {
+        self.frames
+            .lock()
+            .map(|frames| frames.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Clone for SlashedFrames {
+    fn clone(&self) -> Self {
+        Self {
+            frames: Mutex::new(self.snapshot()),
         }
-        .add_to_buffer(transform.clone());
     }
+}
 
-    /// Retrieves the transform path
-    fn retrieve_transform_path(
-        &self,
-        from: String,
-        to: String,
-        time: rosrust::Time,
-    ) -> Result<Vec<String>, TfError> {
-        let mut res = vec![];
-        let mut frontier: VecDeque<String> = VecDeque::new();
-        let mut visited: HashSet<String> = HashSet::new();
-        let mut parents: HashMap<String, String> = HashMap::new();
-        visited.insert(from.clone());
-        frontier.push_front(from.clone());
+impl fmt::Debug for SlashedFrames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlashedFrames")
+            .field("frames", &self.snapshot().len())
+            .finish()
+    }
+}
 
-        while !frontier.is_empty() {
-            let current_node = frontier.pop_front().unwrap();
-            if current_node == to {
-                break;
-            }
-            if let Some(children) = self.child_transform_index.get(&current_node) {
-                for v in children {
-                    if visited.contains(v) {
-                        continue;
-                    }
+/// Records the frames recent lookups went through, most recently used first.
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+struct UsedFrameRecord {
+    frames: Mutex<VecDeque<String>>,
+}
 
-                    if self
-                        .transform_data
-                        .get(&TfGraphNode {
-                            child: v.clone(),
-                            parent: current_node.clone(),
-                        })
-                        .map_or(false, |chain| chain.has_valid_transform(time))
-                    {
-                        parents.insert(v.to_string(), current_node.clone());
-                        frontier.push_front(v.to_string());
-                        visited.insert(v.to_string());
-                    }
-                }
+#[cfg(feature = "metrics")]
+impl UsedFrameRecord {
+    fn record<'a>(&self, used: impl IntoIterator<Item = &'a str>) {
+        let Ok(mut frames) = self.frames.lock() else {
+            return;
+        };
+        for frame in used {
+            if let Some(index) = frames.iter().position(|known| known == frame) {
+                frames.remove(index);
             }
+            frames.push_front(frame.to_string());
         }
-        let mut r = to.clone();
-        while r != from {
-            res.push(r.clone());
-            let parent = parents.get(&r);
+        frames.truncate(MAX_RECORDED_USED_FRAMES);
+    }
 
-            match parent {
-                Some(x) => r = x.to_string(),
-                None => {
-                    return Err(TfError::CouldNotFindTransform(
-                        from,
-                        to,
-                        self.child_transform_index.clone(),
-                    ))
-                }
-            }
+    fn snapshot(&self) -> Vec<String> {
+        self.frames
+            .lock()
+            .map(|frames| frames.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Clone for UsedFrameRecord {
+    fn clone(&self) -> Self {
+        Self {
+            frames: Mutex::new(self.snapshot().into()),
         }
-        res.reverse();
-        Ok(res)
     }
+}
 
-    /// Looks up a transform within the tree at a given time.
-    pub fn lookup_transform(
+#[cfg(feature = "metrics")]
+impl fmt::Debug for UsedFrameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.snapshot()).finish()
+    }
+}
+
+type EdgeSample = (Transform, (Time, Time));
+
+/// Transforms of the edges looked up at the latest lookup time, with the stamps they were computed from.
+///
+/// Lookups only borrow the buffer immutably, so the memo lives behind a lock.
+#[derive(Default)]
+struct InterpolationMemo {
+    entries: Mutex<(Time, HashMap<TfGraphNode, EdgeSample>)>,
+}
+
+impl InterpolationMemo {
+    fn get_or_try_insert_with(
         &self,
-        from: &str,
-        to: &str,
-        time: rosrust::Time,
-    ) -> Result<TransformStamped, TfError> {
-        let from = from.to_string();
-        let to = to.to_string();
-        let path = self.retrieve_transform_path(from.clone(), to.clone(), time);
-
-        match path {
-            Ok(path) => {
-                let mut tf_list: Vec<Transform> = Vec::new();
-                let mut first = from.clone();
-                for intermediate in path {
-                    let node = TfGraphNode {
-                        child: intermediate.clone(),
-                        parent: first.clone(),
-                    };
-                    let time_cache = self.transform_data.get(&node).unwrap();
-                    let transform = time_cache.get_closest_transform(time);
-                    match transform {
-                        Err(e) => return Err(e),
-                        Ok(x) => {
-                            tf_list.push(x.transform);
-                        }
-                    }
-                    first = intermediate.clone();
-                }
-                let final_tf = chain_transforms(&tf_list);
-                let msg = TransformStamped {
-                    child_frame_id: to,
-                    header: Header {
-                        frame_id: from,
-                        stamp: time,
-                        seq: 1,
-                    },
-                    transform: final_tf,
-                };
-                Ok(msg)
-            }
-            Err(x) => Err(x),
+        node: &TfGraphNode,
+        time: Time,
+        compute: impl FnOnce() -> Result<EdgeSample, TfError>,
+    ) -> Result<EdgeSample, TfError> {
+        let Ok(mut entries) = self.entries.lock() else {
+            return compute();
+        };
+        let (memo_time, samples) = &mut *entries;
+        if *memo_time != time {
+            *memo_time = time;
+            samples.clear();
+        }
+        if let Some(sample) = samples.get(node) {
+            return Ok(sample.clone());
         }
+        let sample = compute()?;
+        samples.insert(node.clone(), sample.clone());
+        Ok(sample)
     }
 
-    pub(crate) fn lookup_transform_with_time_travel(
-        &self,
-        to: &str,
-        time2: rosrust::Time,
-        from: &str,
-        time1: rosrust::Time,
-        fixed_frame: &str,
-    ) -> Result<TransformStamped, TfError> {
-        let tf1 = self.lookup_transform(from, fixed_frame, time1)?;
-        let tf2 = self.lookup_transform(to, fixed_frame, time2)?;
-        let transforms = get_inverse(&tf1);
-        let result = chain_transforms(&[tf2.transform, transforms.transform]);
-        Ok(to_transform_stamped(
-            result,
-            from.to_string(),
-            to.to_string(),
-            time1,
-        ))
+    fn clear(&mut self) {
+        if let Ok(entries) = self.entries.get_mut() {
+            entries.1.clear();
+        }
     }
 }
 
-#[cfg(test)]
-mod test {
-    use rosrust::Time;
+impl Clone for InterpolationMemo {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
 
-    use super::*;
-    use crate::transforms::geometry_msgs::{Quaternion, Vector3};
+impl fmt::Debug for InterpolationMemo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self
+            .entries
+            .lock()
+            .map(|entries| entries.1.len())
+            .unwrap_or_default();
+        f.debug_struct("InterpolationMemo")
+            .field("entries", &len)
+            .finish()
+    }
+}
 
-    const PARENT: &str = "parent";
-    const CHILD0: &str = "child0";
-    const CHILD1: &str = "child1";
+/// The path last used between each pair of frames, kept while it stays valid.
+///
+/// Lookups only borrow the buffer immutably, so the memo lives behind a lock.
+#[derive(Default)]
+struct PathMemo {
+    paths: Mutex<HashMap<(String, String), Vec<String>>>,
+}
 
-    /// This function builds a tree consisting of the following items:
-    /// * a world coordinate frame
-    /// * an item in the world frame at (1,0,0)
-    /// * base_link of a robot starting at (0,0,0) and progressing at (0,t,0) where t is time in seconds
-    /// * a camera which is (0.5, 0, 0) from the base_link
-    fn build_test_tree(buffer: &mut TfBuffer, time: f64) {
-        let nsecs = ((time - ((time.floor() as i64) as f64)) * 1E9) as u32;
+impl Clone for PathMemo {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
 
-        let world_to_item = TransformStamped {
-            child_frame_id: "item".to_string(),
-            header: Header {
-                frame_id: "world".to_string(),
-                stamp: rosrust::Time {
-                    sec: time.floor() as u32,
-                    nsec: nsecs,
-                },
-                seq: 1,
-            },
-            transform: Transform {
-                rotation: Quaternion {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64,
-                    w: 1f64,
-                },
-                translation: Vector3 {
-                    x: 1f64,
-                    y: 0f64,
-                    z: 0f64,
-                },
-            },
-        };
-        buffer.add_transform(&world_to_item, true);
-        buffer.add_transform(&get_inverse(&world_to_item), true);
+impl fmt::Debug for PathMemo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self
+            .paths
+            .lock()
+            .map(|paths| paths.len())
+            .unwrap_or_default();
+        f.debug_struct("PathMemo").field("paths", &len).finish()
+    }
+}
 
-        let world_to_base_link = TransformStamped {
-            child_frame_id: "base_link".to_string(),
-            header: Header {
-                frame_id: "world".to_string(),
-                stamp: rosrust::Time {
-                    sec: time.floor() as u32,
-                    nsec: nsecs,
-                },
-                seq: 1,
-            },
-            transform: Transform {
-                rotation: Quaternion {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64,
-                    w: 1f64,
-                },
-                translation: Vector3 {
-                    x: 0f64,
-                    y: time,
-                    z: 0f64,
-                },
-            },
-        };
-        buffer.add_transform(&world_to_base_link, false);
-        buffer.add_transform(&get_inverse(&world_to_base_link), false);
+/// The outcome of exploring the transform graph from one frame.
+#[derive(Default)]
+struct GraphSearch {
+    /// The frame each reached frame was reached from.
+    parents: HashMap<String, String>,
+    /// How far before the oldest sample of a rejected edge the requested time was, if so.
+    shortfall: Option<Duration>,
+    /// Whether frames were left out because of the maximum path length.
+    truncated: bool,
+}
 
-        let base_link_to_camera = TransformStamped {
-            child_frame_id: "camera".to_string(),
-            header: Header {
-                frame_id: "base_link".to_string(),
-                stamp: rosrust::Time {
-                    sec: time.floor() as u32,
-                    nsec: nsecs,
-                },
-                seq: 1,
-            },
-            transform: Transform {
-                rotation: Quaternion {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64,
-                    w: 1f64,
-                },
-                translation: Vector3 {
-                    x: 0.5f64,
-                    y: 0f64,
-                    z: 0f64,
-                },
-            },
-        };
-        buffer.add_transform(&base_link_to_camera, true);
-        buffer.add_transform(&get_inverse(&base_link_to_camera), true);
+/// Builds a [`TfBuffer`] with non-default settings.
+///
+/// ```
+/// use tf_rosrust::{StorageStrategy, TfBuffer};
+///
+/// let buffer = TfBuffer::builder()
+///     .cache_duration(rosrust::Duration::from_seconds(30))
+///     .storage(StorageStrategy::ReadOptimized)
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct TfBufferBuilder {
+    cache_duration: Duration,
+    storage: StorageStrategy,
+    max_path_length: Option<usize>,
+    retain_all_static: bool,
+    past_tolerance: Duration,
+    future_tolerance: Duration,
+    extrapolation: Extrapolation,
+    min_quaternion_norm: f64,
+    time_jump_threshold: Option<Duration>,
+    multiple_parents: MultipleParents,
+    interpolation_mode: InterpolationMode,
+    interpolation_memo: bool,
+    path_hysteresis: bool,
+    gravity: Option<(String, Vector3)>,
+    tf_prefix: Option<String>,
+}
+
+impl TfBufferBuilder {
+    /// How long samples of dynamic transforms are kept.
+    pub fn cache_duration(mut self, cache_duration: Duration) -> Self {
+        self.cache_duration = cache_duration;
+        self
+    }
+
+    /// How the samples of each edge are stored. See [`StorageStrategy`] for the trade-offs.
+    pub fn storage(mut self, storage: StorageStrategy) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Limits lookups to paths of at most this many edges, bounding the cost of a search on a misconfigured graph.
+    /// Unbounded by default.
+    pub fn max_path_length(mut self, max_path_length: usize) -> Self {
+        self.max_path_length = Some(max_path_length);
+        self
+    }
+
+    /// Keeps every sample received on `/tf_static` instead of only the latest per edge.
+    ///
+    /// Like tf2, a static edge is latest-wins by default. Retaining all samples is useful when relaying several
+    /// static publishers with different stamps.
+    pub fn retain_all_static(mut self, retain_all_static: bool) -> Self {
+        self.retain_all_static = retain_all_static;
+        self
+    }
+
+    /// Answers lookups up to this long before the oldest sample of an edge with that sample, instead of failing with
+    /// [`TfError::AttemptedLookupInPast`]. Zero by default.
+    pub fn past_tolerance(mut self, past_tolerance: Duration) -> Self {
+        self.past_tolerance = past_tolerance;
+        self
+    }
+
+    /// Answers lookups up to this long after the newest sample of an edge with that sample, instead of failing with
+    /// [`TfError::AttemptedLookUpInFuture`]. Zero by default.
+    pub fn future_tolerance(mut self, future_tolerance: Duration) -> Self {
+        self.future_tolerance = future_tolerance;
+        self
+    }
+
+    /// Whether lookups past the newest sample of a dynamic edge extrapolate its latest motion. See [`Extrapolation`].
+    /// [`Extrapolation::None`] by default.
+    pub fn extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    /// Rejects transforms whose quaternion has a norm below this with [`TfError::InvalidQuaternion`], instead of
+    /// normalizing it. Such a rotation is mostly noise. The square root of `f64::EPSILON` by default, which only rejects
+    /// zero quaternions.
+    pub fn min_quaternion_norm(mut self, min_quaternion_norm: f64) -> Self {
+        self.min_quaternion_norm = min_quaternion_norm;
+        self
+    }
+
+    /// Clears the buffer when a dynamic transform arrives more than `threshold` before the newest sample of its edge.
+    ///
+    /// Such a jump back in time happens when a simulation or bag playback is restarted, and would otherwise leave the
+    /// buffer full of samples from the future. Off by default, so that late samples are only dropped.
+    pub fn clear_on_time_jump(mut self, threshold: Duration) -> Self {
+        self.time_jump_threshold = Some(threshold);
+        self
+    }
+
+    /// How lookups between two samples of an edge are answered. See [`InterpolationMode`].
+    /// [`InterpolationMode::Linear`] by default. Single edges can be overridden with
+    /// [`TfBuffer::set_interpolation_mode`].
+    pub fn interpolation_mode(mut self, interpolation_mode: InterpolationMode) -> Self {
+        self.interpolation_mode = interpolation_mode;
+        self
+    }
+
+    /// What happens when a frame is published with a second parent. See [`MultipleParents`].
+    /// [`MultipleParents::Allow`] by default, since frames may be re-parented over time.
+    pub fn multiple_parents(mut self, multiple_parents: MultipleParents) -> Self {
+        self.multiple_parents = multiple_parents;
+        self
+    }
+
+    /// Remembers the transform of each edge at the latest lookup time, so that lookups sharing a timestamp and
+    /// edges, e.g. within one control cycle, only interpolate each edge once.
+    ///
+    /// The memo is cleared whenever the lookup time changes or the buffer is modified. Off by default, since
+    /// it costs a lock and a hash per edge on every lookup.
+    pub fn interpolation_memo(mut self, interpolation_memo: bool) -> Self {
+        self.interpolation_memo = interpolation_memo;
+        self
+    }
+
+    /// Keeps using the path found by a previous lookup between the same frames for as long as all of its edges stay
+    /// valid, instead of searching again.
+    ///
+    /// When a frame is re-parented and the old and new parents overlap in time, lookups around the switchover could
+    /// otherwise alternate between both paths, making the result jitter. The price is freshness: the new path is only
+    /// taken once the old one stops covering the lookup time, i.e. up to a cache duration after the switchover. Off
+    /// by default.
+    pub fn path_hysteresis(mut self, path_hysteresis: bool) -> Self {
+        self.path_hysteresis = path_hysteresis;
+        self
+    }
+
+    /// The gravity vector subtracted by [`TfBuffer::transform_accel`], expressed in `frame_id`.
+    ///
+    /// This is what a stationary accelerometer aligned with `frame_id` reports, e.g. `(0, 0, 9.81)` for an ENU frame.
+    pub fn gravity(mut self, frame_id: &str, gravity: Vector3) -> Self {
+        self.gravity = Some((frame_id.to_string(), gravity));
+        self
+    }
+
+    /// Prefixes the frame ids of received transforms, e.g. `robot1` to keep several robots sharing `/tf` apart. See
+    /// [`TfBuffer::set_tf_prefix`].
+    pub fn tf_prefix(mut self, tf_prefix: &str) -> Self {
+        self.tf_prefix = normalize_prefix(tf_prefix);
+        self
+    }
+
+    pub fn build(self) -> TfBuffer {
+        TfBuffer {
+            child_transform_index: HashMap::new(),
+            parent_transform_index: HashMap::new(),
+            transform_data: HashMap::new(),
+            cache_duration: self.cache_duration,
+            storage: self.storage,
+            past_lookups: PastLookupRecord::default(),
+            slashed_frames: SlashedFrames::default(),
+            interpolation_memo: self.interpolation_memo.then(InterpolationMemo::default),
+            path_memo: self.path_hysteresis.then(PathMemo::default),
+            gravity: self.gravity,
+            frame_conventions: HashMap::new(),
+            edge_metadata: HashMap::new(),
+            max_path_length: self.max_path_length,
+            retain_all_static: self.retain_all_static,
+            past_tolerance: self.past_tolerance,
+            future_tolerance: self.future_tolerance,
+            extrapolation: self.extrapolation,
+            min_quaternion_norm: self.min_quaternion_norm,
+            time_jump_threshold: self.time_jump_threshold,
+            multiple_parents: self.multiple_parents,
+            interpolation_mode: self.interpolation_mode,
+            edge_interpolation_modes: HashMap::new(),
+            tf_prefix: self.tf_prefix,
+            ingest_stats: IngestStats::default(),
+            topology_revision: 0,
+            topology_watchers: TopologyWatchers::default(),
+            #[cfg(feature = "metrics")]
+            used_frames: UsedFrameRecord::default(),
+        }
+    }
+}
+
+impl Default for TfBufferBuilder {
+    fn default() -> Self {
+        Self {
+            cache_duration: Duration::from_seconds(DEFAULT_CACHE_DURATION_SECONDS),
+            storage: StorageStrategy::default(),
+            max_path_length: None,
+            retain_all_static: false,
+            past_tolerance: Duration::new(),
+            future_tolerance: Duration::new(),
+            extrapolation: Extrapolation::None,
+            min_quaternion_norm: f64::EPSILON.sqrt(),
+            time_jump_threshold: None,
+            multiple_parents: MultipleParents::Allow,
+            interpolation_mode: InterpolationMode::Linear,
+            interpolation_memo: false,
+            path_hysteresis: false,
+            gravity: None,
+            tf_prefix: None,
+        }
+    }
+}
+
+impl TfBuffer {
+    pub(crate) fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn new_with_duration(cache_duration: Duration) -> Self {
+        Self::builder().cache_duration(cache_duration).build()
+    }
+
+    pub fn builder() -> TfBufferBuilder {
+        TfBufferBuilder::default()
+    }
+
+    /// Resolves the `from` -> `to` transform from a set of edges in one shot, without keeping a buffer around.
+    ///
+    /// Every edge is treated as static and timestamps are ignored. If the same edge is given more than
+    /// once, the last one wins.
+    pub fn compute_chain(
+        transforms: &[TransformStamped],
+        from: &str,
+        to: &str,
+    ) -> Result<TransformStamped, TfError> {
+        let mut edges = HashMap::new();
+        for transform in transforms {
+            let key = (
+                transform.header.frame_id.as_str(),
+                transform.child_frame_id.as_str(),
+            );
+            let mut transform = transform.clone();
+            transform.header.stamp = rosrust::Time::new();
+            edges.insert(key, transform);
+        }
+
+        let mut buffer = Self::new();
+        buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: edges.into_values().collect(),
+            },
+            true,
+        );
+        buffer.lookup_transform(from, to, rosrust::Time::new())
+    }
+
+    /// Inserts every transform of a `tf2_msgs/TFMessage`, as received on `/tf` or `/tf_static`.
+    ///
+    /// Malformed transforms (empty frame ids, a frame parented to itself, non-finite values or a zero quaternion) are
+    /// skipped, since they would otherwise corrupt every lookup going through them. So are transforms that would close
+    /// a loop in the tree, and those giving a frame a second parent if [`MultipleParents::Reject`] is configured.
+    /// Quaternions are normalized before being stored.
+    pub fn handle_incoming_transforms(&mut self, transforms: TFMessage, static_tf: bool) {
+        for transform in transforms.transforms {
+            if !is_well_formed(&transform) {
+                continue;
+            }
+            let transform = self.with_prefix(transform);
+            if !static_tf && self.is_time_jump(&transform) {
+                self.clear();
+            }
+            if let Ok(true) = self.add_published_transform(&transform, static_tf) {
+                self.ingest_stats.reordered += 1;
+            }
+        }
+    }
+
+    /// Prefixes the frame ids of the transforms received through [`TfBuffer::handle_incoming_transforms`] from now on,
+    /// or stops doing so with `None`.
+    ///
+    /// With the prefix `robot1`, a transform from `map` to `base_link` is stored from `robot1/map` to
+    /// `robot1/base_link`, and must be looked up under these names. A leading slash of the received frame ids is
+    /// dropped, so `/base_link` also becomes `robot1/base_link`. Slashes around the prefix are ignored. Transforms
+    /// inserted by other means, e.g. [`TfBuffer::set_transform`], are stored as given.
+    pub fn set_tf_prefix(&mut self, tf_prefix: Option<&str>) {
+        self.tf_prefix = tf_prefix.and_then(normalize_prefix);
+    }
+
+    /// The frame id `frame` is stored under when received, see [`TfBuffer::set_tf_prefix`].
+    pub(crate) fn prefixed_frame(&self, frame: &str) -> String {
+        match &self.tf_prefix {
+            Some(prefix) => format!("{prefix}/{}", self.slashed_frames.strip(frame)),
+            None => self.slashed_frames.strip(frame).to_string(),
+        }
+    }
+
+    fn with_prefix(&self, mut transform: TransformStamped) -> TransformStamped {
+        if self.tf_prefix.is_some() {
+            transform.header.frame_id = self.prefixed_frame(&transform.header.frame_id);
+            transform.child_frame_id = self.prefixed_frame(&transform.child_frame_id);
+        }
+        transform
+    }
+
+    /// Inserts a single transform, e.g. read from a calibration file, as if it had been received from `authority`.
+    ///
+    /// The transform is rejected with [`TfError::InvalidFrameId`] if a frame id is empty or it relates a frame to
+    /// itself, with [`TfError::InvalidArgument`] if it holds non-finite values, with [`TfError::InvalidQuaternion`] if
+    /// its quaternion cannot be normalized, with [`TfError::TransformTreeCycle`] if it would close a loop and with
+    /// [`TfError::MultipleParents`] if configured to reject a second parent. The authority is stored as the `authority`
+    /// metadata of the edge, see [`TfBuffer::edge_metadata`].
+    pub fn set_transform(
+        &mut self,
+        transform: &TransformStamped,
+        authority: &str,
+        is_static: bool,
+    ) -> Result<(), TfError> {
+        check_values(transform)?;
+        if self.add_published_transform(transform, is_static)? {
+            self.ingest_stats.reordered += 1;
+        }
+        self.set_edge_metadata(
+            self.slashed_frames.strip(&transform.header.frame_id),
+            self.slashed_frames.strip(&transform.child_frame_id),
+            "authority",
+            authority,
+        );
+        Ok(())
+    }
+
+    /// Inserts the static transforms listed in `yaml`, e.g. a calibration file, and returns how many were loaded.
+    ///
+    /// The document is a list of `parent`, `child`, `translation: [x, y, z]` and either `rotation: [x, y, z, w]` or
+    /// `rpy: [roll, pitch, yaw]` entries, as written by [`TfBuffer::static_tree_to_yaml`]. Entries are inserted with
+    /// [`TfBuffer::set_transform`] under the `static_yaml` authority. If an entry is malformed, or would be rejected by
+    /// [`TfBuffer::set_transform`] once the entries before it are inserted, the error is returned and nothing is.
+    #[cfg(feature = "yaml")]
+    pub fn load_static_yaml(&mut self, yaml: &str) -> Result<usize, TfError> {
+        let transforms = crate::static_yaml::parse(yaml)?;
+        let mut parents = self.parent_transform_index.clone();
+        for transform in &transforms {
+            check_values(transform)?;
+            let transform = self.normalized(transform)?;
+            check_acyclic_in(&parents, &transform)?;
+            if let (MultipleParents::Reject, Some(existing)) =
+                (&self.multiple_parents, other_parent(&parents, &transform))
+            {
+                return Err(TfError::MultipleParents(
+                    transform.child_frame_id.clone(),
+                    existing.clone(),
+                    transform.header.frame_id.clone(),
+                ));
+            }
+            parents
+                .entry(transform.child_frame_id)
+                .or_default()
+                .insert(transform.header.frame_id);
+        }
+        for transform in &transforms {
+            self.set_transform(transform, "static_yaml", true)?;
+        }
+        Ok(transforms.len())
+    }
+
+    /// Writes the latest sample of every static edge in the format read by [`TfBuffer::load_static_yaml`].
+    #[cfg(feature = "yaml")]
+    pub fn static_tree_to_yaml(&self) -> String {
+        crate::static_yaml::write(&self.to_tf_message(true).transforms)
+    }
+
+    /// Removes every transform, e.g. after a simulation reset made the stored stamps meaningless.
+    ///
+    /// The configuration, such as the cache duration, and the metadata of edges are kept.
+    pub fn clear(&mut self) {
+        self.child_transform_index.clear();
+        self.parent_transform_index.clear();
+        self.transform_data.clear();
+        if let Some(memo) = &mut self.interpolation_memo {
+            memo.clear();
+        }
+        if let Some(memo) = &mut self.path_memo {
+            if let Ok(paths) = memo.paths.get_mut() {
+                paths.clear();
+            }
+        }
+    }
+
+    /// Whether `transform` is further before the newest sample of its edge than
+    /// [`TfBufferBuilder::clear_on_time_jump`] allows.
+    ///
+    /// The edge is found under the frame ids `transform` is stored with, i.e. without a leading slash.
+    fn is_time_jump(&self, transform: &TransformStamped) -> bool {
+        let Some(threshold) = self.time_jump_threshold else {
+            return false;
+        };
+        self.transform_data
+            .get(&TfGraphNode {
+                child: self
+                    .slashed_frames
+                    .strip(&transform.child_frame_id)
+                    .to_string(),
+                parent: self
+                    .slashed_frames
+                    .strip(&transform.header.frame_id)
+                    .to_string(),
+            })
+            .and_then(|chain| chain.dynamic_range())
+            .is_some_and(|(_, newest)| transform.header.stamp + threshold < newest)
+    }
+
+    /// Adds a transform as published.
+    ///
+    /// Returns whether the transform arrived out of order on its edge, or [`TfError::TransformTreeCycle`] if it would
+    /// close a loop.
+    fn add_published_transform(
+        &mut self,
+        transform: &TransformStamped,
+        static_tf: bool,
+    ) -> Result<bool, TfError> {
+        let transform = self.normalized(transform)?;
+        self.check_acyclic(&transform)?;
+        self.check_single_parent(&transform)?;
+        Ok(self.add_transform(&transform, static_tf))
+    }
+
+    /// A copy of `transform` with a unit quaternion, or [`TfError::InvalidQuaternion`] if its norm is below
+    /// [`TfBufferBuilder::min_quaternion_norm`].
+    ///
+    /// Slightly un-normalized quaternions are common over the wire, and would otherwise scale every lookup result.
+    /// A leading slash of either frame id, as used by ROS 1 tf, is dropped like tf2 does, so that `/base_link` and
+    /// `base_link` are the same frame.
+    fn normalized(&self, transform: &TransformStamped) -> Result<TransformStamped, TfError> {
+        let rotation = &transform.transform.rotation;
+        let norm = (rotation.x * rotation.x
+            + rotation.y * rotation.y
+            + rotation.z * rotation.z
+            + rotation.w * rotation.w)
+            .sqrt();
+        if norm.is_nan() || norm < self.min_quaternion_norm {
+            return Err(TfError::InvalidQuaternion(
+                transform.child_frame_id.clone(),
+                norm,
+            ));
+        }
+        let mut normalized = transform.clone();
+        normalized.header.frame_id = self
+            .slashed_frames
+            .strip(&transform.header.frame_id)
+            .to_string();
+        normalized.child_frame_id = self
+            .slashed_frames
+            .strip(&transform.child_frame_id)
+            .to_string();
+        let rotation = &mut normalized.transform.rotation;
+        rotation.x /= norm;
+        rotation.y /= norm;
+        rotation.z /= norm;
+        rotation.w /= norm;
+        Ok(normalized)
+    }
+
+    /// Fails with [`TfError::TransformTreeCycle`] if `transform` would close a loop, i.e. its child frame is already an
+    /// ancestor of its parent frame.
+    fn check_acyclic(&self, transform: &TransformStamped) -> Result<(), TfError> {
+        check_acyclic_in(&self.parent_transform_index, transform)
+    }
+
+    /// Applies the [`MultipleParents`] policy if `transform` gives its child frame a new parent.
+    fn check_single_parent(&self, transform: &TransformStamped) -> Result<(), TfError> {
+        let parent = &transform.header.frame_id;
+        let child = &transform.child_frame_id;
+        let Some(existing) = other_parent(&self.parent_transform_index, transform) else {
+            return Ok(());
+        };
+        match &self.multiple_parents {
+            MultipleParents::Allow => Ok(()),
+            MultipleParents::Warn(callback) => {
+                callback(child, existing, parent);
+                Ok(())
+            }
+            MultipleParents::Reject => Err(TfError::MultipleParents(
+                child.clone(),
+                existing.clone(),
+                parent.clone(),
+            )),
+        }
+    }
+
+    /// Returns whether the transform arrived out of order on its edge.
+    fn add_transform(&mut self, transform: &TransformStamped, static_tf: bool) -> bool {
+        let retain_all_static = self.retain_all_static;
+        let chain = self.edge_mut(transform, static_tf);
+        if static_tf {
+            chain.add_static(transform.clone(), retain_all_static)
+        } else {
+            chain.add_dynamic(transform.clone())
+        }
+    }
+
+    /// The edge `transform` belongs to, created if new.
+    fn edge_mut(
+        &mut self,
+        transform: &TransformStamped,
+        static_tf: bool,
+    ) -> &mut TfIndividualTransformChain {
+        if let Some(memo) = &mut self.interpolation_memo {
+            memo.clear();
+        }
+        self.child_transform_index
+            .entry(transform.header.frame_id.clone())
+            .or_default()
+            .insert(transform.child_frame_id.clone());
+        self.parent_transform_index
+            .entry(transform.child_frame_id.clone())
+            .or_default()
+            .insert(transform.header.frame_id.clone());
+
+        let key = TfGraphNode {
+            child: transform.child_frame_id.clone(),
+            parent: transform.header.frame_id.clone(),
+        };
+
+        let interpolation_mode = self
+            .edge_interpolation_modes
+            .get(&key)
+            .copied()
+            .unwrap_or(self.interpolation_mode);
+        match self.transform_data.entry(key) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                self.topology_revision += 1;
+                self.topology_watchers.notify(self.topology_revision);
+                let mut chain = TfIndividualTransformChain::new(
+                    transform.child_frame_id.clone(),
+                    static_tf,
+                    self.cache_duration,
+                    self.storage,
+                );
+                chain.set_tolerances(self.past_tolerance, self.future_tolerance);
+                chain.set_extrapolation(self.extrapolation);
+                chain.set_interpolation_mode(interpolation_mode);
+                e.insert(chain)
+            }
+        }
+    }
+
+    /// Inserts transforms sorted by stamp, each with whether it is static, as when replaying a recording.
+    ///
+    /// In-order dynamic samples are appended to their edge and pruning happens once at the end, which is cheaper than
+    /// inserting them one by one. Out-of-order samples are still sorted into place and counted in
+    /// [`TfBuffer::ingest_stats`]. Malformed transforms, transforms closing a loop and second parents rejected by
+    /// [`TfBufferBuilder::multiple_parents`] are skipped like in [`TfBuffer::handle_incoming_transforms`].
+    pub fn extend_sorted(&mut self, transforms: &[(TransformStamped, bool)]) {
+        let mut touched = HashSet::new();
+        for (transform, static_tf) in transforms {
+            if !is_well_formed(transform) {
+                continue;
+            }
+            if *static_tf {
+                let _ = self.add_published_transform(transform, true);
+                continue;
+            }
+            let Ok(transform) = self.normalized(transform) else {
+                continue;
+            };
+            if self.check_acyclic(&transform).is_err()
+                || self.check_single_parent(&transform).is_err()
+            {
+                continue;
+            }
+            if self
+                .edge_mut(&transform, false)
+                .add_dynamic_unpruned(transform.clone())
+            {
+                self.ingest_stats.reordered += 1;
+            }
+            touched.insert(TfGraphNode {
+                child: transform.child_frame_id.clone(),
+                parent: transform.header.frame_id.clone(),
+            });
+        }
+        for key in touched {
+            if let Some(chain) = self.transform_data.get_mut(&key) {
+                chain.prune_to_cache();
+            }
+        }
+    }
+
+    /// Copies every stored sample, with its stamp and whether it is static, into a serializable snapshot.
+    #[cfg(feature = "serde")]
+    pub fn export_snapshot(&self) -> crate::snapshot::BufferSnapshot {
+        let mut samples: Vec<_> = self
+            .published_samples()
+            .map(|(transform, is_static)| crate::snapshot::SnapshotSample {
+                transform: transform.into(),
+                is_static,
+            })
+            .collect();
+        samples.sort_by(|a, b| {
+            let key = |sample: &crate::snapshot::SnapshotSample| {
+                (
+                    sample.transform.stamp,
+                    sample.transform.parent.clone(),
+                    sample.transform.child.clone(),
+                )
+            };
+            key(a).cmp(&key(b))
+        });
+        crate::snapshot::BufferSnapshot { samples }
+    }
+
+    /// Inserts the samples of a snapshot produced by [`TfBuffer::export_snapshot`], as with
+    /// [`TfBuffer::extend_sorted`].
+    #[cfg(feature = "serde")]
+    pub fn import_snapshot(&mut self, snapshot: crate::snapshot::BufferSnapshot) {
+        let mut samples: Vec<_> = snapshot
+            .samples
+            .into_iter()
+            .map(|sample| (TransformStamped::from(sample.transform), sample.is_static))
+            .collect();
+        samples.sort_by_key(|(sample, _)| sample.header.stamp);
+        self.extend_sorted(&samples);
+    }
+
+    /// Every stored sample, with whether it is static.
+    #[cfg(any(feature = "snapshot_file", feature = "serde"))]
+    pub(crate) fn published_samples(&self) -> impl Iterator<Item = (&TransformStamped, bool)> {
+        self.transform_data
+            .values()
+            .flat_map(|chain| chain.samples())
+    }
+
+    /// The duration samples of dynamic transforms are kept for.
+    pub fn cache_duration(&self) -> Duration {
+        self.cache_duration
+    }
+
+    /// Changes the duration samples of dynamic transforms are kept for.
+    ///
+    /// Shrinking the window immediately drops the samples that fall outside of it. Growing it only affects samples
+    /// received from now on, since dropped samples are gone for good.
+    pub fn set_cache_duration(&mut self, cache_duration: Duration) {
+        self.cache_duration = cache_duration;
+        for chain in self.transform_data.values_mut() {
+            chain.set_cache_duration(cache_duration);
+        }
+        if let Some(memo) = &mut self.interpolation_memo {
+            memo.clear();
+        }
+    }
+
+    /// Collects the latest sample of every published edge, or only of static edges if `static_only` is set.
+    ///
+    /// The message can be republished as is, e.g. with a [`crate::TfBroadcaster`] bridging to another network.
+    /// Transforms are sorted by parent then child frame.
+    pub fn to_tf_message(&self, static_only: bool) -> TFMessage {
+        let mut transforms: Vec<_> = self
+            .transform_data
+            .values()
+            .filter(|chain| !static_only || chain.is_static())
+            .filter_map(|chain| chain.get_closest_ref(Time::new()).cloned())
+            .collect();
+        transforms.sort_by(|a, b| {
+            (&a.header.frame_id, &a.child_frame_id).cmp(&(&b.header.frame_id, &b.child_frame_id))
+        });
+        TFMessage { transforms }
+    }
+
+    /// Every frame appearing as a parent or a child of an edge, sorted by name.
+    pub fn frame_names(&self) -> Vec<String> {
+        let frames: HashSet<_> = self
+            .child_transform_index
+            .iter()
+            .flat_map(|(parent, children)| std::iter::once(parent).chain(children))
+            .collect();
+        let mut frames: Vec<_> = frames.into_iter().cloned().collect();
+        frames.sort_unstable();
+        frames
+    }
+
+    /// Whether `frame` appears as a parent or a child of an edge.
+    pub fn frame_exists(&self, frame: &str) -> bool {
+        self.child_transform_index.contains_key(frame)
+            || self.parent_transform_index.contains_key(frame)
+    }
+
+    /// Describes every frame with a published parent in YAML, like `tf2::BufferCore::allFramesAsYAML`.
+    ///
+    /// Each frame maps to its `parent`, the stamps in seconds of its `oldest_transform` and `newest_transform`, and its
+    /// `buffer_length`, i.e. the number of samples stored. If a frame has several parents, the one with the newest
+    /// sample is listed. Frames are sorted by name.
+    pub fn all_frames_as_yaml(&self) -> String {
+        let mut frames: Vec<_> = self.parent_transform_index.keys().collect();
+        frames.sort_unstable();
+        let mut yaml = String::new();
+        for frame in frames {
+            let Some((parent, chain, (oldest, newest))) = self
+                .parent_transform_index
+                .get(frame)
+                .into_iter()
+                .flatten()
+                .filter_map(|parent| {
+                    let chain = self.transform_data.get(&TfGraphNode {
+                        child: frame.clone(),
+                        parent: parent.clone(),
+                    })?;
+                    Some((parent, chain, chain.stamp_range()?))
+                })
+                .max_by_key(|(parent, _, (_, newest))| (*newest, *parent))
+            else {
+                continue;
+            };
+            yaml.push_str(&format!(
+                "{frame}:\n  parent: '{parent}'\n  oldest_transform: {}\n  newest_transform: {}\n  buffer_length: {}\n",
+                format_stamp(&oldest),
+                format_stamp(&newest),
+                chain.sample_count(),
+            ));
+        }
+        yaml
+    }
+
+    /// Renders the tree as a Graphviz `digraph`, e.g. to spot disconnected subtrees.
+    ///
+    /// Each published edge is an arrow from parent to child, labelled with the stamp of its newest sample and the
+    /// number of samples stored. `highlight_frame`, if given, is filled in colour.
+    pub fn all_frames_as_dot(&self, highlight_frame: Option<&str>) -> String {
+        let mut edges: Vec<_> = self
+            .parent_transform_index
+            .iter()
+            .flat_map(|(child, parents)| parents.iter().map(move |parent| (parent, child)))
+            .collect();
+        edges.sort_unstable();
+        let mut dot = String::from("digraph tf {\n");
+        for (parent, child) in edges {
+            let Some(chain) = self.transform_data.get(&TfGraphNode {
+                child: child.clone(),
+                parent: parent.clone(),
+            }) else {
+                continue;
+            };
+            let newest = chain
+                .stamp_range()
+                .map(|(_, newest)| format_stamp(&newest))
+                .unwrap_or_default();
+            dot.push_str(&format!(
+                "  {parent:?} -> {child:?} [label=\"stamp: {newest}\\nbuffer: {}\"];\n",
+                chain.sample_count(),
+            ));
+        }
+        if let Some(frame) = highlight_frame {
+            dot.push_str(&format!("  {frame:?} [style=filled, fillcolor=yellow];\n"));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Statistics of every published edge, sorted by child frame, e.g. to flag frames whose newest stamp is too old.
+    ///
+    /// Edges whose samples have all been pruned are left out.
+    pub fn frame_statistics(&self) -> Vec<FrameStats> {
+        let mut statistics: Vec<_> = self
+            .transform_data
+            .iter()
+            .filter_map(|(key, chain)| {
+                let (oldest_stamp, newest_stamp) = chain.stamp_range()?;
+                let buffer_length = chain.sample_count();
+                let span = (newest_stamp - oldest_stamp).nanos() as f64 * 1e-9;
+                let average_rate_hz = if span > 0.0 {
+                    (buffer_length - 1) as f64 / span
+                } else {
+                    0.0
+                };
+                Some(FrameStats {
+                    frame_id: key.child.clone(),
+                    parent: key.parent.clone(),
+                    is_static: chain.is_static(),
+                    buffer_length,
+                    oldest_stamp,
+                    newest_stamp,
+                    average_rate_hz,
+                })
+            })
+            .collect();
+        statistics.sort_by(|a, b| (&a.frame_id, &a.parent).cmp(&(&b.frame_id, &b.parent)));
+        statistics
+    }
+
+    /// Counters describing the quality of the data received so far.
+    ///
+    /// Out-of-order samples, e.g. from recording jitter in bag files, are sorted into place and counted here.
+    pub fn ingest_stats(&self) -> IngestStats {
+        self.ingest_stats
+    }
+
+    /// Counter incremented every time a new edge is added to the tree.
+    ///
+    /// New samples on an existing edge do not change the topology, so they leave the revision untouched.
+    pub fn topology_revision(&self) -> u64 {
+        self.topology_revision
+    }
+
+    /// Returns a channel receiving the new topology revision every time it is incremented.
+    ///
+    /// Receivers can block on it instead of polling `topology_revision`. Dropping the receiver unsubscribes.
+    pub fn subscribe_topology_changes(&mut self) -> mpsc::Receiver<u64> {
+        let (sender, receiver) = mpsc::channel();
+        self.topology_watchers.0.push(sender);
+        receiver
+    }
+
+    /// Checks the internal consistency of the buffer, returning every violated invariant.
+    ///
+    /// Every edge must be indexed in both directions and hold finite samples sorted by stamp.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+        for (key, chain) in &self.transform_data {
+            let edge = format!("{} -> {}", key.parent, key.child);
+            if !self
+                .child_transform_index
+                .get(&key.parent)
+                .is_some_and(|children| children.contains(&key.child))
+            {
+                violations.push(format!("{edge} is missing from the child index"));
+            }
+            if !self
+                .parent_transform_index
+                .get(&key.child)
+                .is_some_and(|parents| parents.contains(&key.parent))
+            {
+                violations.push(format!("{edge} is missing from the parent index"));
+            }
+            let samples: Vec<_> = chain.transform_chain.iter().collect();
+            if samples
+                .windows(2)
+                .any(|pair| pair[0].header.stamp > pair[1].header.stamp)
+            {
+                violations.push(format!("{edge} is not sorted by stamp"));
+            }
+            if samples.iter().any(|sample| !is_well_formed(sample)) {
+                violations.push(format!("{edge} contains a malformed transform"));
+            }
+        }
+        for (parent, children) in &self.child_transform_index {
+            for child in children {
+                if !self.transform_data.contains_key(&TfGraphNode {
+                    child: child.clone(),
+                    parent: parent.clone(),
+                }) {
+                    violations.push(format!("{parent} -> {child} is indexed but has no data"));
+                }
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            violations.sort();
+            Err(violations)
+        }
+    }
+
+    /// Tags a frame with the axis convention it uses. This does not affect lookups.
+    pub fn set_frame_convention(&mut self, frame: &str, convention: Convention) {
+        self.frame_conventions.insert(frame.to_string(), convention);
+    }
+
+    /// The axis convention a frame was tagged with, if any.
+    pub fn frame_convention(&self, frame: &str) -> Option<Convention> {
+        self.frame_conventions.get(frame).copied()
+    }
+
+    /// Tags the edge from `parent` to `child`, e.g. with where its data comes from. This does not affect lookups.
+    ///
+    /// The edge does not need to have received samples yet, and its tags are kept as new samples arrive.
+    pub fn set_edge_metadata(&mut self, parent: &str, child: &str, key: &str, value: &str) {
+        self.edge_metadata
+            .entry(TfGraphNode {
+                child: child.to_string(),
+                parent: parent.to_string(),
+            })
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Changes how lookups between two samples of the edge from `parent` to `child` are answered.
+    ///
+    /// Like metadata, this can be set before the edge receives samples.
+    pub fn set_interpolation_mode(
+        &mut self,
+        parent: &str,
+        child: &str,
+        interpolation_mode: InterpolationMode,
+    ) {
+        let key = TfGraphNode {
+            child: child.to_string(),
+            parent: parent.to_string(),
+        };
+        if let Some(chain) = self.transform_data.get_mut(&key) {
+            chain.set_interpolation_mode(interpolation_mode);
+        }
+        self.edge_interpolation_modes
+            .insert(key, interpolation_mode);
+        if let Some(memo) = &mut self.interpolation_memo {
+            memo.clear();
+        }
+    }
+
+    /// The tags of the edge from `parent` to `child`, if any.
+    pub fn edge_metadata(&self, parent: &str, child: &str) -> Option<&HashMap<String, String>> {
+        self.edge_metadata.get(&TfGraphNode {
+            child: child.to_string(),
+            parent: parent.to_string(),
+        })
+    }
+
+    /// The time intervals covered by the samples of the edge from `parent` to `child`.
+    ///
+    /// Intervals are split wherever consecutive samples are more than `max_gap` apart, since interpolating across
+    /// such a hole is unreliable. An isolated sample yields an interval of zero length. Returns nothing for unknown
+    /// edges. Note that static edges are valid at all times regardless of the stamps of their samples.
+    pub fn edge_coverage_intervals(
+        &self,
+        parent: &str,
+        child: &str,
+        max_gap: Duration,
+    ) -> Vec<(Time, Time)> {
+        let Some((chain, _)) = self.edge(parent, child) else {
+            return Vec::new();
+        };
+        let mut intervals: Vec<(Time, Time)> = Vec::new();
+        for stamp in chain
+            .transform_chain
+            .iter()
+            .map(|sample| sample.header.stamp)
+        {
+            match intervals.last_mut() {
+                Some((_, end)) if stamp - *end <= max_gap => *end = stamp,
+                _ => intervals.push((stamp, stamp)),
+            }
+        }
+        intervals
+    }
+
+    /// Drops dynamic samples older than `now - cache_duration`, e.g. for frames that stopped publishing.
+    ///
+    /// Edges whose samples have all been dropped are kept, but are no longer used for lookups.
+    pub fn prune(&mut self, now: Time) {
+        if now <= Time::from_nanos(0) + self.cache_duration {
+            return;
+        }
+        let time_to_keep = now - self.cache_duration;
+        for chain in self.transform_data.values_mut() {
+            chain.prune_older_than(time_to_keep);
+        }
+        if let Some(memo) = &mut self.interpolation_memo {
+            memo.clear();
+        }
+    }
+
+    /// Suggests a cache duration that would have satisfied 99% of the lookups that failed because they asked for
+    /// data older than the oldest cached sample.
+    ///
+    /// For each such lookup, the largest gap between the requested time and the oldest sample of a rejected edge
+    /// is recorded. Returns the current cache duration if no lookup has failed this way.
+    pub fn suggest_cache_duration(&self) -> Duration {
+        let mut shortfalls: Vec<_> = self.past_lookups.snapshot().into();
+        if shortfalls.is_empty() {
+            return self.cache_duration;
+        }
+        shortfalls.sort();
+        let index = (shortfalls.len() * 99).div_ceil(100) - 1;
+        self.cache_duration + shortfalls[index]
+    }
+
+    /// The samples of the edge between `parent` and `child`, and whether they must be inverted because the edge was
+    /// published from `child` to `parent`.
+    fn edge(&self, parent: &str, child: &str) -> Option<(&TfIndividualTransformChain, bool)> {
+        let key = TfGraphNode {
+            child: child.to_string(),
+            parent: parent.to_string(),
+        };
+        if let Some(chain) = self.transform_data.get(&key) {
+            return Some((chain, false));
+        }
+        self.transform_data
+            .get(&TfGraphNode {
+                child: key.parent,
+                parent: key.child,
+            })
+            .map(|chain| (chain, true))
+    }
+
+    /// The frames sharing an edge with `frame`, in either direction.
+    fn neighbours<'a>(&'a self, frame: &str) -> impl Iterator<Item = &'a String> {
+        let children = self.child_transform_index.get(frame).into_iter().flatten();
+        let parents = self.parent_transform_index.get(frame).into_iter().flatten();
+        children.chain(parents)
+    }
+
+    /// The frames a lookup from `from` to `to` at `time` goes through, in order.
+    ///
+    /// The path excludes `from` but includes `to`, so it is empty if both are the same frame, and each frame is
+    /// connected by an edge to the one before it. Useful to debug lookups taking an unexpected route.
+    pub fn transform_path(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<Vec<String>, TfError> {
+        self.lookup_path(from, to, time.into().0, None)
+            .map(|(_, _, path)| path)
+    }
+
+    /// The path of a lookup from `from` to `to` at `time`, only expanding frames in `allowed` if given, along with the
+    /// two frames as stored.
+    ///
+    /// Lookups go through here, so that a leading slash of `from` or `to` is ignored like when storing transforms, and
+    /// the frames on the path are recorded for [`TfBuffer::recently_used_frames`]. The path from a frame to itself is
+    /// empty, but fails with [`TfError::NoDataForFrame`] if the buffer has never seen the frame.
+    fn lookup_path<'a>(
+        &self,
+        from: &'a str,
+        to: &'a str,
+        time: rosrust::Time,
+        allowed: Option<&HashSet<String>>,
+    ) -> Result<(&'a str, &'a str, Vec<String>), TfError> {
+        let from = self.slashed_frames.strip(from);
+        let to = self.slashed_frames.strip(to);
+        if from == to && !self.frame_exists(from) {
+            return Err(TfError::NoDataForFrame(from.to_string()));
+        }
+        let path = match allowed {
+            Some(allowed) => self.retrieve_transform_path_within(
+                from.to_string(),
+                to.to_string(),
+                time,
+                Some(allowed),
+            )?,
+            None => self.retrieve_transform_path(from.to_string(), to.to_string(), time)?,
+        };
+        #[cfg(feature = "metrics")]
+        self.used_frames
+            .record(std::iter::once(from).chain(path.iter().map(String::as_str)));
+        Ok((from, to, path))
+    }
+
+    /// Retrieves the transform path
+    fn retrieve_transform_path(
+        &self,
+        from: String,
+        to: String,
+        time: rosrust::Time,
+    ) -> Result<Vec<String>, TfError> {
+        let Some(memo) = &self.path_memo else {
+            return self.retrieve_transform_path_within(from, to, time, None);
+        };
+        let key = (from, to);
+        if let Ok(paths) = memo.paths.lock() {
+            if let Some(path) = paths.get(&key) {
+                if self.is_path_valid(&key.0, path, time) {
+                    return Ok(path.clone());
+                }
+            }
+        }
+        let (from, to) = key;
+        let path = self.retrieve_transform_path_within(from.clone(), to.clone(), time, None)?;
+        if let Ok(mut paths) = memo.paths.lock() {
+            paths.insert((from, to), path.clone());
+        }
+        Ok(path)
+    }
+
+    /// Whether every edge of `path`, starting at `from`, can be looked up at `time`.
+    fn is_path_valid(&self, from: &str, path: &[String], time: Time) -> bool {
+        let mut parent = from;
+        path.iter().all(|child| {
+            let valid = self
+                .edge(parent, child)
+                .is_some_and(|(chain, _)| chain.has_valid_transform(time));
+            parent = child;
+            valid
+        })
+    }
+
+    /// Retrieves the transform path, only expanding frames in `allowed` if given.
+    fn retrieve_transform_path_within(
+        &self,
+        from: String,
+        to: String,
+        time: rosrust::Time,
+        allowed: Option<&HashSet<String>>,
+    ) -> Result<Vec<String>, TfError> {
+        self.search_transform_path(from, to, time, allowed)
+            .map_err(|(error, shortfall)| {
+                if let Some(shortfall) = shortfall {
+                    self.past_lookups.record(shortfall);
+                }
+                error
+            })
+    }
+
+    /// Searches the transform path without recording failed lookups.
+    ///
+    /// On failure, also returns how far before the oldest sample of a rejected edge the requested time was, if so.
+    fn search_transform_path(
+        &self,
+        from: String,
+        to: String,
+        time: rosrust::Time,
+        allowed: Option<&HashSet<String>>,
+    ) -> Result<Vec<String>, (TfError, Option<Duration>)> {
+        let search = self.explore_transform_graph(&from, Some(&to), time, allowed);
+        let mut res = vec![];
+        let mut r = to.clone();
+        while r != from {
+            res.push(r.clone());
+            let parent = search.parents.get(&r);
+
+            match parent {
+                Some(x) => r = x.to_string(),
+                None => {
+                    if let (true, Some(max)) = (search.truncated, self.max_path_length) {
+                        return Err((TfError::PathTooLong(from, to, max), None));
+                    }
+                    return Err((
+                        TfError::CouldNotFindTransform(
+                            from,
+                            to,
+                            self.child_transform_index.clone(),
+                        ),
+                        search.shortfall,
+                    ));
+                }
+            }
+        }
+        res.reverse();
+        Ok(res)
+    }
+
+    /// Explores the frames reachable from `from` at `time` breadth-first, stopping once `to` is reached if given.
+    ///
+    /// Each frame is reached through the fewest possible edges, so paths are as short as possible.
+    fn explore_transform_graph(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        time: rosrust::Time,
+        allowed: Option<&HashSet<String>>,
+    ) -> GraphSearch {
+        let is_allowed = |frame: &str| allowed.is_none_or(|allowed| allowed.contains(frame));
+        let mut search = GraphSearch::default();
+        let mut frontier: VecDeque<String> = VecDeque::new();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut depths: HashMap<String, usize> = HashMap::new();
+        visited.insert(from.to_string());
+        depths.insert(from.to_string(), 0);
+        if is_allowed(from) {
+            frontier.push_back(from.to_string());
+        }
+
+        while !frontier.is_empty() {
+            let current_node = frontier.pop_front().unwrap();
+            if to == Some(current_node.as_str()) {
+                break;
+            }
+            let depth = depths[&current_node] + 1;
+            for v in self.neighbours(&current_node) {
+                if visited.contains(v) || !is_allowed(v) {
+                    continue;
+                }
+
+                let Some((chain, _)) = self.edge(&current_node, v) else {
+                    continue;
+                };
+                if chain.is_traversable(time) {
+                    if self.max_path_length.is_some_and(|max| depth > max) {
+                        search.truncated = true;
+                        continue;
+                    }
+                    search.parents.insert(v.to_string(), current_node.clone());
+                    depths.insert(v.to_string(), depth);
+                    frontier.push_back(v.to_string());
+                    visited.insert(v.to_string());
+                } else if let Some(edge_shortfall) = chain.shortfall(time) {
+                    search.shortfall = search.shortfall.max(Some(edge_shortfall));
+                }
+            }
+        }
+        search
+    }
+
+    /// Whether a transform from `from` to `to` can be looked up at `time`.
+    ///
+    /// This is cheaper than calling [`TfBuffer::lookup_transform`] and checking the result, since it neither composes
+    /// the transform nor builds an error. Use [`TfBuffer::can_transform_with_error`] to find out why it cannot.
+    pub fn can_transform(&self, from: &str, to: &str, time: &Time) -> bool {
+        let from = self.slashed_frames.strip(from);
+        let to = self.slashed_frames.strip(to);
+        if from == to {
+            return self.frame_exists(from);
+        }
+        let search = self.explore_transform_graph(from, Some(to), *time, None);
+        // The search keeps single-sample edges that cannot serve `time`, so check each edge on the way back.
+        let mut child = to;
+        while let Some(parent) = search.parents.get(child) {
+            let valid = self
+                .edge(parent, child)
+                .is_some_and(|(chain, _)| chain.has_valid_transform(*time));
+            if !valid {
+                return false;
+            }
+            child = parent;
+        }
+        child == from
+    }
+
+    /// Like [`TfBuffer::can_transform`], but returns the error [`TfBuffer::lookup_transform`] would fail with.
+    pub fn can_transform_with_error(
+        &self,
+        from: &str,
+        to: &str,
+        time: &Time,
+    ) -> Result<(), TfError> {
+        let (from, _, path) = self.lookup_path(from, to, *time, None)?;
+        let mut parent = from;
+        for child in &path {
+            let (chain, _) = self
+                .edge(parent, child)
+                .ok_or_else(|| TfError::NoDataForFrame(child.clone()))?;
+            chain.bracket(*time)?;
+            parent = child;
+        }
+        Ok(())
+    }
+
+    /// Whether every edge on the path between `a` and `b` is static, i.e. the transform between them never changes.
+    ///
+    /// Returns false if the frames are not connected.
+    pub fn is_rigidly_attached(&self, a: &str, b: &str) -> bool {
+        let Ok((a, _, path)) = self.lookup_path(a, b, Time::new(), None) else {
+            return false;
+        };
+        let mut parent = a;
+        path.iter().all(|child| {
+            let is_static = self
+                .edge(parent, child)
+                .is_some_and(|(chain, _)| chain.is_static());
+            parent = child;
+            is_static
+        })
+    }
+
+    /// The time closest to `around` at which a lookup from `from` to `to` would succeed, if any.
+    ///
+    /// Useful when the tree is reconfigured over time and a lookup falls into a gap between coverages.
+    pub fn nearest_valid_time(
+        &self,
+        from: &str,
+        to: &str,
+        around: impl Into<TimePoint>,
+    ) -> Option<Time> {
+        let around = around.into().0;
+        let from = self.slashed_frames.strip(from);
+        let to = self.slashed_frames.strip(to);
+        // Path validity only changes at the ends of the cached ranges, so the nearest valid time is either `around`
+        // or one of those ends.
+        let mut candidates = vec![around];
+        for chain in self.transform_data.values() {
+            if let Some((first, last)) = chain.dynamic_range() {
+                candidates.extend([first, last]);
+            }
+        }
+        let distance = |time: &Time| (time_as_ns_i64(time) - time_as_ns_i64(&around)).abs();
+        candidates.sort_by_key(|time| (distance(time), *time));
+        candidates.dedup();
+        candidates.into_iter().find(|time| {
+            self.search_transform_path(from.to_string(), to.to_string(), *time, None)
+                .is_ok_and(|path| self.is_path_valid(from, &path, *time))
+        })
+    }
+
+    /// The most recent time at which every edge on the path from `from` to `to` has data.
+    ///
+    /// This is the newest stamp of the least recently updated dynamic edge on the path. Static edges are valid at all
+    /// times, so if the path only has static edges, the zero time is returned, which lookups treat as the latest data.
+    /// Fails with [`TfError::NoDataForFrame`] if a dynamic edge on the path has been pruned empty.
+    pub fn lookup_latest_common_time(&self, from: &str, to: &str) -> Result<Time, TfError> {
+        let (from, _, path) = self.lookup_path(from, to, Time::new(), None)?;
+        self.latest_common_time_along(from, &path)
+    }
+
+    /// The most recent time at which every edge on `path`, starting at `from`, has data.
+    fn latest_common_time_along(&self, from: &str, path: &[String]) -> Result<Time, TfError> {
+        let mut latest: Option<Time> = None;
+        let mut parent = from;
+        for child in path {
+            let (chain, _) = self
+                .edge(parent, child)
+                .ok_or_else(|| TfError::NoDataForFrame(child.clone()))?;
+            // Edges received as static, even with dynamic overrides, are valid at all times.
+            match chain.dynamic_range() {
+                Some((_, newest)) => {
+                    latest = Some(latest.map_or(newest, |latest| latest.min(newest)));
+                }
+                None if chain.sample_count() == 0 => {
+                    return Err(TfError::NoDataForFrame(child.clone()));
+                }
+                None => {}
+            }
+            parent = child;
+        }
+        Ok(latest.unwrap_or_default())
+    }
+
+    /// Replaces the zero time, which stands for the latest data, by the latest common time of `path`.
+    ///
+    /// Like in tf2, the empty path from a frame to itself resolves to the newest stamp of the frame, i.e. of the
+    /// dynamic edges to its parents, and stays zero if it has none.
+    fn resolve_latest(&self, from: &str, path: &[String], time: Time) -> Result<Time, TfError> {
+        if time.nanos() != 0 {
+            return Ok(time);
+        }
+        if !path.is_empty() {
+            return self.latest_common_time_along(from, path);
+        }
+        let newest = self
+            .parent_transform_index
+            .get(from)
+            .into_iter()
+            .flatten()
+            .filter_map(|parent| self.edge(parent, from)?.0.dynamic_range())
+            .map(|(_, newest)| newest)
+            .max();
+        Ok(newest.unwrap_or_default())
+    }
+
+    /// The number of edges of the longest path between two frames connected at `time`.
+    ///
+    /// This bounds the number of hops, and thus the cost, of any lookup at that time.
+    pub fn tree_height(&self, time: impl Into<TimePoint>) -> usize {
+        let time = time.into().0;
+        let mut height = 0;
+        let frames = self
+            .child_transform_index
+            .keys()
+            .chain(self.parent_transform_index.keys());
+        for start in frames {
+            let mut depths = HashMap::from([(start, 0)]);
+            let mut frontier = VecDeque::from([start]);
+            while let Some(current) = frontier.pop_front() {
+                let depth = depths[current] + 1;
+                for neighbour in self.neighbours(current) {
+                    if depths.contains_key(neighbour) {
+                        continue;
+                    }
+                    let valid = self
+                        .edge(current, neighbour)
+                        .is_some_and(|(chain, _)| chain.has_valid_transform(time));
+                    if valid {
+                        height = height.max(depth);
+                        depths.insert(neighbour, depth);
+                        frontier.push_back(neighbour);
+                    }
+                }
+            }
+        }
+        height
+    }
+
+    /// Looks up a transform within the tree at a given time.
+    ///
+    /// The time can be given as a `rosrust::Time`, as `i64` nanoseconds or as `f64` seconds. The zero time looks up
+    /// the latest data, at the latest time all edges on the path have in common (see
+    /// [`TfBuffer::lookup_latest_common_time`]), so that edges updated at different times are not mixed. A leading
+    /// slash of `from` or `to` is ignored, as it is when storing transforms.
+    pub fn lookup_transform(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        self.lookup_transform_with_sources(from, to, time.into().0)
+            .map(|(transform, _)| transform)
+    }
+
+    /// Looks up the most recent transform between `from` and `to`, stamped with the latest time all edges on the path
+    /// have data for (see [`TfBuffer::lookup_latest_common_time`]).
+    ///
+    /// This is what [`TfBuffer::lookup_transform`] does for the zero time, without relying on that convention.
+    pub fn lookup_latest_transform(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<TransformStamped, TfError> {
+        self.lookup_transform(from, to, Time::new())
+    }
+
+    /// Looks up the transform from the root of the tree containing `frame` to `frame`, without knowing the root's name.
+    ///
+    /// The root is the only frame of that tree without a published parent. Returns [`TfError::AmbiguousRoot`] if
+    /// there is no such frame or several of them. Like in [`TfBuffer::lookup_transform`], a leading slash of `frame` is
+    /// ignored.
+    pub fn lookup_in_root(
+        &self,
+        frame: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        let frame = self.slashed_frames.strip(frame);
+        if !self.frame_exists(frame) {
+            return Err(TfError::NoDataForFrame(frame.to_string()));
+        }
+        let mut frontier = vec![frame];
+        let mut component = HashSet::from([frame]);
+        while let Some(current) = frontier.pop() {
+            for neighbour in self.neighbours(current) {
+                if component.insert(neighbour) {
+                    frontier.push(neighbour);
+                }
+            }
+        }
+        let mut roots: Vec<_> = component
+            .into_iter()
+            .filter(|candidate| !self.parent_transform_index.contains_key(*candidate))
+            .collect();
+        match roots.as_slice() {
+            [root] => self.lookup_transform(root, frame, time),
+            _ => {
+                roots.sort_unstable();
+                Err(TfError::AmbiguousRoot(
+                    frame.to_string(),
+                    roots.into_iter().map(str::to_string).collect(),
+                ))
+            }
+        }
+    }
+
+    /// Looks up a transform at `time`, or if that fails, at the nearest time within `search_window` where it succeeds.
+    ///
+    /// Returns the transform along with the time actually used. This trades temporal accuracy for availability, e.g.
+    /// when the requested time occasionally falls into a tiny gap between coverages. If no time within the window
+    /// works, the error of the lookup at `time` is returned.
+    pub fn lookup_transform_robust(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+        search_window: Duration,
+    ) -> Result<(TransformStamped, Time), TfError> {
+        let time = time.into().0;
+        let error = match self.lookup_transform(from, to, time) {
+            Ok(transform) => return Ok((transform, time)),
+            Err(error) => error,
+        };
+        let nearest = self
+            .nearest_valid_time(from, to, time)
+            .filter(|nearest| {
+                (time_as_ns_i64(nearest) - time_as_ns_i64(&time)).abs()
+                    <= duration_as_ns_i64(&search_window)
+            })
+            .ok_or(error)?;
+        Ok((self.lookup_transform(from, to, nearest)?, nearest))
+    }
+
+    /// Looks up the average transform over `window`, centred on `center`, e.g. to smooth a noisy pose estimate.
+    ///
+    /// The transform is looked up at 11 evenly spaced times spanning the window, ends included, so every sample has
+    /// the same weight whatever the publishing rate of the edges. Translations are averaged component-wise and
+    /// orientations by normalizing the sum of the quaternions, flipped into the same hemisphere, which is accurate
+    /// for the nearby orientations found within a short window. Times at which the lookup fails are skipped, as are
+    /// times up to zero, which lookups would take for the latest data; if no time is left, the result of the lookup at
+    /// `center` is returned.
+    pub fn lookup_transform_averaged(
+        &self,
+        from: &str,
+        to: &str,
+        center: &Time,
+        window: Duration,
+    ) -> Result<TransformStamped, TfError> {
+        let center_ns = time_as_ns_i64(center);
+        let window_ns = duration_as_ns_i64(&window);
+        let mut translation = Vector3D::zeros();
+        let mut rotation = Quaternion4D::default();
+        let mut count = 0;
+        for index in 0..AVERAGED_LOOKUP_SAMPLES {
+            let time =
+                center_ns - window_ns / 2 + window_ns * index / (AVERAGED_LOOKUP_SAMPLES - 1);
+            if time <= 0 {
+                continue;
+            }
+            let Ok(sample) = self.lookup_transform(from, to, time) else {
+                continue;
+            };
+            let sample = isometry_from_transform(&sample.transform);
+            translation += sample.translation.vector;
+            let quaternion = sample.rotation.into_inner();
+            if count > 0 && rotation.dot(&quaternion) < 0.0 {
+                rotation -= quaternion;
+            } else {
+                rotation += quaternion;
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return self.lookup_transform(from, to, *center);
+        }
+        let average = Isometry3::from_parts(
+            (translation / count as f64).into(),
+            UnitQuaternion::new_normalize(rotation),
+        );
+        Ok(to_transform_stamped(
+            isometry_to_transform(average),
+            from.to_string(),
+            to.to_string(),
+            *center,
+        ))
+    }
+
+    /// Looks up a transform within the tree at a given time, writing the result into `out`.
+    ///
+    /// Stored samples that need no interpolation are composed in place instead of being cloned, and the frame id
+    /// strings of `out` are reused, which saves allocations when looking up the same transform repeatedly.
+    pub fn lookup_transform_into(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+        out: &mut TransformStamped,
+    ) -> Result<(), TfError> {
+        let (from, to, path) = self.lookup_path(from, to, time, None)?;
+        let time = self.resolve_latest(from, &path, time)?;
+        let mut composed = Isometry3::identity();
+        let mut parent = from;
+        for child in &path {
+            let (time_cache, inverted) = self
+                .edge(parent, child)
+                .ok_or_else(|| TfError::NoDataForFrame(child.clone()))?;
+            let edge = match time_cache.get_closest_ref(time) {
+                Some(sample) => isometry_from_transform(&sample.transform),
+                None => {
+                    isometry_from_transform(&time_cache.get_closest_transform(time)?.0.transform)
+                }
+            };
+            composed *= if inverted { edge.inverse() } else { edge };
+            parent = child;
+        }
+        out.header.frame_id.clear();
+        out.header.frame_id.push_str(from);
+        out.header.stamp = time;
+        out.header.seq = 1;
+        out.child_frame_id.clear();
+        out.child_frame_id.push_str(to);
+        out.transform = isometry_to_transform(composed);
+        Ok(())
+    }
+
+    /// Looks up a transform within the tree at a given time, interpolating and composing in single precision.
+    ///
+    /// Meant for GPU uploads and embedded pipelines that use `f32` end-to-end. Samples are stored in `f64` and rounded
+    /// once, but with only about 7 significant digits, translations far from the origin lose sub-millimetre precision
+    /// and rounding errors accumulate along long paths. Prefer [`TfBuffer::lookup_transform`] when precision matters.
+    pub fn lookup_transform_f32(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<Isometry3<f32>, TfError> {
+        let time = time.into().0;
+        let (from, _, path) = self.lookup_path(from, to, time, None)?;
+        let time = self.resolve_latest(from, &path, time)?;
+        let mut edges = Vec::with_capacity(path.len());
+        let mut parent = from;
+        for child in &path {
+            let (time_cache, inverted) = self
+                .edge(parent, child)
+                .ok_or_else(|| TfError::NoDataForFrame(child.clone()))?;
+            let to_f32 =
+                |sample: &TransformStamped| isometry_from_transform(&sample.transform).cast();
+            let edge = match time_cache.bracket(time)? {
+                Bracket::Exact(sample) => to_f32(sample),
+                Bracket::Between(earlier, later, weight) => {
+                    interpolate_f32(&to_f32(earlier), &to_f32(later), weight as f32)
+                }
+                Bracket::Extrapolated(earlier, later, ratio) => isometry_from_transform(
+                    &extrapolate(&earlier.transform, &later.transform, ratio),
+                )
+                .cast(),
+            };
+            edges.push(if inverted { edge.inverse() } else { edge });
+            parent = child;
+        }
+        Ok(chain_transforms_f32(&edges))
+    }
+
+    /// Looks up a transform within the tree at a given time, as a row-major 4x4 homogeneous matrix.
+    ///
+    /// `matrix[row][column]`, with the rotation in the upper left 3x3 block and the translation in the last column, so
+    /// a point `p` in `to` maps to `matrix * [p.x, p.y, p.z, 1]` in `from`. See [`transform_to_matrix`].
+    pub fn lookup_transform_as_matrix(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<[[f64; 4]; 4], TfError> {
+        let transform = self.lookup_transform(from, to, time)?;
+        Ok(transform_to_matrix(&transform.transform))
+    }
+
+    /// Returns the transform of every edge on the path from `from` to `to`, sampled at `time`.
+    ///
+    /// These are exactly the transforms another buffer needs to compute the same lookup, e.g. to send just enough
+    /// data over a constrained link. Edges are oriented along the path, so some may be inverses of published ones. Like
+    /// in [`TfBuffer::lookup_transform`], the zero time stands for the latest time all edges on the path have in
+    /// common, and every edge is sampled and stamped at that time.
+    pub fn subtree_for(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<Vec<TransformStamped>, TfError> {
+        let time = time.into().0;
+        let (from, _, path) = self.lookup_path(from, to, time, None)?;
+        let time = self.resolve_latest(from, &path, time)?;
+        let mut parent = from.to_string();
+        let mut transforms = Vec::with_capacity(path.len());
+        for child in path {
+            let (time_cache, inverted) = self
+                .edge(&parent, &child)
+                .ok_or_else(|| TfError::NoDataForFrame(child.clone()))?;
+            let (mut transform, _) = time_cache.get_closest_transform(time)?;
+            if inverted {
+                transform = get_inverse(&transform);
+            }
+            if time.nanos() != 0 {
+                transform.header.stamp = time;
+            }
+            transforms.push(transform);
+            parent = child;
+        }
+        Ok(transforms)
+    }
+
+    /// How much the edge from `parent` to `child` changed between `t1` and `t2`.
+    ///
+    /// Returns the translational difference in meters and the rotational difference in radians, e.g. to detect a
+    /// supposedly static sensor slipping. Fails with [`TfError::CouldNotFindTransform`] unless the two frames are
+    /// directly connected.
+    pub fn frame_drift(
+        &self,
+        parent: &str,
+        child: &str,
+        t1: impl Into<TimePoint>,
+        t2: impl Into<TimePoint>,
+    ) -> Result<(f64, f64), TfError> {
+        let (chain, inverted) = self.edge(parent, child).ok_or_else(|| {
+            TfError::CouldNotFindTransform(
+                parent.to_string(),
+                child.to_string(),
+                self.child_transform_index.clone(),
+            )
+        })?;
+        let sample = |time: TimePoint| {
+            chain.get_closest_transform(time.0).map(|(transform, _)| {
+                let isometry = isometry_from_transform(&transform.transform);
+                if inverted {
+                    isometry.inverse()
+                } else {
+                    isometry
+                }
+            })
+        };
+        let (first, second) = (sample(t1.into())?, sample(t2.into())?);
+        let translation = (second.translation.vector - first.translation.vector).norm();
+        let rotation = first.rotation.angle_to(&second.rotation);
+        Ok((translation, rotation))
+    }
+
+    /// Estimates the rate in Hz the edge from `parent` to `child` should be published at to keep the interpolation error
+    /// below `max_error_m` in translation and `max_error_rad` in rotation.
+    ///
+    /// Linear interpolation over a period `h` is off by up to `a * h^2 / 8` for an acceleration `a`, so the rate is
+    /// derived from the largest acceleration observed in the cached samples. Constant velocity motion needs no minimum
+    /// rate, and neither do static edges, so both yield zero. Returns `None` if the edge is unknown or has fewer than
+    /// three samples.
+    pub fn recommended_rate(
+        &self,
+        parent: &str,
+        child: &str,
+        max_error_m: f64,
+        max_error_rad: f64,
+    ) -> Option<f64> {
+        let (chain, inverted) = self.edge(parent, child)?;
+        if chain.is_static() {
+            return Some(0.0);
+        }
+        let samples: Vec<_> = chain
+            .transform_chain
+            .iter()
+            .map(|sample| {
+                let isometry = isometry_from_transform(&sample.transform);
+                (
+                    time_as_ns_i64(&sample.header.stamp) as f64 * 1e-9,
+                    if inverted {
+                        isometry.inverse()
+                    } else {
+                        isometry
+                    },
+                )
+            })
+            .collect();
+        if samples.len() < 3 {
+            return None;
+        }
+        let velocities: Vec<_> = samples
+            .windows(2)
+            .filter(|pair| pair[1].0 > pair[0].0)
+            .map(|pair| {
+                let ((t0, iso0), (t1, iso1)) = (&pair[0], &pair[1]);
+                let dt = t1 - t0;
+                let linear = (iso1.translation.vector - iso0.translation.vector) / dt;
+                let angular = (iso0.rotation.inverse() * iso1.rotation).scaled_axis() / dt;
+                ((t0 + t1) / 2.0, linear, angular)
+            })
+            .collect();
+        let (mut linear_acceleration, mut angular_acceleration) = (0f64, 0f64);
+        for pair in velocities.windows(2) {
+            let ((t0, linear0, angular0), (t1, linear1, angular1)) = (&pair[0], &pair[1]);
+            linear_acceleration = linear_acceleration.max((linear1 - linear0).norm() / (t1 - t0));
+            angular_acceleration =
+                angular_acceleration.max((angular1 - angular0).norm() / (t1 - t0));
+        }
+        let rate = |acceleration: f64, max_error: f64| (acceleration / (8.0 * max_error)).sqrt();
+        Some(rate(linear_acceleration, max_error_m).max(rate(angular_acceleration, max_error_rad)))
+    }
+
+    /// Looks up a transform with `from` taken as an ENU frame (X east, Y north, Z up).
+    ///
+    /// REP-103 world frames such as `map` and `odom` already use ENU, so the rotation applied is the identity and the
+    /// result equals [`TfBuffer::lookup_transform`]. It is provided for symmetry with [`TfBuffer::lookup_transform_ned`].
+    pub fn lookup_transform_enu(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        self.lookup_transform(from, to, time)
+    }
+
+    /// Looks up a transform with `from`, an ENU frame as REP-103 world frames are, re-expressed in NED
+    /// (X north, Y east, Z down).
+    ///
+    /// The axes of `from` are remapped with the fixed rotation `(x, y, z) -> (y, x, -z)`, see [`enu_to_ned`].
+    /// The axes of `to` are left as they are.
+    pub fn lookup_transform_ned(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        let mut transform = self.lookup_transform(from, to, time)?;
+        transform.transform = enu_to_ned(&transform.transform);
+        Ok(transform)
+    }
+
+    /// Looks up a transform and its inverse at a given time with a single traversal.
+    ///
+    /// The second element is exactly `get_inverse` of the first, i.e. the `to` -> `from` transform.
+    pub fn lookup_transform_both(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+    ) -> Result<(TransformStamped, TransformStamped), TfError> {
+        let forward = self.lookup_transform(from, to, time)?;
+        let inverse = get_inverse(&forward);
+        Ok((forward, inverse))
+    }
+
+    /// Looks up a transform within the tree at a given time, also reporting which stored samples it was computed from.
+    ///
+    /// The sources are listed per edge in path order, so data lineage can be tracked through interpolated results.
+    pub fn lookup_transform_with_sources(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+    ) -> Result<(TransformStamped, Vec<TransformSource>), TfError> {
+        let (from, to, path) = self.lookup_path(from, to, time, None)?;
+        if from == to {
+            let time = self.resolve_latest(from, &path, time)?;
+            let identity = isometry_to_transform(Isometry3::identity());
+            let transform = to_transform_stamped(identity, from.to_string(), to.to_string(), time);
+            return Ok((transform, Vec::new()));
+        }
+        self.lookup_transform_along_path(from, to, time, path)
+    }
+
+    /// Looks up several transforms at the same time, e.g. from many sensor frames into `map`.
+    ///
+    /// Pairs sharing their `to` frame share a single exploration of the tree instead of searching a path each. Each
+    /// pair gets its own result in the order of `pairs`, so one failing lookup does not prevent the others.
+    pub fn lookup_transforms(
+        &self,
+        pairs: &[(&str, &str)],
+        time: &Time,
+    ) -> Vec<Result<TransformStamped, TfError>> {
+        let mut searches: HashMap<&str, GraphSearch> = HashMap::new();
+        pairs
+            .iter()
+            .map(|&(requested_from, requested_to)| {
+                let from = self.slashed_frames.strip(requested_from);
+                let to = self.slashed_frames.strip(requested_to);
+                if from == to {
+                    return self.lookup_transform(requested_from, requested_to, *time);
+                }
+                let search = searches
+                    .entry(to)
+                    .or_insert_with(|| self.explore_transform_graph(to, None, *time, None));
+                // The search runs from `to`, so following the frames each frame was reached from leads there.
+                let mut path = Vec::new();
+                let mut frame = from;
+                while frame != to {
+                    let Some(next) = search.parents.get(frame) else {
+                        // Not connected: let a single lookup build the error.
+                        return self.lookup_transform(requested_from, requested_to, *time);
+                    };
+                    path.push(next.clone());
+                    frame = next;
+                }
+                #[cfg(feature = "metrics")]
+                self.used_frames
+                    .record(std::iter::once(from).chain(path.iter().map(String::as_str)));
+                self.lookup_transform_along_path(from, to, *time, path)
+                    .map(|(transform, _)| transform)
+            })
+            .collect()
+    }
+
+    /// The frames that recent lookups went through, most recently used first.
+    ///
+    /// Frames missing from this list over a long run are candidates for removal from broadcasters.
+    /// Only the last few dozen distinct frames are remembered.
+    #[cfg(feature = "metrics")]
+    pub fn recently_used_frames(&self) -> Vec<String> {
+        self.used_frames.snapshot()
+    }
+
+    /// Looks up a transform within the tree at a given time and checks that it is not stale.
+    ///
+    /// Fails with `TfError::TransformTooOld` if the newest dynamic sample the result was computed from is older than
+    /// `max_age` at `now`. Paths made only of static transforms never go stale.
+    pub fn lookup_transform_fresh(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+        now: rosrust::Time,
+        max_age: Duration,
+    ) -> Result<TransformStamped, TfError> {
+        let (transform, sources) = self.lookup_transform_with_sources(from, to, time)?;
+        let newest = sources
+            .iter()
+            .filter(|source| !source.is_static)
+            .map(|source| source.later)
+            .max();
+        match newest {
+            Some(newest) if newest + max_age < now => {
+                Err(TfError::TransformTooOld(newest, now, max_age))
+            }
+            _ => Ok(transform),
+        }
+    }
+
+    /// The average velocity of `to` relative to `from` between `t0` and `t1`, expressed in `from`.
+    ///
+    /// The linear velocity is the change in translation divided by the elapsed time, the angular velocity the rotation
+    /// between both lookups as a rotation vector divided by the elapsed time. Fails with [`TfError::InvalidArgument`]
+    /// if `t0` and `t1` are equal, or with the error of either lookup.
+    pub fn lookup_velocity(
+        &self,
+        from: &str,
+        to: &str,
+        t0: &Time,
+        t1: &Time,
+    ) -> Result<Twist, TfError> {
+        if t0 == t1 {
+            return Err(TfError::InvalidArgument(
+                "cannot compute a velocity over a zero duration".to_string(),
+            ));
+        }
+        let start = isometry_from_transform(&self.lookup_transform(from, to, *t0)?.transform);
+        let end = isometry_from_transform(&self.lookup_transform(from, to, *t1)?.transform);
+        let elapsed = duration_as_ns_i64(&(*t1 - *t0)) as f64 / 1e9;
+        let linear = (end.translation.vector - start.translation.vector) / elapsed;
+        let angular = (end.rotation * start.rotation.inverse()).scaled_axis() / elapsed;
+        let to_msg = |vector: Vector3D<f64>| Vector3 {
+            x: vector.x,
+            y: vector.y,
+            z: vector.z,
+        };
+        Ok(Twist {
+            linear: to_msg(linear),
+            angular: to_msg(angular),
+        })
+    }
+
+    /// Looks up a transform within the tree at a given time, only traversing the frames in `allowed`.
+    ///
+    /// This bounds which data the result depends on: if the only path between `from` and `to` goes through a frame
+    /// outside of `allowed` (or either end is not allowed itself), the lookup fails.
+    pub fn lookup_transform_restricted(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+        allowed: &HashSet<String>,
+    ) -> Result<TransformStamped, TfError> {
+        let (from, to, path) = self.lookup_path(from, to, time, Some(allowed))?;
+        self.lookup_transform_along_path(from, to, time, path)
+            .map(|(transform, _)| transform)
+    }
+
+    fn lookup_transform_along_path(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+        path: Vec<String>,
+    ) -> Result<(TransformStamped, Vec<TransformSource>), TfError> {
+        let time = self.resolve_latest(from, &path, time)?;
+        let mut tf_list: Vec<Transform> = Vec::new();
+        let mut sources = Vec::new();
+        let mut first = from.to_string();
+        for intermediate in path {
+            let node = TfGraphNode {
+                child: intermediate.clone(),
+                parent: first.clone(),
+            };
+            let (time_cache, inverted) = self
+                .edge(&node.parent, &node.child)
+                .ok_or_else(|| TfError::NoDataForFrame(node.child.clone()))?;
+            let compute = || {
+                time_cache.get_closest_transform(time).map(|(x, stamps)| {
+                    let x = if inverted { get_inverse(&x) } else { x };
+                    (x.transform, stamps)
+                })
+            };
+            let (transform, (earlier, later)) = match &self.interpolation_memo {
+                Some(memo) => memo.get_or_try_insert_with(&node, time, compute)?,
+                None => compute()?,
+            };
+            tf_list.push(transform);
+            sources.push(TransformSource {
+                parent: node.parent,
+                child: node.child,
+                earlier,
+                later,
+                is_static: time_cache.is_static(),
+            });
+            first = intermediate;
+        }
+        let final_tf = chain_transforms(&tf_list);
+        let msg = TransformStamped {
+            child_frame_id: to.to_string(),
+            header: Header {
+                frame_id: from.to_string(),
+                stamp: time,
+                seq: 1,
+            },
+            transform: final_tf,
+        };
+        Ok((msg, sources))
+    }
+
+    /// Expresses `point` in `target_frame` at `time`.
+    ///
+    /// Like every `transform_*` helper, the output header names `target_frame` and keeps the stamp and sequence number
+    /// of the input, whatever `time` the transform is looked up at.
+    pub fn transform_point(
+        &self,
+        point: &PointStamped,
+        target_frame: &str,
+        time: &Time,
+    ) -> Result<PointStamped, TfError> {
+        let transform = self.lookup_transform(target_frame, &point.header.frame_id, *time)?;
+        Ok(PointStamped {
+            header: Header {
+                frame_id: target_frame.to_string(),
+                stamp: point.header.stamp,
+                seq: point.header.seq,
+            },
+            point: transform_point(&transform.transform, &point.point),
+        })
+    }
+
+    /// Expresses `pose` in `target_frame` at `time`, with the header policy of [`TfBuffer::transform_point`].
+    pub fn transform_pose(
+        &self,
+        pose: &PoseStamped,
+        target_frame: &str,
+        time: &Time,
+    ) -> Result<PoseStamped, TfError> {
+        let transform = self.lookup_transform(target_frame, &pose.header.frame_id, *time)?;
+        Ok(PoseStamped {
+            header: Header {
+                frame_id: target_frame.to_string(),
+                stamp: pose.header.stamp,
+                seq: pose.header.seq,
+            },
+            pose: transform_pose(&transform.transform, &pose.pose),
+        })
+    }
+
+    /// Expresses `pose` and its covariance in `target_frame` at `time`. See [`transform_pose_with_covariance`].
+    ///
+    /// The header policy is that of [`TfBuffer::transform_point`].
+    pub fn transform_pose_with_covariance(
+        &self,
+        pose: &PoseWithCovarianceStamped,
+        target_frame: &str,
+        time: &Time,
+    ) -> Result<PoseWithCovarianceStamped, TfError> {
+        let transform = self.lookup_transform(target_frame, &pose.header.frame_id, *time)?;
+        Ok(PoseWithCovarianceStamped {
+            header: Header {
+                frame_id: target_frame.to_string(),
+                stamp: pose.header.stamp,
+                seq: pose.header.seq,
+            },
+            pose: transform_pose_with_covariance(&transform.transform, &pose.pose),
+        })
+    }
+
+    /// Expresses the acceleration `accel` in `target_frame` at `time`.
+    ///
+    /// The header policy is that of [`TfBuffer::transform_point`]. Accelerations are free vectors, so only the
+    /// rotation between the frames is applied. With `remove_gravity`, the gravity vector configured with
+    /// [`TfBufferBuilder::gravity`] is expressed in `target_frame` as well and subtracted, turning accelerometer
+    /// readings into motion accelerations. Fails with [`TfError::InvalidArgument`] if gravity removal is requested but
+    /// no gravity is configured.
+    pub fn transform_accel(
+        &self,
+        accel: &Vector3Stamped,
+        target_frame: &str,
+        time: &Time,
+        remove_gravity: bool,
+    ) -> Result<Vector3Stamped, TfError> {
+        let rotate = |frame: &str, vector: &Vector3| {
+            let transform = self.lookup_transform(target_frame, frame, *time)?;
+            let rotation = isometry_from_transform(&transform.transform).rotation;
+            Ok::<_, TfError>(rotation * Vector3D::new(vector.x, vector.y, vector.z))
+        };
+        let mut vector = rotate(&accel.header.frame_id, &accel.vector)?;
+        if remove_gravity {
+            let (gravity_frame, gravity) = self.gravity.as_ref().ok_or_else(|| {
+                TfError::InvalidArgument("no gravity configured to remove".to_string())
+            })?;
+            vector -= rotate(gravity_frame, gravity)?;
+        }
+        Ok(Vector3Stamped {
+            header: Header {
+                frame_id: target_frame.to_string(),
+                stamp: accel.header.stamp,
+                seq: accel.header.seq,
+            },
+            vector: Vector3 {
+                x: vector.x,
+                y: vector.y,
+                z: vector.z,
+            },
+        })
+    }
+
+    /// Looks up the transform from `a_frame` to `b_frame`, explicitly routed through `common_frame`.
+    ///
+    /// Meant for multi-robot setups sharing e.g. a `map` frame. Unlike [`TfBuffer::lookup_transform`], this fails
+    /// with [`TfError::CouldNotFindTransform`] naming `common_frame` if either frame is not connected to it, instead of
+    /// silently using another route. The zero time stands for the latest time both halves have in common.
+    pub fn lookup_across_common(
+        &self,
+        a_frame: &str,
+        b_frame: &str,
+        common_frame: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        let mut time = time.into().0;
+        if time.nanos() == 0 {
+            // A zero common time means a half only has static edges, which are valid at any time.
+            let latest = [
+                self.lookup_latest_common_time(a_frame, common_frame)?,
+                self.lookup_latest_common_time(common_frame, b_frame)?,
+            ];
+            time = latest
+                .into_iter()
+                .filter(|latest| latest.nanos() != 0)
+                .min()
+                .unwrap_or_default();
+        }
+        let a_to_common = self.lookup_transform(a_frame, common_frame, time)?;
+        let common_to_b = self.lookup_transform(common_frame, b_frame, time)?;
+        let result = chain_transforms(&[a_to_common.transform, common_to_b.transform]);
+        Ok(to_transform_stamped(
+            result,
+            a_frame.to_string(),
+            b_frame.to_string(),
+            time,
+        ))
+    }
+
+    pub(crate) fn lookup_transform_with_time_travel(
+        &self,
+        to: &str,
+        time2: rosrust::Time,
+        from: &str,
+        time1: rosrust::Time,
+        fixed_frame: &str,
+    ) -> Result<TransformStamped, TfError> {
+        let tf1 = self.lookup_transform(from, fixed_frame, time1)?;
+        let tf2 = self.lookup_transform(to, fixed_frame, time2)?;
+        let transforms = get_inverse(&tf1);
+        let result = chain_transforms(&[tf2.transform, transforms.transform]);
+        Ok(to_transform_stamped(
+            result,
+            from.to_string(),
+            to.to_string(),
+            time1,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        tf_individual_transform_chain::TransformStorage,
+        transforms::{
+            self,
+            geometry_msgs::{Point, Quaternion},
+            TransformStampedBuilder,
+        },
+    };
+
+    const PARENT: &str = "parent";
+    const CHILD0: &str = "child0";
+    const CHILD1: &str = "child1";
+
+    /// This function builds a tree consisting of the following items:
+    /// * a world coordinate frame
+    /// * an item in the world frame at (1,0,0)
+    /// * base_link of a robot starting at (0,0,0) and progressing at (0,t,0) where t is time in seconds
+    /// * a camera which is (0.5, 0, 0) from the base_link
+    fn build_test_tree(buffer: &mut TfBuffer, time: f64) {
+        let nsecs = ((time - ((time.floor() as i64) as f64)) * 1E9) as u32;
+
+        let world_to_item = TransformStamped {
+            child_frame_id: "item".to_string(),
+            header: Header {
+                frame_id: "world".to_string(),
+                stamp: rosrust::Time {
+                    sec: time.floor() as u32,
+                    nsec: nsecs,
+                },
+                seq: 1,
+            },
+            transform: Transform {
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+                translation: Vector3 {
+                    x: 1f64,
+                    y: 0f64,
+                    z: 0f64,
+                },
+            },
+        };
+        buffer.add_transform(&world_to_item, true);
+
+        let world_to_base_link = TransformStamped {
+            child_frame_id: "base_link".to_string(),
+            header: Header {
+                frame_id: "world".to_string(),
+                stamp: rosrust::Time {
+                    sec: time.floor() as u32,
+                    nsec: nsecs,
+                },
+                seq: 1,
+            },
+            transform: Transform {
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+                translation: Vector3 {
+                    x: 0f64,
+                    y: time,
+                    z: 0f64,
+                },
+            },
+        };
+        buffer.add_transform(&world_to_base_link, false);
+
+        let base_link_to_camera = TransformStamped {
+            child_frame_id: "camera".to_string(),
+            header: Header {
+                frame_id: "base_link".to_string(),
+                stamp: rosrust::Time {
+                    sec: time.floor() as u32,
+                    nsec: nsecs,
+                },
+                seq: 1,
+            },
+            transform: Transform {
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+                translation: Vector3 {
+                    x: 0.5f64,
+                    y: 0f64,
+                    z: 0f64,
+                },
+            },
+        };
+        buffer.add_transform(&base_link_to_camera, true);
+    }
+
+    /// Tests a basic lookup
+    #[test]
+    fn test_basic_tf_lookup() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        let res = tf_buffer.lookup_transform("camera", "item", rosrust::Time { sec: 0, nsec: 0 });
+        let expected = TransformStamped {
+            child_frame_id: "item".to_string(),
+            header: Header {
+                frame_id: "camera".to_string(),
+                stamp: rosrust::Time { sec: 0, nsec: 0 },
+                seq: 1,
+            },
+            transform: Transform {
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+                translation: Vector3 {
+                    x: 0.5f64,
+                    y: 0f64,
+                    z: 0f64,
+                },
+            },
+        };
+        assert_eq!(res.unwrap(), expected);
+    }
+
+    /// Tests an interpolated lookup.
+    #[test]
+    fn test_basic_tf_interpolation() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let res = tf_buffer.lookup_transform(
+            "camera",
+            "item",
+            rosrust::Time {
+                sec: 0,
+                nsec: 700_000_000,
+            },
+        );
+        let expected = TransformStamped {
+            child_frame_id: "item".to_string(),
+            header: Header {
+                frame_id: "camera".to_string(),
+                stamp: rosrust::Time {
+                    sec: 0,
+                    nsec: 700_000_000,
+                },
+                seq: 1,
+            },
+            transform: Transform {
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+                translation: Vector3 {
+                    x: 0.5f64,
+                    y: -0.7f64,
+                    z: 0f64,
+                },
+            },
+        };
+        assert_eq!(res.unwrap(), expected);
+    }
+
+    /// Tests an interpolated lookup.
+    #[test]
+    fn test_basic_tf_time_travel() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let res = tf_buffer.lookup_transform_with_time_travel(
+            "camera",
+            rosrust::Time {
+                sec: 0,
+                nsec: 400_000_000,
+            },
+            "camera",
+            rosrust::Time {
+                sec: 0,
+                nsec: 700_000_000,
+            },
+            "item",
+        );
+        let expected = TransformStamped {
+            child_frame_id: "camera".to_string(),
+            header: Header {
+                frame_id: "camera".to_string(),
+                stamp: rosrust::Time {
+                    sec: 0,
+                    nsec: 700_000_000,
+                },
+                seq: 1,
+            },
+            transform: Transform {
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+                translation: Vector3 {
+                    x: 0f64,
+                    y: 0.3f64,
+                    z: 0f64,
+                },
+            },
+        };
+        assert_approx_eq(res.unwrap(), expected);
+    }
+
+    /// Tests that an interpolated lookup reports the samples it was computed from.
+    #[test]
+    fn test_lookup_transform_with_sources() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let (_, sources) = tf_buffer
+            .lookup_transform_with_sources(
+                "camera",
+                "item",
+                rosrust::Time {
+                    sec: 0,
+                    nsec: 700_000_000,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            sources,
+            vec![
+                TransformSource {
+                    parent: "camera".to_string(),
+                    child: "base_link".to_string(),
+                    earlier: Time::from_seconds(1),
+                    later: Time::from_seconds(1),
+                    is_static: true,
+                },
+                TransformSource {
+                    parent: "base_link".to_string(),
+                    child: "world".to_string(),
+                    earlier: Time::from_seconds(0),
+                    later: Time::from_seconds(1),
+                    is_static: false,
+                },
+                TransformSource {
+                    parent: "world".to_string(),
+                    child: "item".to_string(),
+                    earlier: Time::from_seconds(1),
+                    later: Time::from_seconds(1),
+                    is_static: true,
+                },
+            ]
+        );
+    }
+
+    /// Tests that every lookup composed with its reverse lookup yields the identity.
+    #[test]
+    fn test_inverse_symmetry() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let frames = ["world", "item", "base_link", "camera"];
+        for time in [
+            rosrust::Time { sec: 0, nsec: 0 },
+            rosrust::Time {
+                sec: 0,
+                nsec: 300_000_000,
+            },
+            rosrust::Time { sec: 1, nsec: 0 },
+        ] {
+            for a in frames {
+                for b in frames.iter().filter(|&&b| b != a) {
+                    let forward = tf_buffer.lookup_transform(a, b, time).unwrap();
+                    let backward = tf_buffer.lookup_transform(b, a, time).unwrap();
+                    let round_trip = chain_transforms(&[forward.transform, backward.transform]);
+                    assert!(round_trip.translation.x.abs() < 1e-9, "{a} <-> {b}");
+                    assert!(round_trip.translation.y.abs() < 1e-9, "{a} <-> {b}");
+                    assert!(round_trip.translation.z.abs() < 1e-9, "{a} <-> {b}");
+                    assert!(round_trip.rotation.x.abs() < 1e-9, "{a} <-> {b}");
+                    assert!(round_trip.rotation.y.abs() < 1e-9, "{a} <-> {b}");
+                    assert!(round_trip.rotation.z.abs() < 1e-9, "{a} <-> {b}");
+                    assert!(
+                        (round_trip.rotation.w.abs() - 1.0).abs() < 1e-9,
+                        "{a} <-> {b}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Tests that only published edges are stored, while both directions can be traversed.
+    #[test]
+    fn test_published_edges_only() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        assert_eq!(tf_buffer.transform_data.len(), 3);
+        assert!(!tf_buffer.transform_data.contains_key(&TfGraphNode {
+            child: "world".to_string(),
+            parent: "base_link".to_string(),
+        }));
+        assert!(tf_buffer.parent_transform_index["camera"].contains("base_link"));
+        assert_eq!(
+            tf_buffer
+                .retrieve_transform_path(
+                    "camera".to_string(),
+                    "item".to_string(),
+                    rosrust::Time::new()
+                )
+                .unwrap(),
+            vec![
+                "base_link".to_string(),
+                "world".to_string(),
+                "item".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_transform() {
+        let mut tf_buffer = TfBuffer::new();
+        let transform00 = TransformStamped {
+            header: Header {
+                frame_id: PARENT.to_string(),
+                stamp: rosrust::Time { sec: 0, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: CHILD0.to_string(),
+            ..Default::default()
+        };
+        let transform01 = TransformStamped {
+            header: Header {
+                frame_id: PARENT.to_string(),
+                stamp: rosrust::Time { sec: 1, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: CHILD0.to_string(),
+            ..Default::default()
+        };
+        let transform1 = TransformStamped {
+            header: Header {
+                frame_id: PARENT.to_string(),
+                ..Default::default()
+            },
+            child_frame_id: CHILD1.to_string(),
+            ..Default::default()
+        };
+        let transform0_key = TfGraphNode {
+            child: CHILD0.to_owned(),
+            parent: PARENT.to_owned(),
+        };
+        let transform1_key = TfGraphNode {
+            child: CHILD1.to_owned(),
+            parent: PARENT.to_owned(),
+        };
+        let static_tf = false;
+        tf_buffer.add_transform(&transform00, static_tf);
+        assert_eq!(tf_buffer.child_transform_index.len(), 1);
+        assert!(tf_buffer.child_transform_index.contains_key(PARENT));
+        let children = tf_buffer.child_transform_index.get(PARENT).unwrap();
+        assert_eq!(children.len(), 1);
+        assert!(children.contains(CHILD0));
+        assert_eq!(tf_buffer.transform_data.len(), 1);
+        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
+        let data = tf_buffer.transform_data.get(&transform0_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 1);
+
+        tf_buffer.add_transform(&transform01, static_tf);
+        assert_eq!(tf_buffer.child_transform_index.len(), 1);
+        assert!(tf_buffer.child_transform_index.contains_key(PARENT));
+        let children = tf_buffer.child_transform_index.get(PARENT).unwrap();
+        assert_eq!(children.len(), 1);
+        assert!(children.contains(CHILD0));
+        assert_eq!(tf_buffer.transform_data.len(), 1);
+        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
+        let data = tf_buffer.transform_data.get(&transform0_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 2);
+
+        tf_buffer.add_transform(&transform1, static_tf);
+        assert_eq!(tf_buffer.child_transform_index.len(), 1);
+        assert!(tf_buffer.child_transform_index.contains_key(PARENT));
+        let children = tf_buffer.child_transform_index.get(PARENT).unwrap();
+        assert_eq!(children.len(), 2);
+        assert!(children.contains(CHILD0));
+        assert!(children.contains(CHILD1));
+        assert_eq!(tf_buffer.transform_data.len(), 2);
+        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
+        assert!(tf_buffer.transform_data.contains_key(&transform1_key));
+        let data = tf_buffer.transform_data.get(&transform0_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 2);
+        let data = tf_buffer.transform_data.get(&transform1_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 1);
+    }
+
+    #[test]
+    fn test_cache_duration() {
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(1));
+        let transform00 = TransformStamped {
+            header: Header {
+                frame_id: PARENT.to_string(),
+                stamp: rosrust::Time { sec: 0, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: CHILD0.to_string(),
+            ..Default::default()
+        };
+        let transform01 = TransformStamped {
+            header: Header {
+                frame_id: PARENT.to_string(),
+                stamp: rosrust::Time { sec: 1, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: CHILD0.to_string(),
+            ..Default::default()
+        };
+        let transform02 = TransformStamped {
+            header: Header {
+                frame_id: PARENT.to_string(),
+                stamp: rosrust::Time { sec: 2, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: CHILD0.to_string(),
+            ..Default::default()
+        };
+        let transform0_key = TfGraphNode {
+            child: CHILD0.to_owned(),
+            parent: PARENT.to_owned(),
+        };
+
+        let static_tf = false;
+        tf_buffer.add_transform(&transform00, static_tf);
+        assert_eq!(tf_buffer.child_transform_index.len(), 1);
+        assert_eq!(tf_buffer.transform_data.len(), 1);
+        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
+        let data = tf_buffer.transform_data.get(&transform0_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 1);
+        assert_eq!(
+            data.unwrap().transform_chain.get(0).unwrap().header.stamp,
+            Time::from_nanos(0)
+        );
+
+        tf_buffer.add_transform(&transform01, static_tf);
+        assert_eq!(tf_buffer.child_transform_index.len(), 1);
+        assert_eq!(tf_buffer.transform_data.len(), 1);
+        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
+        let data = tf_buffer.transform_data.get(&transform0_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 2);
+        assert_eq!(
+            data.unwrap().transform_chain.get(0).unwrap().header.stamp,
+            Time::from_nanos(0)
+        );
+        assert_eq!(
+            data.unwrap().transform_chain.get(1).unwrap().header.stamp,
+            Time::from_nanos(1_000_000_000)
+        );
+
+        tf_buffer.add_transform(&transform02, static_tf);
+        assert_eq!(tf_buffer.child_transform_index.len(), 1);
+        assert_eq!(tf_buffer.transform_data.len(), 1);
+        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
+        let data = tf_buffer.transform_data.get(&transform0_key);
+        assert!(data.is_some());
+        assert_eq!(data.unwrap().transform_chain.len(), 2);
+        assert_eq!(
+            data.unwrap().transform_chain.get(0).unwrap().header.stamp,
+            Time::from_nanos(1_000_000_000)
+        );
+        assert_eq!(
+            data.unwrap().transform_chain.get(1).unwrap().header.stamp,
+            Time::from_nanos(2_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_transform_point() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = Time {
+            sec: 0,
+            nsec: 500_000_000,
+        };
+        let mut origin = PointStamped::default();
+        origin.header.frame_id = "camera".to_string();
+        origin.header.stamp = Time::from_seconds(1);
+        let result = tf_buffer.transform_point(&origin, "world", &time).unwrap();
+        assert_eq!(result.header.frame_id, "world");
+        assert_eq!(result.header.stamp, origin.header.stamp);
+        assert!((result.point.x - 0.5).abs() < 1e-9);
+        assert!((result.point.y - 0.5).abs() < 1e-9);
+        assert!(result.point.z.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_transform_pose_with_covariance() {
+        let mut tf_buffer = TfBuffer::new();
+        let transform = TransformStampedBuilder::new()
+            .parent("map")
+            .child("base_link")
+            .translation(1.0, 2.0, 0.0)
+            .rotation_rpy(0.0, 0.0, std::f64::consts::FRAC_PI_2)
+            .build();
+        tf_buffer.add_published_transform(&transform, true).unwrap();
+
+        let mut pose = PoseWithCovarianceStamped::default();
+        pose.header.frame_id = "base_link".to_string();
+        pose.header.stamp = Time::from_seconds(1);
+        pose.header.seq = 7;
+        pose.pose.pose.position.x = 3.0;
+        pose.pose.pose.orientation.w = 1.0;
+        for (index, variance) in [1.0, 2.0, 3.0, 4.0, 5.0, 6.0].into_iter().enumerate() {
+            pose.pose.covariance[index * 7] = variance;
+        }
+        let time = Time::new();
+        let in_map = tf_buffer
+            .transform_pose_with_covariance(&pose, "map", &time)
+            .unwrap();
+        assert_eq!(in_map.header.frame_id, "map");
+        assert_eq!(in_map.header.stamp, pose.header.stamp);
+        assert_eq!(in_map.header.seq, 7);
+        assert!((in_map.pose.pose.position.x - 1.0).abs() < 1e-9);
+        assert!((in_map.pose.pose.position.y - 5.0).abs() < 1e-9);
+        // The yaw swaps the x and y variances of both the position and the orientation blocks.
+        let expected = [2.0, 1.0, 3.0, 5.0, 4.0, 6.0];
+        for row in 0..6 {
+            for column in 0..6 {
+                let value = in_map.pose.covariance[row * 6 + column];
+                let expected = if row == column { expected[row] } else { 0.0 };
+                assert!((value - expected).abs() < 1e-9, "{row} {column}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_transform_pose_round_trip() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = Time {
+            sec: 0,
+            nsec: 500_000_000,
+        };
+        let orientation = TransformStampedBuilder::new()
+            .rotation_rpy(0.1, -0.2, 0.3)
+            .build()
+            .transform
+            .rotation;
+        let mut pose = PoseStamped::default();
+        pose.header.frame_id = "camera".to_string();
+        pose.header.stamp = time;
+        pose.pose.position.x = 1.0;
+        pose.pose.position.z = -2.0;
+        pose.pose.orientation = orientation;
+
+        let in_world = tf_buffer.transform_pose(&pose, "world", &time).unwrap();
+        assert!((in_world.pose.position.x - 1.5).abs() < 1e-9);
+        assert!((in_world.pose.position.y - 0.5).abs() < 1e-9);
+        let back = tf_buffer
+            .transform_pose(&in_world, "camera", &time)
+            .unwrap();
+        assert_eq!(back.header, pose.header);
+        let (expected, actual) = (&pose.pose, &back.pose);
+        for (expected, actual) in [
+            (expected.position.x, actual.position.x),
+            (expected.position.y, actual.position.y),
+            (expected.position.z, actual.position.z),
+            (expected.orientation.x, actual.orientation.x),
+            (expected.orientation.y, actual.orientation.y),
+            (expected.orientation.z, actual.orientation.z),
+            (expected.orientation.w, actual.orientation.w),
+        ] {
+            assert!((expected - actual).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_lookup_latest_common_time() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        assert_eq!(
+            tf_buffer
+                .lookup_latest_common_time("item", "camera")
+                .unwrap(),
+            Time::from_seconds(1)
+        );
+        assert_eq!(
+            tf_buffer
+                .lookup_latest_common_time("camera", "world")
+                .unwrap(),
+            Time::from_seconds(1)
+        );
+        // Static paths are valid at any time.
+        assert_eq!(
+            tf_buffer
+                .lookup_latest_common_time("world", "item")
+                .unwrap(),
+            Time::new()
+        );
+        assert!(matches!(
+            tf_buffer.lookup_latest_common_time("world", "map"),
+            Err(TfError::CouldNotFindTransform(..))
+        ));
+    }
+
+    /// Tests that a zero time lookup uses one time for all edges, even if some were updated more recently.
+    #[test]
+    fn test_zero_time_uses_common_time() {
+        let mut tf_buffer = TfBuffer::new();
+        for sec in 0..3 {
+            let base_link = TransformStampedBuilder::new()
+                .parent("world")
+                .child("base_link")
+                .stamp(Time::from_seconds(sec))
+                .translation(sec as f64, 0.0, 0.0)
+                .build();
+            tf_buffer
+                .add_published_transform(&base_link, false)
+                .unwrap();
+        }
+        for sec in 0..2 {
+            let camera = TransformStampedBuilder::new()
+                .parent("base_link")
+                .child("camera")
+                .stamp(Time::from_seconds(sec))
+                .translation(0.0, 10.0 * sec as f64, 0.0)
+                .build();
+            tf_buffer.add_published_transform(&camera, false).unwrap();
+        }
+        let latest = tf_buffer
+            .lookup_transform("world", "camera", Time::new())
+            .unwrap();
+        assert_eq!(latest.header.stamp, Time::from_seconds(1));
+        assert_approx_eq(
+            latest,
+            tf_buffer
+                .lookup_transform("world", "camera", Time::from_seconds(1))
+                .unwrap(),
+        );
+        let translation = tf_buffer
+            .lookup_transform("world", "camera", Time::new())
+            .unwrap()
+            .transform
+            .translation;
+        assert!((translation.x - 1.0).abs() < 1e-9);
+        assert!((translation.y - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lookup_latest_transform() {
+        let mut tf_buffer = TfBuffer::new();
+        for sec in 1..4 {
+            let base_link = TransformStampedBuilder::new()
+                .parent("world")
+                .child("base_link")
+                .stamp(Time::from_seconds(sec))
+                .translation(sec as f64, 0.0, 0.0)
+                .build();
+            tf_buffer
+                .add_published_transform(&base_link, false)
+                .unwrap();
+        }
+        for sec in [0, 2] {
+            let arm = TransformStampedBuilder::new()
+                .parent("base_link")
+                .child("arm")
+                .stamp(Time::from_seconds(sec))
+                .translation(0.0, sec as f64, 0.0)
+                .build();
+            tf_buffer.add_published_transform(&arm, false).unwrap();
+        }
+        let camera = TransformStampedBuilder::new()
+            .parent("arm")
+            .child("camera")
+            .translation(0.0, 0.0, 1.0)
+            .build();
+        tf_buffer.add_published_transform(&camera, true).unwrap();
+
+        let latest = tf_buffer
+            .lookup_latest_transform("world", "camera")
+            .unwrap();
+        assert_eq!(latest.header.stamp, Time::from_seconds(2));
+        assert_eq!(
+            latest.transform.translation,
+            Vector3 {
+                x: 2.0,
+                y: 2.0,
+                z: 1.0
+            }
+        );
+        assert_eq!(
+            tf_buffer
+                .lookup_latest_transform("camera", "world")
+                .unwrap()
+                .header
+                .stamp,
+            Time::from_seconds(2)
+        );
+        assert_eq!(
+            tf_buffer
+                .lookup_latest_transform("world", "base_link")
+                .unwrap()
+                .header
+                .stamp,
+            Time::from_seconds(3)
+        );
+        assert!(tf_buffer.lookup_latest_transform("world", "map").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_stamps() {
+        let mut tf_buffer = TfBuffer::builder()
+            .extrapolation(Extrapolation::LinearUpTo(Duration::from_seconds(1)))
+            .build();
+        for (sec, x) in [(1, 1.0), (2, 2.0), (2, 3.0)] {
+            let transform = TransformStampedBuilder::new()
+                .parent("world")
+                .child("base_link")
+                .stamp(Time::from_seconds(sec))
+                .translation(x, 0.0, 0.0)
+                .build();
+            tf_buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform],
+                },
+                false,
+            );
+        }
+        // The republished sample replaces the stored one instead of being counted as reordered.
+        assert_eq!(tf_buffer.ingest_stats().reordered, 0);
+        assert_eq!(tf_buffer.frame_statistics()[0].buffer_length, 2);
+        for (time, expected) in [(2.0, 3.0), (1.5, 2.0), (2.5, 4.0)] {
+            let transform = tf_buffer
+                .lookup_transform("world", "base_link", time)
+                .unwrap()
+                .transform;
+            assert!((transform.translation.x - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_reject_stale_sample() {
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(10));
+        let transform = |sec: u32| {
+            TransformStampedBuilder::new()
+                .parent("world")
+                .child("base_link")
+                .stamp(Time::from_seconds(sec))
+                .build()
+        };
+        for sec in [20, 21, 5] {
+            tf_buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform(sec)],
+                },
+                false,
+            );
+        }
+        tf_buffer.extend_sorted(&[(transform(6), false)]);
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![transform(15)],
+            },
+            false,
+        );
+        let chain = &tf_buffer.transform_data[&TfGraphNode {
+            child: "base_link".to_string(),
+            parent: "world".to_string(),
+        }];
+        assert_eq!(chain.sample_count(), 3);
+        assert_eq!(
+            chain.stamp_range(),
+            Some((Time::from_seconds(15), Time::from_seconds(21)))
+        );
+        // Only the sample at 15 was sorted into place, the stale ones were never stored.
+        assert_eq!(tf_buffer.ingest_stats().reordered, 1);
+        assert!(tf_buffer
+            .lookup_transform("world", "base_link", Time::from_seconds(5))
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_cache_duration() {
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(10));
+        for sec in 0..10 {
+            let transform = TransformStampedBuilder::new()
+                .parent(PARENT)
+                .child(CHILD0)
+                .stamp(Time::from_seconds(sec))
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+        assert_eq!(tf_buffer.cache_duration(), Duration::from_seconds(10));
+        assert!(tf_buffer
+            .lookup_transform(PARENT, CHILD0, Time::from_seconds(2))
+            .is_ok());
+
+        tf_buffer.set_cache_duration(Duration::from_seconds(3));
+        assert_eq!(tf_buffer.cache_duration(), Duration::from_seconds(3));
+        let key = TfGraphNode {
+            child: CHILD0.to_string(),
+            parent: PARENT.to_string(),
+        };
+        assert_eq!(
+            tf_buffer.transform_data[&key].stamp_range(),
+            Some((Time::from_seconds(6), Time::from_seconds(9)))
+        );
+        assert!(tf_buffer
+            .lookup_transform(PARENT, CHILD0, Time::from_seconds(2))
+            .is_err());
+
+        // Growing the window again does not bring dropped samples back.
+        tf_buffer.set_cache_duration(Duration::from_seconds(10));
+        assert_eq!(tf_buffer.transform_data[&key].sample_count(), 4);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(30));
+        build_test_tree(&mut tf_buffer, 0f64);
+        tf_buffer.clear();
+        assert!(tf_buffer.frame_names().is_empty());
+        assert!(tf_buffer
+            .lookup_transform("camera", "item", Time::new())
+            .is_err());
+        assert_eq!(tf_buffer.cache_duration(), Duration::from_seconds(30));
+
+        build_test_tree(&mut tf_buffer, 1f64);
+        assert!(tf_buffer
+            .lookup_transform("camera", "item", Time::new())
+            .is_ok());
+    }
+
+    /// Tests that a jump back in time clears the buffer only if enabled.
+    #[test]
+    fn test_clear_on_time_jump() {
+        let message = |sec| TFMessage {
+            transforms: vec![TransformStampedBuilder::new()
+                .parent(PARENT)
+                .child(CHILD0)
+                .stamp(Time::from_seconds(sec))
+                .build()],
+        };
+        let mut tf_buffer = TfBuffer::builder()
+            .clear_on_time_jump(Duration::from_seconds(1))
+            .build();
+        let mut unguarded = TfBuffer::new();
+        for sec in [100, 101, 0] {
+            tf_buffer.handle_incoming_transforms(message(sec), false);
+            unguarded.handle_incoming_transforms(message(sec), false);
+        }
+        assert_eq!(
+            tf_buffer.lookup_latest_common_time(PARENT, CHILD0).unwrap(),
+            Time::from_seconds(0)
+        );
+        assert_eq!(
+            unguarded.lookup_latest_common_time(PARENT, CHILD0).unwrap(),
+            Time::from_seconds(101)
+        );
+
+        // Jumps are detected on frames received with a leading slash too.
+        let mut tf_buffer = TfBuffer::builder()
+            .clear_on_time_jump(Duration::from_seconds(1))
+            .build();
+        for sec in [100, 101, 0] {
+            let mut message = message(sec);
+            for transform in &mut message.transforms {
+                transform.header.frame_id.insert(0, '/');
+                transform.child_frame_id.insert(0, '/');
+            }
+            tf_buffer.handle_incoming_transforms(message, false);
+        }
+        assert_eq!(
+            tf_buffer.lookup_latest_common_time(PARENT, CHILD0).unwrap(),
+            Time::from_seconds(0)
+        );
+    }
+
+    #[test]
+    fn test_frame_names() {
+        let mut tf_buffer = TfBuffer::new();
+        assert!(tf_buffer.frame_names().is_empty());
+        build_test_tree(&mut tf_buffer, 0f64);
+        assert_eq!(
+            tf_buffer.frame_names(),
+            vec!["base_link", "camera", "item", "world"]
+        );
+        // Leaves only ever appear as children.
+        assert!(tf_buffer.frame_exists("camera"));
+        assert!(tf_buffer.frame_exists("world"));
+        assert!(!tf_buffer.frame_exists("map"));
+    }
+
+    #[test]
+    fn test_all_frames_as_yaml() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let yaml = tf_buffer.all_frames_as_yaml();
+        assert!(yaml.contains(
+            "base_link:\n  parent: 'world'\n  oldest_transform: 0.000000000\n  newest_transform: 1.000000000\n  buffer_length: 2\n"
+        ));
+        assert!(yaml.contains("camera:\n  parent: 'base_link'\n"));
+        assert!(yaml.contains("item:\n  parent: 'world'\n"));
+        // Inverse edges are not listed.
+        assert!(!yaml.contains("world:"));
+        assert_eq!(yaml.lines().count(), 3 * 5);
+    }
+
+    #[test]
+    fn test_all_frames_as_dot() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let dot = tf_buffer.all_frames_as_dot(Some("camera"));
+        assert!(dot.starts_with("digraph tf {\n"));
+        assert!(dot.ends_with("}\n"));
+        let edges: Vec<_> = dot
+            .lines()
+            .filter_map(|line| line.trim().split_once(" [label="))
+            .map(|(edge, _)| edge)
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                "\"base_link\" -> \"camera\"",
+                "\"world\" -> \"base_link\"",
+                "\"world\" -> \"item\"",
+            ]
+        );
+        assert!(
+            dot.contains("\"world\" -> \"base_link\" [label=\"stamp: 1.000000000\\nbuffer: 2\"];")
+        );
+        assert!(dot.contains("\"camera\" [style=filled"));
+        assert!(!tf_buffer.all_frames_as_dot(None).contains("style"));
+    }
+
+    #[test]
+    fn test_linear_extrapolation() {
+        let mut tf_buffer = TfBuffer::builder()
+            .extrapolation(Extrapolation::LinearUpTo(Duration::from_nanos(500_000_000)))
+            .build();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+
+        let time = Time {
+            sec: 1,
+            nsec: 400_000_000,
+        };
+        let result = tf_buffer
+            .lookup_transform("world", "base_link", time)
+            .unwrap();
+        assert!((result.transform.translation.y - 1.4).abs() < 1e-9);
+        let result = tf_buffer.lookup_transform("camera", "item", time).unwrap();
+        assert!((result.transform.translation.y + 1.4).abs() < 1e-9);
+        assert!(tf_buffer
+            .lookup_transform("world", "base_link", Time::from_seconds(2))
+            .is_err());
+
+        let mut default_buffer = TfBuffer::new();
+        build_test_tree(&mut default_buffer, 0f64);
+        build_test_tree(&mut default_buffer, 1f64);
+        assert!(default_buffer
+            .lookup_transform("world", "base_link", time)
+            .is_err());
+    }
+
+    /// Tests that a high-rate edge keeps exactly the samples within the cache duration.
+    #[test]
+    fn test_high_rate_eviction() {
+        let mut tf_buffer = TfBuffer::new();
+        let mut transform = TransformStampedBuilder::new()
+            .parent(PARENT)
+            .child(CHILD0)
+            .build();
+        for millis in 0..12_000 {
+            transform.header.stamp = Time::from_nanos(millis * 1_000_000);
+            tf_buffer.add_transform(&transform, false);
+        }
+        let chain = &tf_buffer.transform_data[&TfGraphNode {
+            child: CHILD0.to_owned(),
+            parent: PARENT.to_owned(),
+        }]
+            .transform_chain;
+        assert!(matches!(chain, TransformStorage::Ring(_)));
+        assert_eq!(chain.len(), 10_001);
+        assert_eq!(
+            chain.first().unwrap().header.stamp,
+            Time::from_nanos(1_999_000_000)
+        );
+        assert_eq!(
+            chain.last().unwrap().header.stamp,
+            Time::from_nanos(11_999_000_000)
+        );
+    }
+
+    /// Tests that both storage strategies keep samples sorted and pruned identically.
+    #[test]
+    fn test_storage_strategies() {
+        let stamps = [3, 1, 2, 5, 4, 6];
+        let key = TfGraphNode {
+            child: CHILD0.to_owned(),
+            parent: PARENT.to_owned(),
+        };
+        for storage in [
+            StorageStrategy::ReadOptimized,
+            StorageStrategy::WriteOptimized,
+        ] {
+            let mut tf_buffer = TfBuffer::builder()
+                .cache_duration(Duration::from_seconds(3))
+                .storage(storage)
+                .build();
+            for sec in stamps {
+                let transform = TransformStamped {
+                    header: Header {
+                        frame_id: PARENT.to_string(),
+                        stamp: rosrust::Time { sec, nsec: 0 },
+                        ..Default::default()
+                    },
+                    child_frame_id: CHILD0.to_string(),
+                    ..Default::default()
+                };
+                tf_buffer.add_transform(&transform, false);
+            }
+            let chain = &tf_buffer.transform_data.get(&key).unwrap().transform_chain;
+            let kept: Vec<_> = (0..chain.len())
+                .map(|i| chain.get(i).unwrap().header.stamp.sec)
+                .collect();
+            assert_eq!(kept, vec![3, 4, 5, 6], "{storage:?}");
+        }
+    }
+
+    /// Tests that every accepted time form gives the same lookup.
+    #[test]
+    fn test_lookup_transform_time_forms() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = rosrust::Time {
+            sec: 0,
+            nsec: 700_000_000,
+        };
+        let expected = tf_buffer.lookup_transform("camera", "item", time).unwrap();
+        assert_eq!(
+            tf_buffer
+                .lookup_transform("camera", "item", 700_000_000_i64)
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            tf_buffer.lookup_transform("camera", "item", 0.7).unwrap(),
+            expected
+        );
+    }
+
+    /// Tests that a static value applies outside the range of dynamic samples received on the same edge.
+    #[test]
+    fn test_static_with_dynamic_overrides() {
+        let sample = |sec, x| {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = PARENT.to_string();
+            transform.header.stamp = rosrust::Time { sec, nsec: 0 };
+            transform.child_frame_id = CHILD0.to_string();
+            transform.transform.translation.x = x;
+            transform.transform.rotation.w = 1.0;
+            TFMessage {
+                transforms: vec![transform],
+            }
+        };
+        let mut tf_buffer = TfBuffer::new();
+        tf_buffer.handle_incoming_transforms(sample(0, 1.0), true);
+        tf_buffer.handle_incoming_transforms(sample(4, 4.0), false);
+        tf_buffer.handle_incoming_transforms(sample(6, 6.0), false);
+        assert!(!tf_buffer.is_rigidly_attached(PARENT, CHILD0));
+
+        let x_at = |sec| {
+            tf_buffer
+                .lookup_transform(PARENT, CHILD0, rosrust::Time { sec, nsec: 0 })
+                .unwrap()
+                .transform
+                .translation
+                .x
+        };
+        assert!((x_at(2) - 1.0).abs() < 1e-9);
+        assert!((x_at(5) - 5.0).abs() < 1e-9);
+        assert!((x_at(8) - 1.0).abs() < 1e-9);
+        assert!((x_at(0) - 6.0).abs() < 1e-9);
+
+        let mut tf_buffer = TfBuffer::new();
+        tf_buffer.handle_incoming_transforms(sample(4, 4.0), false);
+        tf_buffer.handle_incoming_transforms(sample(6, 6.0), false);
+        tf_buffer.handle_incoming_transforms(sample(0, 1.0), true);
+        let res = tf_buffer
+            .lookup_transform(PARENT, CHILD0, rosrust::Time { sec: 2, nsec: 0 })
+            .unwrap();
+        assert!((res.transform.translation.x - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lookup_transform_f32() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        for time in [0.0, 0.3, 1.0] {
+            let single = tf_buffer
+                .lookup_transform_f32("camera", "item", time)
+                .unwrap();
+            let double = isometry_from_transform(
+                &tf_buffer
+                    .lookup_transform("camera", "item", time)
+                    .unwrap()
+                    .transform,
+            );
+            assert!(
+                (single.cast::<f64>().to_homogeneous() - double.to_homogeneous()).amax() < 1e-5
+            );
+        }
+    }
+
+    #[test]
+    fn test_subtree_for() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = rosrust::Time {
+            sec: 0,
+            nsec: 400_000_000,
+        };
+        let transforms = tf_buffer.subtree_for("camera", "item", time).unwrap();
+        assert_eq!(transforms.len(), 3);
+
+        let mut remote = TfBuffer::new();
+        remote.handle_incoming_transforms(TFMessage { transforms }, false);
+        assert_approx_eq(
+            remote.lookup_transform("camera", "item", time).unwrap(),
+            tf_buffer.lookup_transform("camera", "item", time).unwrap(),
+        );
+
+        // At the zero time, every edge is sampled at the latest common time, even if some have newer data.
+        let mut tf_buffer = TfBuffer::new();
+        for (child, secs) in [("a", 1..3), ("b", 1..4)] {
+            for sec in secs {
+                let transform = TransformStampedBuilder::new()
+                    .parent("map")
+                    .child(child)
+                    .stamp(Time::from_seconds(sec))
+                    .translation(sec as f64, 0.0, 0.0)
+                    .build();
+                tf_buffer
+                    .add_published_transform(&transform, false)
+                    .unwrap();
+            }
+        }
+        let transforms = tf_buffer.subtree_for("a", "b", Time::new()).unwrap();
+        assert!(transforms
+            .iter()
+            .all(|transform| transform.header.stamp == Time::from_seconds(2)));
+
+        let mut remote = TfBuffer::new();
+        remote.handle_incoming_transforms(TFMessage { transforms }, false);
+        assert_approx_eq(
+            remote.lookup_transform("a", "b", Time::new()).unwrap(),
+            tf_buffer.lookup_transform("a", "b", Time::new()).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_frame_drift() {
+        let mut tf_buffer = TfBuffer::new();
+        for (sec, x, yaw) in [(1, 0.5, 0.0f64), (2, 0.8, 0.1f64)] {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = "base_link".to_string();
+            transform.header.stamp = rosrust::Time { sec, nsec: 0 };
+            transform.child_frame_id = "camera".to_string();
+            transform.transform.translation.x = x;
+            transform.transform.rotation.z = (yaw / 2.0).sin();
+            transform.transform.rotation.w = (yaw / 2.0).cos();
+            tf_buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform],
+                },
+                false,
+            );
+        }
+        let (translation, rotation) = tf_buffer
+            .frame_drift("base_link", "camera", 1.0, 2.0)
+            .unwrap();
+        assert!((translation - 0.3).abs() < 1e-9);
+        assert!((rotation - 0.1).abs() < 1e-9);
+
+        let (translation, rotation) = tf_buffer
+            .frame_drift("base_link", "camera", 1.0, 1.0)
+            .unwrap();
+        assert_eq!((translation, rotation), (0.0, 0.0));
+        assert!(tf_buffer
+            .frame_drift("base_link", "camera", 1.0, 3.0)
+            .is_err());
+
+        let mut lens = TransformStamped::default();
+        lens.header.frame_id = "camera".to_string();
+        lens.child_frame_id = "lens".to_string();
+        lens.transform.rotation.w = 1.0;
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![lens],
+            },
+            true,
+        );
+        assert!(matches!(
+            tf_buffer.frame_drift("base_link", "lens", 1.0, 2.0),
+            Err(TfError::CouldNotFindTransform(..))
+        ));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_recently_used_frames() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        assert!(tf_buffer.recently_used_frames().is_empty());
+        tf_buffer
+            .lookup_transform("base_link", "camera", rosrust::Time::new())
+            .unwrap();
+        assert_eq!(
+            tf_buffer.recently_used_frames(),
+            vec!["camera", "base_link"]
+        );
+        tf_buffer
+            .lookup_transform("world", "item", rosrust::Time::new())
+            .unwrap();
+        assert_eq!(
+            tf_buffer.recently_used_frames(),
+            vec!["item", "world", "camera", "base_link"]
+        );
+        assert!(tf_buffer
+            .lookup_transform("world", "unknown", rosrust::Time::new())
+            .is_err());
+        assert_eq!(tf_buffer.recently_used_frames().len(), 4);
+
+        // Every kind of lookup records the frames it went through.
+        tf_buffer
+            .lookup_transform_f32("camera", "base_link", rosrust::Time::new())
+            .unwrap();
+        assert_eq!(
+            tf_buffer.recently_used_frames(),
+            vec!["base_link", "camera", "item", "world"]
+        );
+        tf_buffer
+            .subtree_for("item", "world", rosrust::Time::new())
+            .unwrap();
+        assert_eq!(
+            tf_buffer.recently_used_frames(),
+            vec!["world", "item", "base_link", "camera"]
+        );
+    }
+
+    #[test]
+    fn test_lookup_across_common() {
+        let mut tf_buffer = TfBuffer::new();
+        let transform = |parent: &str, child: &str, x, yaw: f64| {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = parent.to_string();
+            transform.child_frame_id = child.to_string();
+            transform.transform.translation.x = x;
+            transform.transform.rotation.z = (yaw / 2.0).sin();
+            transform.transform.rotation.w = (yaw / 2.0).cos();
+            transform
+        };
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![
+                    transform("map", "robot_a/odom", 1.0, 0.5),
+                    transform("robot_a/odom", "robot_a/base", 2.0, 0.0),
+                    transform("map", "robot_b/odom", -1.0, -0.5),
+                    transform("robot_b/odom", "robot_b/base", 0.5, 1.0),
+                    transform("robot_c/odom", "robot_c/base", 0.5, 1.0),
+                ],
+            },
+            true,
+        );
+
+        let time = rosrust::Time::new();
+        assert_approx_eq(
+            tf_buffer
+                .lookup_across_common("robot_a/base", "robot_b/base", "map", time)
+                .unwrap(),
+            tf_buffer
+                .lookup_transform("robot_a/base", "robot_b/base", time)
+                .unwrap(),
+        );
+        assert!(matches!(
+            tf_buffer.lookup_across_common("robot_a/base", "robot_c/base", "map", time),
+            Err(TfError::CouldNotFindTransform(from, to, _)) if from == "map" && to == "robot_c/base"
+        ));
+        assert!(matches!(
+            tf_buffer.lookup_across_common("robot_a/base", "robot_b/base", "robot_c/odom", time),
+            Err(TfError::CouldNotFindTransform(from, to, _)) if from == "robot_a/base" && to == "robot_c/odom"
+        ));
+
+        // At the zero time, both halves are looked up at the latest time they have in common.
+        for (sec, x) in [(1, 0.0), (2, 4.0), (3, 8.0)] {
+            let transform = TransformStampedBuilder::new()
+                .parent("map")
+                .child("robot_c/odom")
+                .stamp(Time::from_seconds(sec))
+                .translation(x, 0.0, 0.0)
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+        for sec in [1, 2] {
+            let transform = TransformStampedBuilder::new()
+                .parent("map")
+                .child("robot_d/odom")
+                .stamp(Time::from_seconds(sec))
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+        let across = tf_buffer
+            .lookup_across_common("robot_c/odom", "robot_d/odom", "map", time)
+            .unwrap();
+        assert_eq!(across.header.stamp, Time::from_seconds(2));
+        assert_approx_eq(
+            across,
+            tf_buffer
+                .lookup_transform("robot_c/odom", "robot_d/odom", time)
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_lookup_transform_as_matrix() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = Time::from_nanos(400_000_000);
+        let matrix = tf_buffer
+            .lookup_transform_as_matrix("item", "camera", time)
+            .unwrap();
+        let transform = tf_buffer
+            .lookup_transform("item", "camera", time)
+            .unwrap()
+            .transform;
+        for (x, y, z) in [(0.0, 0.0, 0.0), (1.0, -2.0, 0.5), (-3.0, 4.0, 7.0)] {
+            let expected = transforms::transform_point(&transform, &Point { x, y, z });
+            let mapped: Vec<_> = matrix
+                .iter()
+                .map(|row| row[0] * x + row[1] * y + row[2] * z + row[3])
+                .collect();
+            assert!((mapped[0] - expected.x).abs() < 1e-12);
+            assert!((mapped[1] - expected.y).abs() < 1e-12);
+            assert!((mapped[2] - expected.z).abs() < 1e-12);
+            assert_eq!(mapped[3], 1.0);
+        }
+    }
+
+    #[test]
+    fn test_transform_path() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        assert_eq!(
+            tf_buffer
+                .transform_path("camera", "item", Time::new())
+                .unwrap(),
+            vec!["base_link", "world", "item"]
+        );
+        assert!(tf_buffer
+            .transform_path("camera", "camera", Time::new())
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            tf_buffer.transform_path("camera", "map", Time::new()),
+            Err(TfError::CouldNotFindTransform(..))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let snapshot = tf_buffer.export_snapshot();
+        assert!(snapshot.samples.iter().any(|sample| sample.is_static));
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        let mut imported = TfBuffer::new();
+        imported.import_snapshot(serde_json::from_str(&json).unwrap());
+        assert_eq!(imported.export_snapshot(), snapshot);
+        for time in [0.0, 0.5, 1.0] {
+            assert_eq!(
+                imported.lookup_transform("camera", "item", time).unwrap(),
+                tf_buffer.lookup_transform("camera", "item", time).unwrap()
+            );
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_static_yaml() {
+        let mut tf_buffer = TfBuffer::new();
+        let loaded = tf_buffer
+            .load_static_yaml(
+                "- parent: world\n  child: base_link\n  translation: [1, 2.5, 0]\n  rotation: [0.0, 0.0, 0.0, 1.0]\n\
+                 - parent: base_link\n  child: camera\n  translation: [0.1, 0.0, 0.5]\n  rpy: [0.0, 0.0, 1.5707963267948966]\n",
+            )
+            .unwrap();
+        assert_eq!(loaded, 2);
+        let camera = tf_buffer
+            .lookup_transform("world", "camera", Time::new())
+            .unwrap();
+        let translation = &camera.transform.translation;
+        let rotation = &camera.transform.rotation;
+        for (actual, expected) in [
+            (translation.x, 1.1),
+            (translation.y, 2.5),
+            (translation.z, 0.5),
+            (rotation.z, std::f64::consts::FRAC_1_SQRT_2),
+            (rotation.w, std::f64::consts::FRAC_1_SQRT_2),
+        ] {
+            assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+        }
+
+        let yaml = tf_buffer.static_tree_to_yaml();
+        let mut reloaded = TfBuffer::new();
+        assert_eq!(reloaded.load_static_yaml(&yaml).unwrap(), 2);
+        assert_eq!(reloaded.static_tree_to_yaml(), yaml);
+        assert_eq!(
+            reloaded
+                .lookup_transform("world", "camera", Time::new())
+                .unwrap()
+                .transform,
+            camera.transform
+        );
+
+        for (yaml, reason) in [
+            (
+                "- parent: a\n  translation: [0, 0, 0]\n  rpy: [0, 0, 0]",
+                "missing `child`",
+            ),
+            (
+                "- parent: a\n  child: b\n  translation: [0, 0]\n  rpy: [0, 0, 0]",
+                "`translation`",
+            ),
+            (
+                "- parent: a\n  child: b\n  translation: [0, 0, 0]",
+                "`rotation` or `rpy`",
+            ),
+            ("parent: a", "list"),
+        ] {
+            match TfBuffer::new().load_static_yaml(yaml) {
+                Err(TfError::InvalidArgument(message)) => {
+                    assert!(message.contains(reason), "{message}")
+                }
+                other => panic!("{other:?}"),
+            }
+        }
+
+        // A file rejected by a later entry inserts none of the earlier ones.
+        for yaml in [
+            "- parent: a\n  child: b\n  translation: [0, 0, 0]\n  rpy: [0, 0, 0]\n\
+             - parent: b\n  child: a\n  translation: [0, 0, 0]\n  rpy: [0, 0, 0]",
+            "- parent: a\n  child: b\n  translation: [0, 0, 0]\n  rpy: [0, 0, 0]\n\
+             - parent: c\n  child: c\n  translation: [0, 0, 0]\n  rpy: [0, 0, 0]",
+        ] {
+            let mut tf_buffer = TfBuffer::new();
+            assert!(tf_buffer.load_static_yaml(yaml).is_err());
+            assert!(tf_buffer.frame_names().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_lookup_transforms() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let pairs = [
+            ("camera", "item"),
+            ("world", "item"),
+            ("item", "item"),
+            ("item", "camera"),
+            ("map", "item"),
+            ("base_link", "camera"),
+        ];
+        for time in [Time::new(), Time::from_nanos(500_000_000)] {
+            let results = tf_buffer.lookup_transforms(&pairs, &time);
+            assert_eq!(results.len(), pairs.len());
+            for ((from, to), result) in pairs.iter().zip(results) {
+                match (result, tf_buffer.lookup_transform(from, to, time)) {
+                    (Ok(batch), Ok(single)) => assert_eq!(batch, single),
+                    (
+                        Err(TfError::CouldNotFindTransform(..)),
+                        Err(TfError::CouldNotFindTransform(..)),
+                    ) => {}
+                    other => panic!("{from} -> {to}: {other:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_velocity() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let t0 = Time::from_nanos(200_000_000);
+        let t1 = Time::from_nanos(700_000_000);
+        let twist = tf_buffer
+            .lookup_velocity("world", "base_link", &t0, &t1)
+            .unwrap();
+        assert!((twist.linear.y - 1.0).abs() < 1e-9);
+        assert!(twist.linear.x.abs() < 1e-9);
+        assert!(twist.angular.z.abs() < 1e-9);
+        let twist = tf_buffer
+            .lookup_velocity("world", "base_link", &t1, &t0)
+            .unwrap();
+        assert!((twist.linear.y - 1.0).abs() < 1e-9);
+
+        assert!(matches!(
+            tf_buffer.lookup_velocity("world", "base_link", &t0, &t0),
+            Err(TfError::InvalidArgument(_))
+        ));
+        assert!(tf_buffer
+            .lookup_velocity("world", "base_link", &t0, &Time::from_seconds(2))
+            .is_err());
+    }
+
+    #[test]
+    fn test_tf_prefix() {
+        let mut tf_buffer = TfBuffer::builder().tf_prefix("/robot1/").build();
+        let mut transform = TransformStampedBuilder::new()
+            .parent("map")
+            .child("base_link")
+            .stamp(Time::from_seconds(1))
+            .build();
+        let mut slashed = TransformStampedBuilder::new()
+            .parent("/base_link")
+            .child("/camera")
+            .stamp(Time::from_seconds(1))
+            .translation(1.0, 0.0, 0.0)
+            .build();
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![transform.clone(), slashed.clone()],
+            },
+            true,
+        );
+        assert_eq!(
+            tf_buffer.frame_names(),
+            vec!["robot1/base_link", "robot1/camera", "robot1/map"]
+        );
+        let result = tf_buffer
+            .lookup_transform("robot1/map", "robot1/camera", Time::new())
+            .unwrap();
+        assert_eq!(result.transform.translation.x, 1.0);
+        assert!(tf_buffer
+            .lookup_transform("map", "camera", Time::new())
+            .is_err());
+
+        tf_buffer.set_tf_prefix(Some("robot2"));
+        transform.child_frame_id = "odom".to_string();
+        slashed.header.frame_id = "odom".to_string();
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![transform, slashed],
+            },
+            true,
+        );
+        assert!(tf_buffer
+            .lookup_transform("robot2/map", "robot2/camera", Time::new())
+            .is_ok());
+        assert!(tf_buffer.frame_exists("robot1/camera"));
+    }
+
+    #[test]
+    fn test_leading_slash() {
+        let mut tf_buffer = TfBuffer::new();
+        let message = TFMessage {
+            transforms: vec![
+                TransformStampedBuilder::new()
+                    .parent("/world")
+                    .child("base_link")
+                    .translation(1.0, 0.0, 0.0)
+                    .build(),
+                TransformStampedBuilder::new()
+                    .parent("base_link")
+                    .child("/camera")
+                    .translation(0.0, 1.0, 0.0)
+                    .build(),
+            ],
+        };
+        tf_buffer.handle_incoming_transforms(message, true);
+        assert_eq!(
+            tf_buffer.frame_names(),
+            vec!["base_link", "camera", "world"]
+        );
+        for (from, to) in [
+            ("world", "camera"),
+            ("/world", "/camera"),
+            ("world", "/camera"),
+        ] {
+            let transform = tf_buffer.lookup_transform(from, to, Time::new()).unwrap();
+            assert_eq!(transform.header.frame_id, "world");
+            assert_eq!(transform.child_frame_id, "camera");
+            assert_eq!(transform.transform.translation.x, 1.0);
+            assert_eq!(transform.transform.translation.y, 1.0);
+        }
+        assert!(tf_buffer
+            .lookup_transform("/camera", "camera", Time::new())
+            .is_ok());
+
+        let time = Time::new();
+        assert!(tf_buffer.can_transform("/world", "/camera", &time));
+        assert!(tf_buffer
+            .can_transform_with_error("/world", "/camera", &time)
+            .is_ok());
+        assert_eq!(
+            tf_buffer.transform_path("/world", "/camera", time).unwrap(),
+            vec!["base_link", "camera"]
+        );
+        let mut out = TransformStamped::default();
+        tf_buffer
+            .lookup_transform_into("/world", "/camera", time, &mut out)
+            .unwrap();
+        assert_eq!(
+            (out.header.frame_id.as_str(), out.child_frame_id.as_str()),
+            ("world", "camera")
+        );
+        let f32_result = tf_buffer
+            .lookup_transform_f32("/world", "/camera", time)
+            .unwrap();
+        assert_eq!(f32_result.translation.vector.x, 1.0);
+        assert_eq!(
+            tf_buffer
+                .subtree_for("/world", "/camera", time)
+                .unwrap()
+                .len(),
+            2
+        );
+        let allowed = HashSet::from(["world", "base_link", "camera"].map(String::from));
+        assert!(tf_buffer
+            .lookup_transform_restricted("/world", "/camera", time, &allowed)
+            .is_ok());
+        assert!(tf_buffer
+            .lookup_transforms(&[("/world", "/camera")], &time)
+            .into_iter()
+            .all(|result| result.is_ok()));
+
+        // Only a single leading slash is dropped, with or without a prefix.
+        assert!(tf_buffer
+            .lookup_transform("//world", "camera", time)
+            .is_err());
+        assert_eq!(tf_buffer.prefixed_frame("//world"), "/world");
+        tf_buffer.set_tf_prefix(Some("robot1"));
+        assert_eq!(tf_buffer.prefixed_frame("/world"), "robot1/world");
+        assert_eq!(tf_buffer.prefixed_frame("//world"), "robot1//world");
+    }
+
+    #[test]
+    fn test_lookup_same_frame() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        let time = Time::from_nanos(500_000_000);
+        let identity = tf_buffer
+            .lookup_transform("base_link", "base_link", time)
+            .unwrap();
+        assert_eq!(identity.header.frame_id, "base_link");
+        assert_eq!(identity.child_frame_id, "base_link");
+        assert_eq!(identity.header.stamp, time);
+        assert_eq!(identity.transform, chain_transforms(&[]));
+        assert_eq!(identity.transform.rotation.w, 1.0);
+        assert_eq!(identity.transform.translation.x, 0.0);
+
+        // The zero time resolves to the newest sample of the frame, or stays zero for frames with only static parents.
+        build_test_tree(&mut tf_buffer, 1f64);
+        let latest = tf_buffer
+            .lookup_transform("base_link", "base_link", Time::new())
+            .unwrap();
+        assert_eq!(latest.header.stamp, Time::from_seconds(1));
+        let latest = tf_buffer
+            .lookup_transform("camera", "camera", Time::new())
+            .unwrap();
+        assert_eq!(latest.header.stamp, Time::new());
+
+        assert!(matches!(
+            tf_buffer.lookup_transform("unknown", "unknown", time),
+            Err(TfError::NoDataForFrame(frame)) if frame == "unknown"
+        ));
+        assert!(!tf_buffer.can_transform("unknown", "unknown", &time));
+        assert!(tf_buffer.can_transform("camera", "camera", &time));
+    }
+
+    #[test]
+    fn test_interpolation_modes() {
+        let x_at = |tf_buffer: &TfBuffer, seconds: f64| {
+            tf_buffer
+                .lookup_transform(PARENT, CHILD0, seconds)
+                .unwrap()
+                .transform
+                .translation
+                .x
+        };
+        let fill = |tf_buffer: &mut TfBuffer| {
+            for sec in 0..2 {
+                let transform = TransformStampedBuilder::new()
+                    .parent(PARENT)
+                    .child(CHILD0)
+                    .stamp(Time::from_seconds(sec))
+                    .translation(sec as f64, 0.0, 0.0)
+                    .build();
+                tf_buffer
+                    .add_published_transform(&transform, false)
+                    .unwrap();
+            }
+        };
+        for (mode, expected) in [
+            (InterpolationMode::Linear, [0.3, 0.7]),
+            (InterpolationMode::Nearest, [0.0, 1.0]),
+            (InterpolationMode::Previous, [0.0, 0.0]),
+        ] {
+            let mut tf_buffer = TfBuffer::builder().interpolation_mode(mode).build();
+            fill(&mut tf_buffer);
+            assert!(
+                (x_at(&tf_buffer, 0.3) - expected[0]).abs() < 1e-9,
+                "{mode:?}"
+            );
+            assert!(
+                (x_at(&tf_buffer, 0.7) - expected[1]).abs() < 1e-9,
+                "{mode:?}"
+            );
+            // Samples are still returned as is at their stamps, and bounds are still enforced.
+            assert_eq!(x_at(&tf_buffer, 1.0), 1.0);
+            assert!(tf_buffer.lookup_transform(PARENT, CHILD0, 1.5).is_err());
+        }
+
+        // A single edge can be configured before it receives samples.
+        let mut tf_buffer = TfBuffer::new();
+        tf_buffer.set_interpolation_mode(PARENT, CHILD0, InterpolationMode::Previous);
+        fill(&mut tf_buffer);
+        assert_eq!(x_at(&tf_buffer, 0.7), 0.0);
+        tf_buffer.set_interpolation_mode(PARENT, CHILD0, InterpolationMode::Linear);
+        assert!((x_at(&tf_buffer, 0.7) - 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_multiple_parents() {
+        let edge = |parent: &str| {
+            TransformStampedBuilder::new()
+                .parent(parent)
+                .child("c")
+                .build()
+        };
+
+        let mut permissive = TfBuffer::new();
+        permissive.set_transform(&edge("a"), "", true).unwrap();
+        permissive.set_transform(&edge("b"), "", true).unwrap();
+        assert!(permissive.lookup_transform("a", "b", Time::new()).is_ok());
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let recorded = warnings.clone();
+        let mut warning = TfBuffer::builder()
+            .multiple_parents(MultipleParents::Warn(Arc::new(
+                move |child, existing, new| {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push(format!("{child}: {existing} {new}"));
+                },
+            )))
+            .build();
+        warning.set_transform(&edge("a"), "", true).unwrap();
+        warning.set_transform(&edge("a"), "", true).unwrap();
+        warning.set_transform(&edge("b"), "", true).unwrap();
+        assert_eq!(*warnings.lock().unwrap(), vec!["c: a b"]);
+        assert!(warning.lookup_transform("b", "c", Time::new()).is_ok());
+
+        let mut strict = TfBuffer::builder()
+            .multiple_parents(MultipleParents::Reject)
+            .build();
+        strict.set_transform(&edge("a"), "", true).unwrap();
+        assert!(matches!(
+            strict.set_transform(&edge("b"), "", true),
+            Err(TfError::MultipleParents(child, existing, new))
+                if child == "c" && existing == "a" && new == "b"
+        ));
+        assert!(!strict.frame_exists("b"));
+
+        let dynamic = |parent: &str, sec: u32| {
+            TransformStampedBuilder::new()
+                .parent(parent)
+                .child("c")
+                .stamp(Time::from_seconds(sec))
+                .build()
+        };
+        let mut strict = TfBuffer::builder()
+            .multiple_parents(MultipleParents::Reject)
+            .build();
+        strict.extend_sorted(&[
+            (dynamic("a", 1), false),
+            (dynamic("b", 2), false),
+            (dynamic("a", 3), false),
+        ]);
+        assert!(!strict.frame_exists("b"));
+        assert_eq!(strict.frame_statistics()[0].buffer_length, 2);
+    }
+
+    #[test]
+    fn test_frame_statistics() {
+        let mut tf_buffer = TfBuffer::new();
+        for index in 0..=20 {
+            let transform = TransformStampedBuilder::new()
+                .parent("world")
+                .child("base_link")
+                .stamp(Time::from_nanos(1_000_000_000 + index * 50_000_000))
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+        let camera = TransformStampedBuilder::new()
+            .parent("base_link")
+            .child("camera")
+            .build();
+        tf_buffer.add_published_transform(&camera, true).unwrap();
+
+        let statistics = tf_buffer.frame_statistics();
+        assert_eq!(statistics.len(), 2);
+        let base_link = &statistics[0];
+        assert_eq!(base_link.frame_id, "base_link");
+        assert_eq!(base_link.parent, "world");
+        assert!(!base_link.is_static);
+        assert_eq!(base_link.buffer_length, 21);
+        assert_eq!(base_link.oldest_stamp, Time::from_seconds(1));
+        assert_eq!(base_link.newest_stamp, Time::from_seconds(2));
+        assert!((base_link.average_rate_hz - 20.0).abs() < 1e-9);
+        assert_eq!(
+            statistics[1],
+            FrameStats {
+                frame_id: "camera".to_string(),
+                parent: "base_link".to_string(),
+                is_static: true,
+                buffer_length: 1,
+                oldest_stamp: Time::new(),
+                newest_stamp: Time::new(),
+                average_rate_hz: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_transform() {
+        let mut tf_buffer = TfBuffer::new();
+        let transform = TransformStampedBuilder::new()
+            .parent("base_link")
+            .child("camera")
+            .translation(1.0, 0.0, 0.0)
+            .build();
+        tf_buffer
+            .set_transform(&transform, "calibration", true)
+            .unwrap();
+        assert_eq!(
+            tf_buffer.edge_metadata("base_link", "camera").unwrap()["authority"],
+            "calibration"
+        );
+        let translation = tf_buffer
+            .lookup_transform("base_link", "camera", Time::new())
+            .unwrap()
+            .transform
+            .translation;
+        assert_eq!(translation.x, 1.0);
+
+        let mut no_parent = transform.clone();
+        no_parent.header.frame_id.clear();
+        let mut no_child = transform.clone();
+        no_child.child_frame_id.clear();
+        let mut own_parent = transform.clone();
+        own_parent.child_frame_id = "base_link".to_string();
+        for invalid in [no_parent, no_child, own_parent] {
+            assert!(matches!(
+                tf_buffer.set_transform(&invalid, "calibration", true),
+                Err(TfError::InvalidFrameId(_))
+            ));
+        }
+        let mut non_finite = transform;
+        non_finite.child_frame_id = "lidar".to_string();
+        non_finite.transform.rotation.x = f64::NAN;
+        assert!(matches!(
+            tf_buffer.set_transform(&non_finite, "calibration", true),
+            Err(TfError::InvalidArgument(_))
+        ));
+        assert_eq!(tf_buffer.frame_names(), vec!["base_link", "camera"]);
+    }
+
+    #[test]
+    fn test_edge_metadata() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        assert!(tf_buffer.edge_metadata("world", "base_link").is_none());
+        tf_buffer.set_edge_metadata("world", "base_link", "source", "slam");
+        tf_buffer.set_edge_metadata("world", "base_link", "source", "odometry");
+        tf_buffer.set_edge_metadata("base_link", "camera", "kind", "calibration");
+        build_test_tree(&mut tf_buffer, 1f64);
+
+        let metadata = tf_buffer.edge_metadata("world", "base_link").unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata["source"], "odometry");
+        assert_eq!(
+            tf_buffer.edge_metadata("base_link", "camera").unwrap()["kind"],
+            "calibration"
+        );
+        assert!(tf_buffer.edge_metadata("base_link", "world").is_none());
+    }
+
+    #[test]
+    fn test_recommended_rate() {
+        let mut tf_buffer = TfBuffer::new();
+        for step in 0..10 {
+            build_test_tree(&mut tf_buffer, step as f64 * 0.5);
+        }
+        assert_eq!(
+            tf_buffer.recommended_rate("world", "base_link", 0.001, 0.001),
+            Some(0.0)
+        );
+        assert_eq!(
+            tf_buffer.recommended_rate("base_link", "camera", 0.001, 0.001),
+            Some(0.0)
+        );
+        assert_eq!(
+            tf_buffer.recommended_rate("world", "camera", 0.001, 0.001),
+            None
+        );
+
+        // Moving on a unit circle at 1 rad/s, i.e. with a centripetal acceleration of 1 m/s^2.
+        for step in 0..50 {
+            let t = step as f64 * 0.1;
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = "world".to_string();
+            transform.header.stamp = Time::from_nanos(step * 100_000_000);
+            transform.child_frame_id = "robot".to_string();
+            transform.transform.translation.x = t.cos();
+            transform.transform.translation.y = t.sin();
+            transform.transform.rotation.z = (t / 2.0).sin();
+            transform.transform.rotation.w = (t / 2.0).cos();
+            tf_buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform],
+                },
+                false,
+            );
+        }
+        let rate = tf_buffer
+            .recommended_rate("world", "robot", 0.001, 0.001)
+            .unwrap();
+        let expected = (1.0f64 / (8.0 * 0.001)).sqrt();
+        assert!(
+            (rate - expected).abs() < 0.01 * expected,
+            "{rate} != {expected}"
+        );
+        assert!(
+            tf_buffer
+                .recommended_rate("world", "robot", 0.01, 0.001)
+                .unwrap()
+                < rate
+        );
+    }
+
+    #[test]
+    fn test_lookup_transform_enu_ned() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = 0.5;
+        let ros = tf_buffer.lookup_transform("world", "camera", time).unwrap();
+        assert_eq!(
+            tf_buffer
+                .lookup_transform_enu("world", "camera", time)
+                .unwrap(),
+            ros
+        );
+
+        // world -> camera is (0.5, 0.5, 0) in ENU, i.e. 0.5 north and 0.5 east in NED.
+        let ned = tf_buffer
+            .lookup_transform_ned("world", "camera", time)
+            .unwrap();
+        assert_eq!(ned.header, ros.header);
+        assert_eq!(ned.child_frame_id, ros.child_frame_id);
+        assert!((ned.transform.translation.x - ros.transform.translation.y).abs() < 1e-9);
+        assert!((ned.transform.translation.y - ros.transform.translation.x).abs() < 1e-9);
+        assert!((ned.transform.translation.z + ros.transform.translation.z).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_edge_coverage_intervals() {
+        let mut tf_buffer = TfBuffer::new();
+        for time in [0.0, 0.1, 0.2, 0.3, 1.5, 1.6, 1.7] {
+            build_test_tree(&mut tf_buffer, time);
+        }
+        let max_gap = Duration::from_nanos(150_000_000);
+        assert_eq!(
+            tf_buffer.edge_coverage_intervals("world", "base_link", max_gap),
+            vec![
+                (Time::new(), Time::from_nanos(300_000_000)),
+                (
+                    Time::from_nanos(1_500_000_000),
+                    Time::from_nanos(1_700_000_000)
+                ),
+            ]
+        );
+        assert_eq!(
+            tf_buffer
+                .edge_coverage_intervals("world", "base_link", Duration::from_seconds(2))
+                .len(),
+            1
+        );
+        assert!(tf_buffer
+            .edge_coverage_intervals("world", "camera", max_gap)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_tree_height() {
+        let mut tf_buffer = TfBuffer::new();
+        assert_eq!(tf_buffer.tree_height(Time::new()), 0);
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        // item -> world -> base_link -> camera
+        assert_eq!(tf_buffer.tree_height(Time::new()), 3);
+        assert_eq!(tf_buffer.tree_height(0.5), 3);
+        // Without world -> base_link, only the static edges remain.
+        assert_eq!(tf_buffer.tree_height(5.0), 1);
+    }
+
+    #[test]
+    fn test_to_tf_message() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+
+        let static_message = tf_buffer.to_tf_message(true);
+        let edges: Vec<_> = static_message
+            .transforms
+            .iter()
+            .map(|transform| {
+                (
+                    transform.header.frame_id.as_str(),
+                    transform.child_frame_id.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(edges, vec![("base_link", "camera"), ("world", "item")]);
+        let message = tf_buffer.to_tf_message(false);
+        assert_eq!(message.transforms.len(), 3);
+
+        let mut republished = TfBuffer::new();
+        republished.handle_incoming_transforms(static_message, true);
+        republished.handle_incoming_transforms(message, false);
+        for (from, to) in [
+            ("camera", "item"),
+            ("world", "camera"),
+            ("item", "base_link"),
+        ] {
+            assert_approx_eq(
+                republished.lookup_transform(from, to, Time::new()).unwrap(),
+                tf_buffer.lookup_transform(from, to, Time::new()).unwrap(),
+            );
+        }
     }
 
-    /// Tests a basic lookup
     #[test]
-    fn test_basic_tf_lookup() {
+    fn test_lookup_in_root() {
         let mut tf_buffer = TfBuffer::new();
         build_test_tree(&mut tf_buffer, 0f64);
-        let res = tf_buffer.lookup_transform("camera", "item", rosrust::Time { sec: 0, nsec: 0 });
-        let expected = TransformStamped {
-            child_frame_id: "item".to_string(),
-            header: Header {
-                frame_id: "camera".to_string(),
-                stamp: rosrust::Time { sec: 0, nsec: 0 },
-                seq: 1,
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = rosrust::Time {
+            sec: 0,
+            nsec: 500_000_000,
+        };
+        for frame in ["camera", "base_link", "item", "world"] {
+            assert_eq!(
+                tf_buffer.lookup_in_root(frame, time).unwrap(),
+                tf_buffer.lookup_transform("world", frame, time).unwrap()
+            );
+        }
+        assert_eq!(
+            tf_buffer.lookup_in_root("/camera", time).unwrap(),
+            tf_buffer.lookup_transform("world", "camera", time).unwrap()
+        );
+
+        let mut transform = TransformStamped::default();
+        transform.header.frame_id = "map".to_string();
+        transform.child_frame_id = "camera".to_string();
+        transform.transform.rotation.w = 1.0;
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![transform],
             },
-            transform: Transform {
-                rotation: Quaternion {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64,
-                    w: 1f64,
-                },
-                translation: Vector3 {
-                    x: 0.5f64,
-                    y: 0f64,
-                    z: 0f64,
+            true,
+        );
+        match tf_buffer.lookup_in_root("item", time) {
+            Err(TfError::AmbiguousRoot(frame, roots)) => {
+                assert_eq!(frame, "item");
+                assert_eq!(roots, vec!["map".to_string(), "world".to_string()]);
+            }
+            other => panic!("expected AmbiguousRoot, got {other:?}"),
+        }
+        assert!(matches!(
+            tf_buffer.lookup_in_root("unknown", time),
+            Err(TfError::NoDataForFrame(_))
+        ));
+    }
+
+    /// Tests that shuffled samples end up sorted and are counted as reordered.
+    #[test]
+    fn test_reorder_count() {
+        let mut tf_buffer = TfBuffer::new();
+        for sec in [1, 3, 2, 4, 0, 5] {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = PARENT.to_string();
+            transform.header.stamp = rosrust::Time { sec, nsec: 0 };
+            transform.child_frame_id = CHILD0.to_string();
+            transform.transform.rotation.w = 1.0;
+            tf_buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform],
                 },
-            },
+                false,
+            );
+        }
+        assert_eq!(tf_buffer.ingest_stats().reordered, 2);
+        assert!(tf_buffer.validate().is_ok());
+        let chain = &tf_buffer.transform_data[&TfGraphNode {
+            child: CHILD0.to_string(),
+            parent: PARENT.to_string(),
+        }];
+        let stamps: Vec<_> = chain
+            .transform_chain
+            .iter()
+            .map(|transform| transform.header.stamp.sec)
+            .collect();
+        assert_eq!(stamps, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_is_rigidly_attached() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        assert!(tf_buffer.is_rigidly_attached("base_link", "camera"));
+        assert!(tf_buffer.is_rigidly_attached("camera", "base_link"));
+        assert!(tf_buffer.is_rigidly_attached("world", "item"));
+        assert!(!tf_buffer.is_rigidly_attached("world", "base_link"));
+        assert!(!tf_buffer.is_rigidly_attached("camera", "item"));
+        assert!(!tf_buffer.is_rigidly_attached("camera", "unknown"));
+    }
+
+    /// Tests that static edges only keep their latest sample unless asked to retain all of them.
+    #[test]
+    fn test_static_latest_wins() {
+        let static_sample = |sec, x| {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = PARENT.to_string();
+            transform.header.stamp = rosrust::Time { sec, nsec: 0 };
+            transform.child_frame_id = CHILD0.to_string();
+            transform.transform.translation.x = x;
+            transform.transform.rotation.w = 1.0;
+            TFMessage {
+                transforms: vec![transform],
+            }
         };
-        assert_eq!(res.unwrap(), expected);
+        let edge = TfGraphNode {
+            child: CHILD0.to_string(),
+            parent: PARENT.to_string(),
+        };
+
+        let mut tf_buffer = TfBuffer::new();
+        tf_buffer.handle_incoming_transforms(static_sample(2, 1.0), true);
+        tf_buffer.handle_incoming_transforms(static_sample(1, 2.0), true);
+        assert_eq!(tf_buffer.transform_data[&edge].transform_chain.len(), 1);
+        let res = tf_buffer
+            .lookup_transform(PARENT, CHILD0, rosrust::Time::new())
+            .unwrap();
+        assert_eq!(res.transform.translation.x, 2.0);
+
+        let mut tf_buffer = TfBuffer::builder().retain_all_static(true).build();
+        tf_buffer.handle_incoming_transforms(static_sample(2, 1.0), true);
+        tf_buffer.handle_incoming_transforms(static_sample(1, 2.0), true);
+        assert_eq!(tf_buffer.transform_data[&edge].transform_chain.len(), 2);
+
+        // Retained static samples are not pruned by the cache duration.
+        let mut tf_buffer = TfBuffer::builder()
+            .cache_duration(rosrust::Duration::from_seconds(1))
+            .retain_all_static(true)
+            .build();
+        tf_buffer.handle_incoming_transforms(static_sample(1, 1.0), true);
+        tf_buffer.handle_incoming_transforms(static_sample(100, 2.0), true);
+        let stamps: Vec<_> = tf_buffer.transform_data[&edge]
+            .transform_chain
+            .iter()
+            .map(|sample| sample.header.stamp.sec)
+            .collect();
+        assert_eq!(stamps, vec![1, 100]);
     }
 
-    /// Tests an interpolated lookup.
+    /// Tests that looking up into an existing message matches a regular lookup.
     #[test]
-    fn test_basic_tf_interpolation() {
+    fn test_lookup_transform_into() {
         let mut tf_buffer = TfBuffer::new();
         build_test_tree(&mut tf_buffer, 0f64);
         build_test_tree(&mut tf_buffer, 1f64);
-        let res = tf_buffer.lookup_transform(
-            "camera",
-            "item",
+        let mut out = TransformStamped::default();
+        for time in [
+            rosrust::Time { sec: 0, nsec: 0 },
             rosrust::Time {
                 sec: 0,
                 nsec: 700_000_000,
             },
-        );
-        let expected = TransformStamped {
-            child_frame_id: "item".to_string(),
-            header: Header {
-                frame_id: "camera".to_string(),
-                stamp: rosrust::Time {
-                    sec: 0,
-                    nsec: 700_000_000,
-                },
-                seq: 1,
-            },
-            transform: Transform {
-                rotation: Quaternion {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64,
-                    w: 1f64,
-                },
-                translation: Vector3 {
-                    x: 0.5f64,
-                    y: -0.7f64,
-                    z: 0f64,
-                },
-            },
+            rosrust::Time { sec: 1, nsec: 0 },
+        ] {
+            for (from, to) in [("camera", "item"), ("base_link", "camera")] {
+                tf_buffer
+                    .lookup_transform_into(from, to, time, &mut out)
+                    .unwrap();
+                assert_eq!(out, tf_buffer.lookup_transform(from, to, time).unwrap());
+            }
+        }
+        assert!(tf_buffer
+            .lookup_transform_into("camera", "unknown", rosrust::Time::new(), &mut out)
+            .is_err());
+    }
+
+    /// Tests that the inverse returned alongside a lookup matches the reverse lookup.
+    #[test]
+    fn test_lookup_transform_both() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = rosrust::Time {
+            sec: 0,
+            nsec: 700_000_000,
         };
-        assert_eq!(res.unwrap(), expected);
+        let (forward, inverse) = tf_buffer
+            .lookup_transform_both("camera", "item", time)
+            .unwrap();
+        assert_eq!(
+            forward,
+            tf_buffer.lookup_transform("camera", "item", time).unwrap()
+        );
+        assert_approx_eq(
+            inverse,
+            tf_buffer.lookup_transform("item", "camera", time).unwrap(),
+        );
     }
 
-    /// Tests an interpolated lookup.
+    /// Tests the freshness check of a lookup.
     #[test]
-    fn test_basic_tf_time_travel() {
+    fn test_lookup_transform_fresh() {
         let mut tf_buffer = TfBuffer::new();
         build_test_tree(&mut tf_buffer, 0f64);
         build_test_tree(&mut tf_buffer, 1f64);
-        let res = tf_buffer.lookup_transform_with_time_travel(
-            "camera",
-            rosrust::Time {
-                sec: 0,
-                nsec: 400_000_000,
-            },
-            "camera",
-            rosrust::Time {
-                sec: 0,
-                nsec: 700_000_000,
-            },
-            "item",
+        let max_age = Duration::from_nanos(500_000_000);
+        assert!(tf_buffer
+            .lookup_transform_fresh(
+                "camera",
+                "item",
+                Time::new(),
+                Time::from_nanos(1_400_000_000),
+                max_age
+            )
+            .is_ok());
+        assert!(matches!(
+            tf_buffer.lookup_transform_fresh(
+                "camera",
+                "item",
+                Time::new(),
+                Time::from_seconds(2),
+                max_age
+            ),
+            Err(TfError::TransformTooOld(newest, ..)) if newest == Time::from_seconds(1)
+        ));
+        // Only static edges between base_link and camera.
+        assert!(tf_buffer
+            .lookup_transform_fresh(
+                "base_link",
+                "camera",
+                Time::new(),
+                Time::from_seconds(100),
+                max_age
+            )
+            .is_ok());
+    }
+
+    /// Tests that a restricted lookup never routes through a disallowed frame.
+    #[test]
+    fn test_lookup_transform_restricted() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        let time = rosrust::Time { sec: 0, nsec: 0 };
+        let allowed: HashSet<String> = ["camera", "base_link", "world", "item"]
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect();
+        assert_eq!(
+            tf_buffer
+                .lookup_transform_restricted("camera", "item", time, &allowed)
+                .unwrap(),
+            tf_buffer.lookup_transform("camera", "item", time).unwrap()
         );
-        let expected = TransformStamped {
-            child_frame_id: "camera".to_string(),
-            header: Header {
-                frame_id: "camera".to_string(),
-                stamp: rosrust::Time {
-                    sec: 0,
-                    nsec: 700_000_000,
-                },
-                seq: 1,
-            },
-            transform: Transform {
-                rotation: Quaternion {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64,
-                    w: 1f64,
+
+        let allowed: HashSet<String> = ["camera", "world", "item"]
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect();
+        assert!(matches!(
+            tf_buffer.lookup_transform_restricted("camera", "item", time, &allowed),
+            Err(TfError::CouldNotFindTransform(..))
+        ));
+        assert!(tf_buffer
+            .lookup_transform_restricted("world", "item", time, &allowed)
+            .is_ok());
+    }
+
+    /// Tests that a depth limit shorter than the required path aborts the lookup.
+    #[test]
+    fn test_max_path_length() {
+        let mut tf_buffer = TfBuffer::builder().max_path_length(3).build();
+        for i in 0..5 {
+            let transform = TransformStamped {
+                header: Header {
+                    frame_id: format!("frame{i}"),
+                    ..Default::default()
                 },
-                translation: Vector3 {
-                    x: 0f64,
-                    y: 0.3f64,
-                    z: 0f64,
+                child_frame_id: format!("frame{}", i + 1),
+                transform: Transform {
+                    rotation: Quaternion {
+                        w: 1f64,
+                        ..Default::default()
+                    },
+                    ..Default::default()
                 },
-            },
-        };
-        assert_approx_eq(res.unwrap(), expected);
+            };
+            tf_buffer.add_transform(&transform, true);
+        }
+        let time = Time::new();
+        assert!(tf_buffer.lookup_transform("frame0", "frame3", time).is_ok());
+        assert!(tf_buffer.lookup_transform("frame4", "frame1", time).is_ok());
+        assert!(matches!(
+            tf_buffer.lookup_transform("frame0", "frame5", time),
+            Err(TfError::PathTooLong(_, _, 3))
+        ));
+        // The search gives up before it can tell that the frame does not exist at all.
+        assert!(matches!(
+            tf_buffer.lookup_transform("frame0", "unknown", time),
+            Err(TfError::PathTooLong(..))
+        ));
     }
 
+    /// Tests that only new edges are reported as topology changes.
     #[test]
-    fn test_add_transform() {
+    fn test_topology_changes() {
         let mut tf_buffer = TfBuffer::new();
-        let transform00 = TransformStamped {
-            header: Header {
-                frame_id: PARENT.to_string(),
-                stamp: rosrust::Time { sec: 0, nsec: 0 },
-                ..Default::default()
-            },
-            child_frame_id: CHILD0.to_string(),
-            ..Default::default()
-        };
-        let transform01 = TransformStamped {
+        let changes = tf_buffer.subscribe_topology_changes();
+        let mut transform = TransformStamped {
             header: Header {
                 frame_id: PARENT.to_string(),
                 stamp: rosrust::Time { sec: 1, nsec: 0 },
@@ -433,146 +4989,264 @@ mod test {
             child_frame_id: CHILD0.to_string(),
             ..Default::default()
         };
-        let transform1 = TransformStamped {
-            header: Header {
-                frame_id: PARENT.to_string(),
-                ..Default::default()
-            },
-            child_frame_id: CHILD1.to_string(),
-            ..Default::default()
-        };
-        let transform0_key = TfGraphNode {
-            child: CHILD0.to_owned(),
-            parent: PARENT.to_owned(),
-        };
-        let transform1_key = TfGraphNode {
-            child: CHILD1.to_owned(),
-            parent: PARENT.to_owned(),
-        };
-        let static_tf = true;
-        tf_buffer.add_transform(&transform00, static_tf);
-        assert_eq!(tf_buffer.child_transform_index.len(), 1);
-        assert!(tf_buffer.child_transform_index.contains_key(PARENT));
-        let children = tf_buffer.child_transform_index.get(PARENT).unwrap();
-        assert_eq!(children.len(), 1);
-        assert!(children.contains(CHILD0));
-        assert_eq!(tf_buffer.transform_data.len(), 1);
-        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
-        let data = tf_buffer.transform_data.get(&transform0_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 1);
+        tf_buffer.add_transform(&transform, false);
+        assert_eq!(tf_buffer.topology_revision(), 1);
+        assert_eq!(changes.try_recv(), Ok(1));
 
-        tf_buffer.add_transform(&transform01, static_tf);
-        assert_eq!(tf_buffer.child_transform_index.len(), 1);
-        assert!(tf_buffer.child_transform_index.contains_key(PARENT));
-        let children = tf_buffer.child_transform_index.get(PARENT).unwrap();
-        assert_eq!(children.len(), 1);
-        assert!(children.contains(CHILD0));
-        assert_eq!(tf_buffer.transform_data.len(), 1);
-        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
-        let data = tf_buffer.transform_data.get(&transform0_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 2);
+        transform.header.stamp.sec = 2;
+        tf_buffer.add_transform(&transform, false);
+        assert_eq!(tf_buffer.topology_revision(), 1);
+        assert!(changes.try_recv().is_err());
 
-        tf_buffer.add_transform(&transform1, static_tf);
-        assert_eq!(tf_buffer.child_transform_index.len(), 1);
-        assert!(tf_buffer.child_transform_index.contains_key(PARENT));
-        let children = tf_buffer.child_transform_index.get(PARENT).unwrap();
-        assert_eq!(children.len(), 2);
-        assert!(children.contains(CHILD0));
-        assert!(children.contains(CHILD1));
-        assert_eq!(tf_buffer.transform_data.len(), 2);
-        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
-        assert!(tf_buffer.transform_data.contains_key(&transform1_key));
-        let data = tf_buffer.transform_data.get(&transform0_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 2);
-        let data = tf_buffer.transform_data.get(&transform1_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 1);
+        transform.child_frame_id = CHILD1.to_string();
+        tf_buffer.add_transform(&transform, false);
+        assert_eq!(changes.try_recv(), Ok(2));
+
+        drop(changes);
+        transform.child_frame_id = "child2".to_string();
+        tf_buffer.add_transform(&transform, false);
+        assert!(tf_buffer.topology_watchers.0.is_empty());
     }
 
+    /// Tests that lookups older than the cache grow the suggested cache duration.
     #[test]
-    fn test_cache_duration() {
-        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(1));
-        let transform00 = TransformStamped {
-            header: Header {
-                frame_id: PARENT.to_string(),
-                stamp: rosrust::Time { sec: 0, nsec: 0 },
-                ..Default::default()
-            },
-            child_frame_id: CHILD0.to_string(),
-            ..Default::default()
-        };
-        let transform01 = TransformStamped {
-            header: Header {
-                frame_id: PARENT.to_string(),
-                stamp: rosrust::Time { sec: 1, nsec: 0 },
-                ..Default::default()
-            },
-            child_frame_id: CHILD0.to_string(),
-            ..Default::default()
-        };
-        let transform02 = TransformStamped {
-            header: Header {
-                frame_id: PARENT.to_string(),
-                stamp: rosrust::Time { sec: 2, nsec: 0 },
-                ..Default::default()
-            },
-            child_frame_id: CHILD0.to_string(),
-            ..Default::default()
-        };
-        let transform0_key = TfGraphNode {
-            child: CHILD0.to_owned(),
-            parent: PARENT.to_owned(),
-        };
+    fn test_suggest_cache_duration() {
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(2));
+        for sec in [10, 11, 12] {
+            let transform = TransformStampedBuilder::new()
+                .parent(PARENT)
+                .child(CHILD0)
+                .stamp(rosrust::Time { sec, nsec: 0 })
+                .build();
+            tf_buffer.add_transform(&transform, false);
+        }
+        assert_eq!(
+            tf_buffer.suggest_cache_duration(),
+            Duration::from_seconds(2)
+        );
 
-        let static_tf = true;
-        tf_buffer.add_transform(&transform00, static_tf);
-        assert_eq!(tf_buffer.child_transform_index.len(), 1);
-        assert_eq!(tf_buffer.transform_data.len(), 1);
-        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
-        let data = tf_buffer.transform_data.get(&transform0_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 1);
+        assert!(tf_buffer
+            .lookup_transform(PARENT, CHILD0, Time::from_seconds(9))
+            .is_err());
         assert_eq!(
-            data.unwrap().transform_chain.get(0).unwrap().header.stamp,
-            Time::from_nanos(0)
+            tf_buffer.suggest_cache_duration(),
+            Duration::from_seconds(3)
+        );
+
+        assert!(tf_buffer
+            .lookup_transform(PARENT, CHILD0, Time::from_seconds(8))
+            .is_err());
+        assert!(tf_buffer
+            .lookup_transform(CHILD0, PARENT, Time::from_seconds(5))
+            .is_err());
+        assert_eq!(
+            tf_buffer.suggest_cache_duration(),
+            Duration::from_seconds(7)
         );
 
-        tf_buffer.add_transform(&transform01, static_tf);
-        assert_eq!(tf_buffer.child_transform_index.len(), 1);
-        assert_eq!(tf_buffer.transform_data.len(), 1);
-        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
-        let data = tf_buffer.transform_data.get(&transform0_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 2);
+        // Lookups in the future do not count.
+        assert!(tf_buffer
+            .lookup_transform(PARENT, CHILD0, Time::from_seconds(20))
+            .is_err());
         assert_eq!(
-            data.unwrap().transform_chain.get(0).unwrap().header.stamp,
-            Time::from_nanos(0)
+            tf_buffer.suggest_cache_duration(),
+            Duration::from_seconds(7)
         );
+    }
+
+    /// Small deterministic xorshift generator, so the randomized tests need no extra dependencies.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.next() as usize % items.len()]
+        }
+
+        fn float(&mut self) -> f64 {
+            match self.next() % 8 {
+                0 => f64::NAN,
+                1 => f64::INFINITY,
+                2..=4 => 0.0,
+                _ => (self.next() % 2000) as f64 / 1000.0 - 1.0,
+            }
+        }
+    }
+
+    /// Feeds randomized, partly malformed messages into the buffer and checks it never panics and stays consistent.
+    #[test]
+    fn test_randomized_ingest() {
+        let frames = ["", "a", "b", "c", "d", "/a", "world"];
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(5));
+        for _ in 0..500 {
+            let transforms = (0..rng.next() % 4)
+                .map(|_| TransformStamped {
+                    header: Header {
+                        frame_id: rng.pick(&frames).to_string(),
+                        stamp: rosrust::Time {
+                            sec: (rng.next() % 20) as u32,
+                            nsec: (rng.next() % 2_000_000_000) as u32,
+                        },
+                        seq: rng.next() as u32,
+                    },
+                    child_frame_id: rng.pick(&frames).to_string(),
+                    transform: Transform {
+                        translation: Vector3 {
+                            x: rng.float(),
+                            y: rng.float(),
+                            z: rng.float(),
+                        },
+                        rotation: Quaternion {
+                            x: rng.float(),
+                            y: rng.float(),
+                            z: rng.float(),
+                            w: rng.float(),
+                        },
+                    },
+                })
+                .collect();
+            tf_buffer
+                .handle_incoming_transforms(TFMessage { transforms }, rng.next().is_multiple_of(4));
+
+            assert_eq!(tf_buffer.validate(), Ok(()));
+            for key in tf_buffer.transform_data.keys() {
+                assert!(!key.parent.is_empty() && !key.child.is_empty());
+                assert_ne!(key.parent, key.child);
+            }
+            for _ in 0..4 {
+                let time = rosrust::Time {
+                    sec: (rng.next() % 20) as u32,
+                    nsec: (rng.next() % 1_000_000_000) as u32,
+                };
+                let _ = tf_buffer.lookup_transform(rng.pick(&frames), rng.pick(&frames), time);
+            }
+        }
+    }
+
+    /// Tests that corrupting a buffer is reported by `validate`.
+    #[test]
+    fn test_validate() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        assert_eq!(tf_buffer.validate(), Ok(()));
+
+        let key = TfGraphNode {
+            child: "base_link".to_owned(),
+            parent: "world".to_owned(),
+        };
+        let mut corrupted = tf_buffer.clone();
+        let mut sample = corrupted
+            .lookup_transform("world", "base_link", 0.5)
+            .unwrap();
+        sample.transform.translation.x = f64::NAN;
+        match &mut corrupted
+            .transform_data
+            .get_mut(&key)
+            .unwrap()
+            .transform_chain
+        {
+            TransformStorage::Sorted(chain) => chain.push(sample),
+            TransformStorage::Ring(chain) => chain.push_back(sample),
+        }
         assert_eq!(
-            data.unwrap().transform_chain.get(1).unwrap().header.stamp,
-            Time::from_nanos(1_000_000_000)
+            corrupted.validate(),
+            Err(vec![
+                "world -> base_link contains a malformed transform".to_string(),
+                "world -> base_link is not sorted by stamp".to_string(),
+            ])
         );
 
-        tf_buffer.add_transform(&transform02, static_tf);
-        assert_eq!(tf_buffer.child_transform_index.len(), 1);
-        assert_eq!(tf_buffer.transform_data.len(), 1);
-        assert!(tf_buffer.transform_data.contains_key(&transform0_key));
-        let data = tf_buffer.transform_data.get(&transform0_key);
-        assert!(data.is_some());
-        assert_eq!(data.unwrap().transform_chain.len(), 2);
+        let mut corrupted = tf_buffer.clone();
+        corrupted
+            .child_transform_index
+            .get_mut("world")
+            .unwrap()
+            .remove("base_link");
         assert_eq!(
-            data.unwrap().transform_chain.get(0).unwrap().header.stamp,
-            Time::from_nanos(1_000_000_000)
+            corrupted.validate(),
+            Err(vec![
+                "world -> base_link is missing from the child index".to_string()
+            ])
         );
+
+        let mut corrupted = tf_buffer.clone();
+        corrupted.transform_data.remove(&key);
         assert_eq!(
-            data.unwrap().transform_chain.get(1).unwrap().header.stamp,
-            Time::from_nanos(2_000_000_000)
+            corrupted.validate(),
+            Err(vec![
+                "world -> base_link is indexed but has no data".to_string()
+            ])
         );
     }
 
+    /// Tests that lookups over an edge pruned empty fail cleanly.
+    #[test]
+    fn test_pruned_empty_chain() {
+        let mut tf_buffer = TfBuffer::new_with_duration(Duration::from_seconds(1));
+        let transform = TransformStampedBuilder::new()
+            .parent(PARENT)
+            .child(CHILD0)
+            .stamp(rosrust::Time { sec: 1, nsec: 0 })
+            .build();
+        tf_buffer.add_transform(&transform, false);
+        tf_buffer.prune(Time::from_seconds(10));
+
+        let key = TfGraphNode {
+            child: CHILD0.to_owned(),
+            parent: PARENT.to_owned(),
+        };
+        let chain = tf_buffer.transform_data.get(&key).unwrap();
+        assert!(chain.transform_chain.is_empty());
+        assert!(matches!(
+            chain.get_closest_transform(Time::new()),
+            Err(TfError::NoDataForFrame(frame)) if frame == CHILD0
+        ));
+        assert!(matches!(
+            chain.get_closest_transform(Time::from_seconds(1)),
+            Err(TfError::NoDataForFrame(_))
+        ));
+
+        assert!(matches!(
+            tf_buffer.lookup_transform(PARENT, CHILD0, Time::new()),
+            Err(TfError::CouldNotFindTransform(..))
+        ));
+        assert!(matches!(
+            tf_buffer.lookup_transform(CHILD0, PARENT, Time::from_seconds(1)),
+            Err(TfError::CouldNotFindTransform(..))
+        ));
+    }
+
+    /// Tests that requesting the latest sample of an edge without samples reports the missing data.
+    #[test]
+    fn test_latest_on_empty_chain() {
+        for static_tf in [false, true] {
+            let chain = TfIndividualTransformChain::new(
+                CHILD0.to_string(),
+                static_tf,
+                Duration::from_seconds(1),
+                StorageStrategy::default(),
+            );
+            assert!(matches!(
+                chain.get_closest_transform(Time::new()),
+                Err(TfError::NoDataForFrame(frame)) if frame == CHILD0
+            ));
+            assert!(matches!(
+                chain.bracket(Time::new()),
+                Err(TfError::NoDataForFrame(_))
+            ));
+            assert!(chain.get_closest_ref(Time::new()).is_none());
+            assert!(!chain.has_valid_transform(Time::new()));
+        }
+    }
+
     fn assert_approx_eq(msg1: TransformStamped, msg2: TransformStamped) {
         assert_eq!(msg1.header, msg2.header);
         assert_eq!(msg1.child_frame_id, msg2.child_frame_id);
@@ -587,6 +5261,731 @@ mod test {
         assert!((msg1.transform.translation.z - msg2.transform.translation.z).abs() < 1e-9);
     }
 
+    /// Tests that a single-sample edge queried off its stamp reports the lack of samples rather than past or future.
+    #[test]
+    fn test_single_sample_edge() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 1f64);
+        assert!(tf_buffer
+            .lookup_transform("world", "base_link", Time::from_seconds(1))
+            .is_ok());
+        for nanos in [999_999_999, 1_000_000_001] {
+            let time = Time::from_nanos(nanos);
+            assert!(matches!(
+                tf_buffer.lookup_transform("world", "base_link", time),
+                Err(TfError::InsufficientSamplesForInterpolation(frame, 1)) if frame == "base_link"
+            ));
+            assert!(matches!(
+                tf_buffer.can_transform_with_error("world", "base_link", &time),
+                Err(TfError::InsufficientSamplesForInterpolation(frame, 1)) if frame == "base_link"
+            ));
+            assert!(!tf_buffer.can_transform("world", "base_link", &time));
+            assert_eq!(
+                tf_buffer.nearest_valid_time("world", "base_link", time),
+                Some(Time::from_seconds(1))
+            );
+        }
+    }
+
+    /// Tests that the interpolation memo gives the same results as computing every lookup from scratch.
+    #[test]
+    fn test_interpolation_memo() {
+        let mut memoized = TfBuffer::builder().interpolation_memo(true).build();
+        let mut uncached = TfBuffer::new();
+        for tf_buffer in [&mut memoized, &mut uncached] {
+            build_test_tree(tf_buffer, 0f64);
+            build_test_tree(tf_buffer, 1f64);
+        }
+        let pairs = [
+            ("camera", "item"),
+            ("item", "camera"),
+            ("world", "camera"),
+            ("base_link", "item"),
+        ];
+        let check = |memoized: &TfBuffer, uncached: &TfBuffer, time: f64| {
+            for (from, to) in pairs {
+                assert_eq!(
+                    memoized
+                        .lookup_transform_with_sources(from, to, TimePoint::from(time).0)
+                        .unwrap(),
+                    uncached
+                        .lookup_transform_with_sources(from, to, TimePoint::from(time).0)
+                        .unwrap()
+                );
+            }
+        };
+        for time in [0.25, 0.25, 0.75, 0.25, 0.0] {
+            check(&memoized, &uncached, time);
+        }
+
+        // New samples on an edge already in the memo must not be hidden by it.
+        for tf_buffer in [&mut memoized, &mut uncached] {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = "world".to_string();
+            transform.header.stamp = Time::from_nanos(500_000_000);
+            transform.child_frame_id = "base_link".to_string();
+            transform.transform.translation.x = 5.0;
+            transform.transform.rotation.w = 1.0;
+            tf_buffer.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform],
+                },
+                false,
+            );
+        }
+        check(&memoized, &uncached, 0.25);
+        check(&memoized, &uncached, 0.0);
+    }
+
+    /// Tests that lookups just outside the cached range are answered within separate past and future tolerances.
+    #[test]
+    fn test_past_and_future_tolerance() {
+        let build = |past, future| {
+            let mut tf_buffer = TfBuffer::builder()
+                .past_tolerance(Duration::from_nanos(past))
+                .future_tolerance(Duration::from_nanos(future))
+                .build();
+            build_test_tree(&mut tf_buffer, 1f64);
+            build_test_tree(&mut tf_buffer, 2f64);
+            tf_buffer
+        };
+        let lookup = |tf_buffer: &TfBuffer, time: f64| {
+            tf_buffer
+                .lookup_transform("world", "base_link", time)
+                .map(|transform| transform.transform.translation.y)
+        };
+
+        let tf_buffer = build(0, 500_000_000);
+        assert_eq!(lookup(&tf_buffer, 2.3).unwrap(), 2.0);
+        assert!(lookup(&tf_buffer, 2.6).is_err());
+        assert!(lookup(&tf_buffer, 0.7).is_err());
+
+        let tf_buffer = build(500_000_000, 0);
+        assert_eq!(lookup(&tf_buffer, 0.7).unwrap(), 1.0);
+        assert!(lookup(&tf_buffer, 0.4).is_err());
+        assert!(lookup(&tf_buffer, 2.3).is_err());
+
+        let chain = &tf_buffer.transform_data[&TfGraphNode {
+            child: "base_link".to_string(),
+            parent: "world".to_string(),
+        }];
+        assert!(matches!(
+            chain.get_closest_transform(Time::from_nanos(2_300_000_000)),
+            Err(TfError::AttemptedLookUpInFuture { gap, .. }) if gap == Duration::from_nanos(300_000_000)
+        ));
+        assert!(matches!(
+            chain.get_closest_transform(Time::from_nanos(400_000_000)),
+            Err(TfError::AttemptedLookupInPast { gap, .. }) if gap == Duration::from_nanos(600_000_000)
+        ));
+        let (transform, _) = chain
+            .get_closest_transform(Time::from_nanos(700_000_000))
+            .unwrap();
+        assert_eq!(transform.header.stamp, Time::from_seconds(1));
+    }
+
+    /// Tests that bulk ingestion matches inserting transforms one by one.
+    #[test]
+    fn test_extend_sorted() {
+        let sample = |parent: &str, child: &str, nanos: i64| {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = parent.to_string();
+            transform.header.stamp = rosrust::Time::from_nanos(nanos);
+            transform.child_frame_id = child.to_string();
+            transform.transform.translation.x = nanos as f64 * 1e-9;
+            transform.transform.rotation.w = 1.0;
+            transform
+        };
+        let mut transforms = vec![(sample("base_link", "camera", 0), true)];
+        for step in 0..300 {
+            transforms.push((sample("map", "base_link", step * 100_000_000), false));
+        }
+        transforms.swap(10, 11);
+
+        let mut bulk = TfBuffer::new();
+        bulk.extend_sorted(&transforms);
+        let mut one_by_one = TfBuffer::new();
+        for (transform, static_tf) in &transforms {
+            one_by_one.handle_incoming_transforms(
+                TFMessage {
+                    transforms: vec![transform.clone()],
+                },
+                *static_tf,
+            );
+        }
+
+        assert!(bulk.validate().is_ok());
+        assert_eq!(bulk.ingest_stats(), one_by_one.ingest_stats());
+        assert_eq!(bulk.ingest_stats().reordered, 1);
+        for (key, chain) in &one_by_one.transform_data {
+            let expected: Vec<_> = chain.transform_chain.iter().collect();
+            let actual: Vec<_> = bulk.transform_data[key].transform_chain.iter().collect();
+            assert_eq!(actual, expected);
+        }
+        assert_eq!(
+            bulk.lookup_transform("map", "camera", 25.05).unwrap(),
+            one_by_one.lookup_transform("map", "camera", 25.05).unwrap()
+        );
+    }
+
+    /// Tests finding the nearest time a lookup succeeds at, around a gap in coverage.
+    /// time 1-2(sec): [base] -> [camera1] -> [marker]
+    /// time 3-4(sec): [base] -> [camera2] -> [marker]
+    #[test]
+    fn test_nearest_valid_time() {
+        let mut tf_buffer = TfBuffer::new();
+        let transform = |parent: &str, child: &str, sec| {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = parent.to_string();
+            transform.header.stamp = rosrust::Time { sec, nsec: 0 };
+            transform.child_frame_id = child.to_string();
+            transform.transform.rotation.w = 1.0;
+            TFMessage {
+                transforms: vec![transform],
+            }
+        };
+        tf_buffer.handle_incoming_transforms(transform("base", "camera1", 0), true);
+        tf_buffer.handle_incoming_transforms(transform("base", "camera2", 0), true);
+        for sec in [1, 2] {
+            tf_buffer.handle_incoming_transforms(transform("camera1", "marker", sec), false);
+        }
+        for sec in [3, 4] {
+            tf_buffer.handle_incoming_transforms(transform("camera2", "marker", sec), false);
+        }
+
+        let nearest = |sec: f64| tf_buffer.nearest_valid_time("base", "marker", sec);
+        assert_eq!(nearest(1.5), Some(Time::from_nanos(1_500_000_000)));
+        assert_eq!(nearest(2.4), Some(Time::from_seconds(2)));
+        assert_eq!(nearest(2.6), Some(Time::from_seconds(3)));
+        assert_eq!(nearest(10.0), Some(Time::from_seconds(4)));
+        assert_eq!(tf_buffer.nearest_valid_time("base", "unknown", 1.5), None);
+        assert_eq!(
+            tf_buffer.suggest_cache_duration(),
+            Duration::from_seconds(10)
+        );
+    }
+
+    /// Tests that a robust lookup steps over a tiny gap between coverages that makes the exact lookup fail.
+    #[test]
+    fn test_lookup_transform_robust() {
+        let mut tf_buffer = TfBuffer::new();
+        let transform = |parent: &str, child: &str, nanos| {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = parent.to_string();
+            transform.header.stamp = Time::from_nanos(nanos);
+            transform.child_frame_id = child.to_string();
+            transform.transform.rotation.w = 1.0;
+            TFMessage {
+                transforms: vec![transform],
+            }
+        };
+        tf_buffer.handle_incoming_transforms(transform("base", "camera1", 0), true);
+        tf_buffer.handle_incoming_transforms(transform("base", "camera2", 0), true);
+        for nanos in [1_000_000_000, 2_000_000_000] {
+            tf_buffer.handle_incoming_transforms(transform("camera1", "marker", nanos), false);
+        }
+        for nanos in [2_002_000_000, 3_000_000_000] {
+            tf_buffer.handle_incoming_transforms(transform("camera2", "marker", nanos), false);
+        }
+
+        let time = Time::from_nanos(2_000_500_000);
+        let window = Duration::from_nanos(10_000_000);
+        assert!(tf_buffer.lookup_transform("base", "marker", time).is_err());
+        let (_, used) = tf_buffer
+            .lookup_transform_robust("base", "marker", time, window)
+            .unwrap();
+        assert_eq!(used, Time::from_seconds(2));
+        let (_, used) = tf_buffer
+            .lookup_transform_robust("base", "marker", Time::from_nanos(1_500_000_000), window)
+            .unwrap();
+        assert_eq!(used, Time::from_nanos(1_500_000_000));
+        assert!(tf_buffer
+            .lookup_transform_robust("base", "marker", time, Duration::from_nanos(100_000))
+            .is_err());
+    }
+
+    #[test]
+    fn test_transform_accel() {
+        let mut tf_buffer = TfBuffer::new();
+        let imu = TransformStampedBuilder::new()
+            .parent("base_link")
+            .child("imu")
+            .translation(0.3, 0.0, 0.1)
+            .rotation_rpy(0.0, 0.0, std::f64::consts::FRAC_PI_2)
+            .build();
+        tf_buffer.add_published_transform(&imu, true).unwrap();
+
+        let mut accel = Vector3Stamped::default();
+        accel.header.frame_id = "imu".to_string();
+        accel.vector.x = 2.0;
+        let result = tf_buffer
+            .transform_accel(&accel, "base_link", &Time::new(), false)
+            .unwrap();
+        assert_eq!(result.header.frame_id, "base_link");
+        assert!(result.vector.x.abs() < 1e-9);
+        assert!((result.vector.y - 2.0).abs() < 1e-9);
+        assert!(result.vector.z.abs() < 1e-9);
+        assert!(matches!(
+            tf_buffer.transform_accel(&accel, "base_link", &Time::new(), true),
+            Err(TfError::InvalidArgument(_))
+        ));
+    }
+
+    /// Tests that stamped helpers express their output in the target frame, keeping the input stamp.
+    #[test]
+    fn test_transform_helpers_headers() {
+        let mut tf_buffer = TfBuffer::builder()
+            .gravity(
+                "world",
+                Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 9.81,
+                },
+            )
+            .build();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = Time {
+            sec: 0,
+            nsec: 500_000_000,
+        };
+
+        let mut point = PointStamped::default();
+        point.header.frame_id = "camera".to_string();
+        point.header.stamp = Time { sec: 1, nsec: 0 };
+        point.header.seq = 7;
+        let result = tf_buffer.transform_point(&point, "world", &time).unwrap();
+        assert_eq!(result.header.frame_id, "world");
+        assert_eq!(result.header.stamp, point.header.stamp);
+        assert_eq!(result.header.seq, point.header.seq);
+
+        let mut pose = PoseStamped {
+            header: point.header.clone(),
+            ..Default::default()
+        };
+        pose.pose.orientation.w = 1.0;
+        let result = tf_buffer.transform_pose(&pose, "world", &time).unwrap();
+        assert_eq!(result.header.frame_id, "world");
+        assert_eq!(result.header.stamp, pose.header.stamp);
+        assert_eq!(result.header.seq, pose.header.seq);
+
+        let mut pose = PoseWithCovarianceStamped {
+            header: point.header.clone(),
+            ..Default::default()
+        };
+        pose.pose.pose.orientation.w = 1.0;
+        let result = tf_buffer
+            .transform_pose_with_covariance(&pose, "world", &time)
+            .unwrap();
+        assert_eq!(result.header.frame_id, "world");
+        assert_eq!(result.header.stamp, pose.header.stamp);
+        assert_eq!(result.header.seq, pose.header.seq);
+
+        let mut accel = Vector3Stamped::default();
+        accel.header.frame_id = "camera".to_string();
+        accel.header.stamp = Time { sec: 1, nsec: 0 };
+        accel.header.seq = 7;
+        for remove_gravity in [false, true] {
+            let result = tf_buffer
+                .transform_accel(&accel, "item", &time, remove_gravity)
+                .unwrap();
+            assert_eq!(result.header.frame_id, "item");
+            assert_eq!(result.header.stamp, accel.header.stamp);
+            assert_eq!(result.header.seq, accel.header.seq);
+        }
+    }
+
+    #[test]
+    fn test_transform_accel_remove_gravity() {
+        let gravity = Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 9.81,
+        };
+        let mut tf_buffer = TfBuffer::builder().gravity("world", gravity).build();
+        let pitch = 0.3_f64;
+        let tilted = TransformStampedBuilder::new()
+            .parent("world")
+            .child("imu")
+            .rotation_rpy(0.0, pitch, 0.0)
+            .build();
+        tf_buffer.add_published_transform(&tilted, true).unwrap();
+
+        // A stationary accelerometer pitched by `pitch` measures gravity partly along its x axis.
+        let mut accel = Vector3Stamped::default();
+        accel.header.frame_id = "imu".to_string();
+        accel.vector.x = -9.81 * pitch.sin();
+        accel.vector.z = 9.81 * pitch.cos();
+        let raw = tf_buffer
+            .transform_accel(&accel, "imu", &Time::new(), false)
+            .unwrap();
+        assert_eq!(raw.vector, accel.vector);
+        let result = tf_buffer
+            .transform_accel(&accel, "imu", &Time::new(), true)
+            .unwrap();
+        for value in [result.vector.x, result.vector.y, result.vector.z] {
+            assert!(value.abs() < 1e-9);
+        }
+    }
+
+    /// Tests that with hysteresis, lookups keep the path they used until it becomes invalid.
+    /// time 1-3(sec): [base] -> [camera1] -> [marker]
+    /// time 2-4(sec): [base] -> [camera2] -> [marker]
+    #[test]
+    fn test_path_hysteresis() {
+        let mut tf_buffer = TfBuffer::builder().path_hysteresis(true).build();
+        for (camera, x) in [("camera1", 1.0), ("camera2", 2.0)] {
+            let mut transform = TransformStamped::default();
+            transform.header.frame_id = "base".to_string();
+            transform.child_frame_id = camera.to_string();
+            transform.transform.rotation.w = 1.0;
+            transform.transform.translation.x = x;
+            tf_buffer.add_published_transform(&transform, true).unwrap();
+        }
+        for (camera, secs) in [("camera1", [1, 3]), ("camera2", [2, 4])] {
+            for sec in secs {
+                let mut transform = TransformStamped::default();
+                transform.header.frame_id = camera.to_string();
+                transform.header.stamp = Time { sec, nsec: 0 };
+                transform.child_frame_id = "marker".to_string();
+                transform.transform.rotation.w = 1.0;
+                tf_buffer
+                    .add_published_transform(&transform, false)
+                    .unwrap();
+            }
+        }
+
+        let parent_of_marker = |time: f64| {
+            let (_, sources) = tf_buffer
+                .lookup_transform_with_sources("base", "marker", TimePoint::from(time).0)
+                .unwrap();
+            sources[1].parent.clone()
+        };
+        assert_eq!(parent_of_marker(1.5), "camera1");
+        for _ in 0..3 {
+            assert_eq!(parent_of_marker(2.5), "camera1");
+        }
+        assert_eq!(parent_of_marker(3.5), "camera2");
+        for _ in 0..3 {
+            assert_eq!(parent_of_marker(2.5), "camera2");
+        }
+    }
+
+    #[test]
+    fn test_lookup_transform_averaged() {
+        let mut tf_buffer = TfBuffer::new();
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut noise = || (rng.next() % 2000) as f64 / 1000.0 - 1.0;
+        for index in 0..1000 {
+            let transform = TransformStampedBuilder::new()
+                .parent("odom")
+                .child("base_link")
+                .stamp(Time::from_nanos(index * 10_000_000))
+                .translation(1.0 + 0.1 * noise(), 0.0, 0.0)
+                .rotation_rpy(0.0, 0.0, 0.1 * noise())
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+
+        let window = Duration::from_nanos(200_000_000);
+        let (mut raw_variance, mut averaged_variance) = (0.0, 0.0);
+        for index in 1..90 {
+            let center = Time::from_nanos(index * 100_000_000 + 5_000_000);
+            let raw = tf_buffer
+                .lookup_transform("odom", "base_link", center)
+                .unwrap();
+            let averaged = tf_buffer
+                .lookup_transform_averaged("odom", "base_link", &center, window)
+                .unwrap();
+            assert_eq!(averaged.header.stamp, center);
+            let yaw = |transform: &TransformStamped| {
+                isometry_from_transform(&transform.transform)
+                    .rotation
+                    .euler_angles()
+                    .2
+            };
+            raw_variance += (raw.transform.translation.x - 1.0).powi(2) + yaw(&raw).powi(2);
+            averaged_variance +=
+                (averaged.transform.translation.x - 1.0).powi(2) + yaw(&averaged).powi(2);
+        }
+        assert!(averaged_variance < raw_variance / 4.0);
+
+        // Falls back to the samples available within the window.
+        let edge = Time::from_nanos(9_990_000_000);
+        assert!(tf_buffer
+            .lookup_transform_averaged("odom", "base_link", &edge, window)
+            .is_ok());
+        assert!(tf_buffer
+            .lookup_transform_averaged("odom", "base_link", &Time::from_seconds(20), window)
+            .is_err());
+
+        // Near the start of the timeline, times before it are skipped instead of looking up the latest data.
+        let mut tf_buffer = TfBuffer::new();
+        for sec in 0..=10 {
+            let transform = TransformStampedBuilder::new()
+                .parent("odom")
+                .child("base_link")
+                .stamp(Time::from_seconds(sec))
+                .translation(sec as f64, 0.0, 0.0)
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+        let averaged = tf_buffer
+            .lookup_transform_averaged(
+                "odom",
+                "base_link",
+                &Time::from_nanos(100_000_000),
+                Duration::from_seconds(1),
+            )
+            .unwrap();
+        assert!((averaged.transform.translation.x - 0.35).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_normalize_quaternions() {
+        let mut tf_buffer = TfBuffer::builder().min_quaternion_norm(0.5).build();
+        let mut transform = TransformStampedBuilder::new()
+            .parent("base_link")
+            .child("camera")
+            .rotation_rpy(0.2, 0.0, 0.4)
+            .build();
+        let rotation = &mut transform.transform.rotation;
+        for value in [
+            &mut rotation.x,
+            &mut rotation.y,
+            &mut rotation.z,
+            &mut rotation.w,
+        ] {
+            *value *= 1.01;
+        }
+        tf_buffer.add_published_transform(&transform, true).unwrap();
+        let stored = &tf_buffer.transform_data[&TfGraphNode {
+            child: "camera".to_string(),
+            parent: "base_link".to_string(),
+        }]
+            .transform_chain
+            .last()
+            .unwrap()
+            .transform
+            .rotation;
+        let norm =
+            (stored.x * stored.x + stored.y * stored.y + stored.z * stored.z + stored.w * stored.w)
+                .sqrt();
+        assert!((norm - 1.0).abs() < 1e-12);
+
+        let mut degenerate = TransformStampedBuilder::new()
+            .parent("base_link")
+            .child("lidar")
+            .build();
+        degenerate.transform.rotation.w = 0.1;
+        assert!(matches!(
+            tf_buffer.add_published_transform(&degenerate, true),
+            Err(TfError::InvalidQuaternion(frame, norm)) if frame == "lidar" && (norm - 0.1).abs() < 1e-12
+        ));
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![degenerate],
+            },
+            true,
+        );
+        assert!(!tf_buffer.frame_exists("lidar"));
+    }
+
+    /// Tests that the path with the fewest edges is chosen when frames are connected in several ways.
+    /// [a] -> [b] -> [d], [a] -> [c] -> [d], [a] -> [e] -> [f] -> [g] -> [d]
+    #[test]
+    fn test_shortest_path() {
+        let edges = [
+            ("a", "b"),
+            ("b", "d"),
+            ("a", "c"),
+            ("c", "d"),
+            ("a", "e"),
+            ("e", "f"),
+            ("f", "g"),
+            ("g", "d"),
+        ];
+        // Neighbours are visited in hash order, which differs between buffers.
+        for _ in 0..20 {
+            let mut tf_buffer = TfBuffer::new();
+            for (parent, child) in edges {
+                let transform = TransformStampedBuilder::new()
+                    .parent(parent)
+                    .child(child)
+                    .translation(1.0, 0.0, 0.0)
+                    .build();
+                tf_buffer.add_published_transform(&transform, true).unwrap();
+            }
+            for (from, to) in [("a", "d"), ("d", "a")] {
+                let path = tf_buffer
+                    .retrieve_transform_path(from.to_string(), to.to_string(), Time::new())
+                    .unwrap();
+                assert_eq!(path.len(), 2, "{path:?}");
+                let result = tf_buffer.lookup_transform(from, to, Time::new()).unwrap();
+                assert!((result.transform.translation.x.abs() - 2.0).abs() < 1e-9);
+            }
+            assert_eq!(
+                tf_buffer
+                    .retrieve_transform_path("g".to_string(), "b".to_string(), Time::new())
+                    .unwrap(),
+                vec!["d", "b"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_reject_cycle() {
+        let mut tf_buffer = TfBuffer::new();
+        for (parent, child) in [("a", "b"), ("b", "c")] {
+            let transform = TransformStampedBuilder::new()
+                .parent(parent)
+                .child(child)
+                .build();
+            tf_buffer.add_published_transform(&transform, true).unwrap();
+        }
+        let closing = TransformStampedBuilder::new()
+            .parent("c")
+            .child("a")
+            .build();
+        assert!(matches!(
+            tf_buffer.add_published_transform(&closing, true),
+            Err(TfError::TransformTreeCycle(parent, child)) if parent == "c" && child == "a"
+        ));
+        let reversed = TransformStampedBuilder::new()
+            .parent("b")
+            .child("a")
+            .build();
+        assert!(tf_buffer.add_published_transform(&reversed, true).is_err());
+        assert!(!tf_buffer.transform_data.contains_key(&TfGraphNode {
+            child: "a".to_string(),
+            parent: "c".to_string(),
+        }));
+        tf_buffer.handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![closing],
+            },
+            false,
+        );
+        assert!(tf_buffer.validate().is_ok());
+
+        // Republishing an existing edge, or adding a second parent, is no loop.
+        let republished = TransformStampedBuilder::new()
+            .parent("a")
+            .child("b")
+            .build();
+        assert!(tf_buffer
+            .add_published_transform(&republished, true)
+            .is_ok());
+        let second_parent = TransformStampedBuilder::new()
+            .parent("a")
+            .child("c")
+            .build();
+        assert!(tf_buffer
+            .add_published_transform(&second_parent, true)
+            .is_ok());
+    }
+
+    /// Tests lookups starting or ending at the root of the tree, or going through it.
+    #[test]
+    fn test_lookup_through_root() {
+        let mut tf_buffer = TfBuffer::new();
+        build_test_tree(&mut tf_buffer, 0f64);
+        build_test_tree(&mut tf_buffer, 1f64);
+        let time = Time {
+            sec: 0,
+            nsec: 500_000_000,
+        };
+        let expect = |from: &str, to: &str, x: f64, y: f64| {
+            let result = tf_buffer.lookup_transform(from, to, time).unwrap();
+            assert_eq!(result.header.frame_id, from);
+            assert_eq!(result.child_frame_id, to);
+            assert_eq!(result.header.stamp, time);
+            let mut expected = TransformStampedBuilder::new()
+                .parent(from)
+                .child(to)
+                .stamp(time)
+                .translation(x, y, 0.0)
+                .build();
+            expected.header.seq = result.header.seq;
+            assert_approx_eq(result, expected);
+        };
+        // root -> root
+        expect("world", "world", 0.0, 0.0);
+        // root -> leaf and back
+        expect("world", "camera", 0.5, 0.5);
+        expect("camera", "world", -0.5, -0.5);
+        // leaf -> leaf through the root
+        expect("item", "camera", -0.5, 0.5);
+        expect("camera", "item", 0.5, -0.5);
+        // leaf -> itself
+        expect("camera", "camera", 0.0, 0.0);
+    }
+
+    /// Tests `can_transform` on the tree of `test_dynamic_tree`
+    /// time 1-2(sec): [base] -> [camera1] -> [marker] -> [target]
+    /// time 3-4(sec): [base] -> [camera2] -> [marker] -> [target]
+    #[test]
+    fn test_can_transform_dynamic_tree() {
+        let mut tf_buffer = TfBuffer::new();
+        for (parent, child) in [
+            ("base", "camera1"),
+            ("base", "camera2"),
+            ("marker", "target"),
+        ] {
+            let transform = TransformStampedBuilder::new()
+                .parent(parent)
+                .child(child)
+                .stamp(Time { sec: 1, nsec: 0 })
+                .build();
+            tf_buffer.add_published_transform(&transform, true).unwrap();
+        }
+        for (camera, sec) in [
+            ("camera1", 1),
+            ("camera1", 2),
+            ("camera2", 3),
+            ("camera2", 4),
+        ] {
+            let transform = TransformStampedBuilder::new()
+                .parent(camera)
+                .child("marker")
+                .stamp(Time { sec, nsec: 0 })
+                .build();
+            tf_buffer
+                .add_published_transform(&transform, false)
+                .unwrap();
+        }
+
+        for nanos in [
+            1_000_000_000,
+            2_000_000_000,
+            3_000_000_000,
+            3_500_000_000,
+            4_000_000_000,
+        ] {
+            let time = Time::from_nanos(nanos);
+            assert!(tf_buffer.can_transform("base", "target", &time));
+            assert!(tf_buffer
+                .can_transform_with_error("base", "target", &time)
+                .is_ok());
+        }
+        for nanos in [2_500_000_000, 4_500_000_000] {
+            let time = Time::from_nanos(nanos);
+            assert!(!tf_buffer.can_transform("base", "target", &time));
+            assert!(matches!(
+                tf_buffer.can_transform_with_error("base", "target", &time),
+                Err(TfError::CouldNotFindTransform(..))
+            ));
+        }
+        // Static edges are valid at any time.
+        assert!(tf_buffer.can_transform("target", "marker", &Time::from_nanos(4_500_000_000)));
+        assert!(tf_buffer.can_transform("base", "base", &Time::new()));
+        assert!(!tf_buffer.can_transform("base", "unknown", &Time::new()));
+    }
+
     /// Tests a case in which the tree structure changes dynamically
     /// time 1-2(sec): [base] -> [camera1] -> [marker] -> [target]
     /// time 3-4(sec): [base] -> [camera2] -> [marker] -> [target]
@@ -617,7 +6016,6 @@ mod test {
             },
         };
         tf_buffer.add_transform(&base_to_camera1, true);
-        tf_buffer.add_transform(&get_inverse(&base_to_camera1), true);
 
         let base_to_camera2 = TransformStamped {
             child_frame_id: "camera2".to_string(),
@@ -641,7 +6039,6 @@ mod test {
             },
         };
         tf_buffer.add_transform(&base_to_camera2, true);
-        tf_buffer.add_transform(&get_inverse(&base_to_camera2), true);
 
         let marker_to_target = TransformStamped {
             child_frame_id: "target".to_string(),
@@ -665,7 +6062,6 @@ mod test {
             },
         };
         tf_buffer.add_transform(&marker_to_target, true);
-        tf_buffer.add_transform(&get_inverse(&marker_to_target), true);
 
         let mut camera1_to_marker = TransformStamped {
             child_frame_id: "marker".to_string(),
@@ -689,13 +6085,11 @@ mod test {
             },
         };
         tf_buffer.add_transform(&camera1_to_marker, false);
-        tf_buffer.add_transform(&get_inverse(&camera1_to_marker), false);
 
         camera1_to_marker.header.stamp.sec = 2;
         camera1_to_marker.header.seq += 1;
         camera1_to_marker.transform.translation.y = -1.0;
         tf_buffer.add_transform(&camera1_to_marker, false);
-        tf_buffer.add_transform(&get_inverse(&camera1_to_marker), false);
 
         let mut camera2_to_marker = TransformStamped {
             child_frame_id: "marker".to_string(),
@@ -719,13 +6113,11 @@ mod test {
             },
         };
         tf_buffer.add_transform(&camera2_to_marker, false);
-        tf_buffer.add_transform(&get_inverse(&camera2_to_marker), false);
 
         camera2_to_marker.header.stamp.sec = 4;
         camera2_to_marker.header.seq += 1;
         camera2_to_marker.transform.translation.y = -1.0;
         tf_buffer.add_transform(&camera2_to_marker, false);
-        tf_buffer.add_transform(&get_inverse(&camera2_to_marker), false);
 
         let result =
             tf_buffer.lookup_transform("base", "target", rosrust::Time { sec: 1, nsec: 0 });
@@ -830,13 +6222,11 @@ mod test {
         camera1_to_marker.transform.translation.x = 0.5;
         camera1_to_marker.transform.translation.y = 1.0;
         tf_buffer.add_transform(&camera1_to_marker, false);
-        tf_buffer.add_transform(&get_inverse(&camera1_to_marker), false);
 
         camera1_to_marker.header.stamp.sec = 6;
         camera1_to_marker.header.seq += 1;
         camera1_to_marker.transform.translation.y = -1.0;
         tf_buffer.add_transform(&camera1_to_marker, false);
-        tf_buffer.add_transform(&get_inverse(&camera1_to_marker), false);
 
         let result =
             tf_buffer.lookup_transform("base", "target", rosrust::Time { sec: 5, nsec: 0 });
@@ -877,4 +6267,61 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn test_compute_chain() {
+        let a_to_b = TransformStamped {
+            header: Header {
+                frame_id: "a".to_string(),
+                stamp: rosrust::Time { sec: 5, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: "b".to_string(),
+            transform: Transform {
+                translation: Vector3 {
+                    x: 1f64,
+                    y: 0f64,
+                    z: 0f64,
+                },
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+            },
+        };
+        let b_to_c = TransformStamped {
+            header: Header {
+                frame_id: "b".to_string(),
+                stamp: rosrust::Time { sec: 1, nsec: 0 },
+                ..Default::default()
+            },
+            child_frame_id: "c".to_string(),
+            transform: Transform {
+                translation: Vector3 {
+                    x: 0f64,
+                    y: 2f64,
+                    z: 0f64,
+                },
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: 0f64,
+                    w: 1f64,
+                },
+            },
+        };
+        let res = TfBuffer::compute_chain(&[a_to_b, b_to_c], "a", "c").unwrap();
+        assert_eq!(res.header.frame_id, "a");
+        assert_eq!(res.child_frame_id, "c");
+        assert_eq!(
+            res.transform.translation,
+            Vector3 {
+                x: 1f64,
+                y: 2f64,
+                z: 0f64,
+            }
+        );
+    }
 }
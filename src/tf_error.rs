@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use rosrust::Time;
+use rosrust::{Duration, Time};
 use thiserror::Error;
 
 use crate::transforms::geometry_msgs::TransformStamped;
@@ -10,14 +10,62 @@ use crate::transforms::geometry_msgs::TransformStamped;
 #[non_exhaustive]
 pub enum TfError {
     /// Error due to looking up too far in the past. I.E the information is no longer available in the TF Cache.
-    #[error("tf_rosrust: AttemptedLookupInPast {:?} < {:?}",.0, .1)]
-    AttemptedLookupInPast(Time, Box<TransformStamped>),
-    /// Error due to the transform not yet being available.
-    #[error("tf_rosrust: AttemptedLookupInFuture {:?} < {:?}",.0, .1)]
-    AttemptedLookUpInFuture(Box<TransformStamped>, Time),
+    /// `gap` is how far the requested time lies before the oldest sample.
+    #[error("tf_rosrust: AttemptedLookupInPast {:?} < {:?} (gap {:?})", .requested, .oldest, .gap)]
+    AttemptedLookupInPast {
+        requested: Time,
+        oldest: Box<TransformStamped>,
+        gap: Duration,
+    },
+    /// Error due to the transform not yet being available. `gap` is how far the requested time lies after the newest
+    /// sample.
+    #[error("tf_rosrust: AttemptedLookupInFuture {:?} < {:?} (gap {:?})", .newest, .requested, .gap)]
+    AttemptedLookUpInFuture {
+        newest: Box<TransformStamped>,
+        requested: Time,
+        gap: Duration,
+    },
     /// There is no path between the from and to frame.
     #[error("tf_rosrust: CouldNotFindTransform {} -> {} ({:?})", .0, .1, .2)]
     CouldNotFindTransform(String, String, HashMap<String, HashSet<String>>),
+    /// The edge leading to this frame exists, but all of its samples have been pruned.
+    #[error("tf_rosrust: NoDataForFrame {}", .0)]
+    NoDataForFrame(String),
+    /// The requested time differs from the stamp of the only sample of a dynamic edge, so there is nothing to
+    /// interpolate between. Holds the child frame and the number of samples available.
+    #[error("tf_rosrust: InsufficientSamplesForInterpolation {} ({} available)", .0, .1)]
+    InsufficientSamplesForInterpolation(String, usize),
+    /// The newest sample a lookup was computed from is older than the allowed age.
+    #[error("tf_rosrust: TransformTooOld newest sample {:?} is more than {:?} older than {:?}", .0, .2, .1)]
+    TransformTooOld(Time, Time, Duration),
+    /// No path between the from and to frame exists within the configured maximum path length.
+    #[error("tf_rosrust: PathTooLong {} -> {} (max {} edges)", .0, .1, .2)]
+    PathTooLong(String, String, usize),
+    /// The frame's part of the tree does not have exactly one root, i.e. one frame without a published parent.
+    #[error("tf_rosrust: AmbiguousRoot for {} (roots: {:?})", .0, .1)]
+    AmbiguousRoot(String, Vec<String>),
+    /// Adding the transform from the first frame to the second would close a loop, since the first frame is already a
+    /// descendant of the second.
+    #[error("tf_rosrust: TransformTreeCycle {} -> {}", .0, .1)]
+    TransformTreeCycle(String, String),
+    /// The rotation of the transform to the frame is too far from a unit quaternion to be normalized. Holds the child
+    /// frame and the norm of the quaternion.
+    #[error("tf_rosrust: InvalidQuaternion for {} (norm {})", .0, .1)]
+    InvalidQuaternion(String, f64),
+    /// The frame is published with a new parent while it already has another one, which the buffer was configured to
+    /// reject. Holds the child frame, the existing parent and the new parent.
+    #[error("tf_rosrust: MultipleParents for {} ({} and {})", .0, .1, .2)]
+    MultipleParents(String, String, String),
+    /// A transform to be sent or stored has an empty frame id, or is a transform from a frame to itself.
+    #[error("tf_rosrust: InvalidFrameId {}", .0)]
+    InvalidFrameId(String),
+    /// A snapshot file could not be written, read or decoded.
+    #[cfg(feature = "snapshot_file")]
+    #[error("tf_rosrust: SnapshotFile {}", .0)]
+    SnapshotFile(String),
+    /// The arguments of a computation do not allow it to be carried out.
+    #[error("tf_rosrust: InvalidArgument {}", .0)]
+    InvalidArgument(String),
     /// In the event that a write is simultaneously happening with a read of the same tf buffer
     #[error("tf_rosrust: CouldNotAcquireLock")]
     CouldNotAcquireLock,
@@ -25,3 +73,26 @@ pub enum TfError {
     #[error("tf_rosrust: rosrust error {:?}", .0)]
     Rosrust(String),
 }
+
+/// Rejects transforms with an empty frame id or from a frame to itself, which would corrupt any buffer storing them.
+pub(crate) fn validate_frame_ids(tf: &TransformStamped) -> Result<(), TfError> {
+    if tf.header.frame_id.is_empty() {
+        return Err(TfError::InvalidFrameId(format!(
+            "empty frame_id for child frame {:?}",
+            tf.child_frame_id
+        )));
+    }
+    if tf.child_frame_id.is_empty() {
+        return Err(TfError::InvalidFrameId(format!(
+            "empty child_frame_id for frame {:?}",
+            tf.header.frame_id
+        )));
+    }
+    if tf.header.frame_id == tf.child_frame_id {
+        return Err(TfError::InvalidFrameId(format!(
+            "frame {:?} cannot be its own parent",
+            tf.child_frame_id
+        )));
+    }
+    Ok(())
+}
@@ -1,93 +1,532 @@
+use std::collections::VecDeque;
+
 use rosrust::{Duration, Time};
 
 use crate::{
     tf_error::TfError,
-    transforms::{geometry_msgs::TransformStamped, interpolate, to_transform_stamped},
+    transforms::{extrapolate, geometry_msgs::TransformStamped, interpolate, to_transform_stamped},
+    utils::duration_as_ns_i64,
 };
 
-fn get_nanos(dur: rosrust::Duration) -> i64 {
-    i64::from(dur.sec) * 1_000_000_000 + i64::from(dur.nsec)
+/// How the samples of each edge are stored.
+///
+/// Both strategies keep samples sorted by stamp and answer lookups with a binary search, so they only differ in cost.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageStrategy {
+    /// Samples are kept in a contiguous `Vec`. Lookups search a single slice, which is the most cache-friendly layout,
+    /// but every insertion and eviction shifts the remaining samples. Best for many lookups and few updates.
+    ReadOptimized,
+    /// Samples are kept in a `VecDeque`. In-order samples are appended in O(1) amortized time,
+    /// at the cost of lookups searching a ring that may wrap around. Best for high-rate updates.
+    #[default]
+    WriteOptimized,
+}
+
+/// How lookups between two samples of an edge are answered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InterpolationMode {
+    /// Interpolates linearly in translation and spherically in rotation, for continuous trajectories.
+    #[default]
+    Linear,
+    /// Returns the sample closest in time, the earlier one on a tie.
+    Nearest,
+    /// Returns the last sample at or before the lookup time, for discrete waypoints.
+    Previous,
+}
+
+/// Whether lookups past the newest sample of an edge are answered by extrapolating its latest motion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Extrapolation {
+    /// Lookups past the newest sample fail with [`TfError::AttemptedLookUpInFuture`].
+    #[default]
+    None,
+    /// Lookups up to this long past the newest sample continue the motion between the two newest samples: linearly
+    /// for the translation, at constant angular velocity for the rotation. Lookups further in the future still fail.
+    LinearUpTo(Duration),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum TransformStorage {
+    Sorted(Vec<TransformStamped>),
+    Ring(VecDeque<TransformStamped>),
+}
+
+impl TransformStorage {
+    fn new(strategy: StorageStrategy) -> Self {
+        match strategy {
+            StorageStrategy::ReadOptimized => Self::Sorted(Vec::new()),
+            StorageStrategy::WriteOptimized => Self::Ring(VecDeque::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Sorted(chain) => chain.len(),
+            Self::Ring(chain) => chain.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&TransformStamped> {
+        match self {
+            Self::Sorted(chain) => chain.get(index),
+            Self::Ring(chain) => chain.get(index),
+        }
+    }
+
+    pub fn first(&self) -> Option<&TransformStamped> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&TransformStamped> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TransformStamped> {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// An empty storage using the same strategy.
+    fn new_like(&self) -> Self {
+        match self {
+            Self::Sorted(_) => Self::Sorted(Vec::new()),
+            Self::Ring(_) => Self::Ring(VecDeque::new()),
+        }
+    }
+
+    /// Whether `time` lies within the stored samples. The zero time is covered by any sample.
+    fn covers(&self, time: Time) -> bool {
+        match (self.first(), self.last()) {
+            (Some(first), Some(last)) => {
+                time.nanos() == 0 || (time >= first.header.stamp && time <= last.header.stamp)
+            }
+            _ => false,
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Self::Sorted(chain) => chain.clear(),
+            Self::Ring(chain) => chain.clear(),
+        }
+    }
+
+    fn binary_search_time(&self, time: Time) -> Result<usize, usize> {
+        match self {
+            Self::Sorted(chain) => {
+                chain.binary_search_by(|element| element.header.stamp.cmp(&time))
+            }
+            Self::Ring(chain) => chain.binary_search_by(|element| element.header.stamp.cmp(&time)),
+        }
+    }
+
+    /// Inserts `msg` in stamp order, returning whether it arrived out of order, i.e. before the newest sample.
+    ///
+    /// A sample with the stamp of a stored one replaces it, so that the samples keep strictly increasing stamps.
+    fn insert(&mut self, msg: TransformStamped) -> bool {
+        if self
+            .last()
+            .is_none_or(|last| last.header.stamp < msg.header.stamp)
+        {
+            match self {
+                Self::Sorted(chain) => chain.push(msg),
+                Self::Ring(chain) => chain.push_back(msg),
+            }
+            return false;
+        }
+        match self.binary_search_time(msg.header.stamp) {
+            Ok(index) => {
+                match self {
+                    Self::Sorted(chain) => chain[index] = msg,
+                    Self::Ring(chain) => chain[index] = msg,
+                }
+                false
+            }
+            Err(index) => {
+                match self {
+                    Self::Sorted(chain) => chain.insert(index, msg),
+                    Self::Ring(chain) => chain.insert(index, msg),
+                }
+                true
+            }
+        }
+    }
+
+    /// Removes every sample older than `time_to_keep`.
+    ///
+    /// A ring pops evicted samples off its front, which costs O(1) per sample and leaves the others in place.
+    fn remove_older_than(&mut self, time_to_keep: Time) {
+        match self {
+            Self::Sorted(chain) => {
+                let index = chain.partition_point(|sample| sample.header.stamp < time_to_keep);
+                chain.drain(..index);
+            }
+            Self::Ring(chain) => {
+                while chain
+                    .front()
+                    .is_some_and(|sample| sample.header.stamp < time_to_keep)
+                {
+                    chain.pop_front();
+                }
+            }
+        }
+    }
 }
 
-fn binary_search_time(chain: &[TransformStamped], time: Time) -> Result<usize, usize> {
-    chain.binary_search_by(|element| element.header.stamp.cmp(&time))
+/// The stored samples a lookup is computed from.
+pub(crate) enum Bracket<'a> {
+    /// A single sample answers the lookup as is.
+    Exact(&'a TransformStamped),
+    /// The samples before and after the requested time, and the interpolation weight of the earlier one.
+    Between(&'a TransformStamped, &'a TransformStamped, f64),
+    /// The two newest samples, and how many times the motion between them continues until the requested time.
+    Extrapolated(&'a TransformStamped, &'a TransformStamped, f64),
 }
 
 #[derive(Clone, Debug)]
 pub(crate) struct TfIndividualTransformChain {
+    child_frame_id: String,
     cache_duration: Duration,
     static_tf: bool,
-    //TODO:  Implement a circular buffer. Current method is slow.
-    pub(crate) transform_chain: Vec<TransformStamped>,
+    /// Samples sorted by stamp. With the default [`StorageStrategy::WriteOptimized`], a ring buffer.
+    pub(crate) transform_chain: TransformStorage,
+    /// Dynamic samples received on a static edge. They override the static value within their range.
+    dynamic_overrides: TransformStorage,
+    /// How far before the oldest sample lookups are still answered with it.
+    past_tolerance: Duration,
+    /// How far after the newest sample lookups are still answered with it.
+    future_tolerance: Duration,
+    extrapolation: Extrapolation,
+    interpolation_mode: InterpolationMode,
 }
 
 impl TfIndividualTransformChain {
-    pub fn new(static_tf: bool, cache_duration: Duration) -> Self {
+    pub fn new(
+        child_frame_id: String,
+        static_tf: bool,
+        cache_duration: Duration,
+        storage: StorageStrategy,
+    ) -> Self {
         Self {
+            child_frame_id,
             cache_duration,
-            transform_chain: Vec::new(),
+            transform_chain: TransformStorage::new(storage),
+            dynamic_overrides: TransformStorage::new(storage),
             static_tf,
+            past_tolerance: Duration::new(),
+            future_tolerance: Duration::new(),
+            extrapolation: Extrapolation::None,
+            interpolation_mode: InterpolationMode::Linear,
         }
     }
 
-    pub fn newest_stamp(&self) -> Option<Time> {
-        self.transform_chain.last().map(|x| x.header.stamp)
+    /// Answers lookups up to `past_tolerance` before the oldest sample and up to `future_tolerance` after the newest
+    /// sample with that sample, instead of failing.
+    pub fn set_tolerances(&mut self, past_tolerance: Duration, future_tolerance: Duration) {
+        self.past_tolerance = past_tolerance;
+        self.future_tolerance = future_tolerance;
     }
 
-    pub fn add_to_buffer(&mut self, msg: TransformStamped) {
-        let index = binary_search_time(&self.transform_chain, msg.header.stamp)
-            .unwrap_or_else(|index| index);
-        self.transform_chain.insert(index, msg);
+    /// Answers lookups past the newest sample according to `extrapolation`.
+    pub fn set_extrapolation(&mut self, extrapolation: Extrapolation) {
+        self.extrapolation = extrapolation;
+    }
 
-        if let Some(newest_stamp) = self.newest_stamp() {
-            if newest_stamp > Time::from_nanos(0) + self.cache_duration {
-                let time_to_keep = newest_stamp - self.cache_duration;
-                let index =
-                    binary_search_time(&self.transform_chain, time_to_keep).unwrap_or_else(|x| x);
-                self.transform_chain.drain(..index);
-            }
+    /// Answers lookups between two samples according to `interpolation_mode`.
+    pub fn set_interpolation_mode(&mut self, interpolation_mode: InterpolationMode) {
+        self.interpolation_mode = interpolation_mode;
+    }
+
+    /// Changes how long dynamic samples are kept, dropping those already outside the new window.
+    pub fn set_cache_duration(&mut self, cache_duration: Duration) {
+        self.cache_duration = cache_duration;
+        self.prune_to_cache();
+    }
+
+    /// Whether lookups at `time`, after the newest of `samples`, are answered by extrapolation.
+    fn extrapolates_to(&self, samples: &TransformStorage, time: Time) -> bool {
+        let Extrapolation::LinearUpTo(limit) = self.extrapolation else {
+            return false;
+        };
+        samples.len() >= 2
+            && samples
+                .last()
+                .is_some_and(|last| time - last.header.stamp <= limit)
+    }
+
+    /// Whether the edge only holds static samples.
+    pub fn is_static(&self) -> bool {
+        self.static_tf && self.dynamic_overrides.is_empty()
+    }
+
+    /// Every stored sample, with whether it was received as a static transform.
+    #[cfg(any(feature = "snapshot_file", feature = "serde"))]
+    pub fn samples(&self) -> impl Iterator<Item = (&TransformStamped, bool)> {
+        self.transform_chain
+            .iter()
+            .map(|sample| (sample, self.static_tf))
+            .chain(self.dynamic_overrides.iter().map(|sample| (sample, false)))
+    }
+
+    /// Adds a dynamic sample. On a static edge, it overrides the static value within the range of dynamic samples.
+    ///
+    /// Returns whether the sample arrived out of order.
+    pub fn add_dynamic(&mut self, msg: TransformStamped) -> bool {
+        let reordered = self.add_dynamic_unpruned(msg);
+        self.prune_to_cache();
+        reordered
+    }
+
+    /// Adds a dynamic sample like [`Self::add_dynamic`], but leaves pruning to a later [`Self::prune_to_cache`].
+    ///
+    /// In-order samples are appended in O(1) amortized time with [`StorageStrategy::WriteOptimized`]. Samples already
+    /// outside of the cache duration are dropped.
+    pub fn add_dynamic_unpruned(&mut self, msg: TransformStamped) -> bool {
+        let cache_duration = self.cache_duration;
+        let samples = self.dynamic_samples_mut();
+        if Self::is_stale(samples, &msg, cache_duration) {
+            return false;
         }
+        samples.insert(msg)
     }
 
-    /// If timestamp is zero, return the latest transform.
-    pub fn get_closest_transform(&self, time: rosrust::Time) -> Result<TransformStamped, TfError> {
-        if time.nanos() == 0 {
-            return Ok(self.transform_chain.last().unwrap().clone());
+    /// Drops dynamic samples older than the cache duration before the newest one.
+    pub fn prune_to_cache(&mut self) {
+        let cache_duration = self.cache_duration;
+        Self::prune_within_cache(self.dynamic_samples_mut(), cache_duration);
+    }
+
+    fn dynamic_samples_mut(&mut self) -> &mut TransformStorage {
+        if self.static_tf {
+            &mut self.dynamic_overrides
+        } else {
+            &mut self.transform_chain
+        }
+    }
+
+    /// Adds a static sample, keeping every previous one if `retain_all` is set, however old.
+    ///
+    /// Dynamic samples already stored on the edge are kept as overrides of the static value.
+    /// Returns whether the sample arrived out of order.
+    pub fn add_static(&mut self, msg: TransformStamped, retain_all: bool) -> bool {
+        if !self.static_tf {
+            let empty = self.transform_chain.new_like();
+            self.dynamic_overrides = std::mem::replace(&mut self.transform_chain, empty);
+            self.static_tf = true;
+        }
+        if retain_all {
+            self.transform_chain.insert(msg)
+        } else {
+            self.transform_chain.clear();
+            self.transform_chain.insert(msg)
         }
+    }
+
+    /// Whether `msg` is older than the cache duration before the newest sample, i.e. would be pruned right away.
+    fn is_stale(
+        samples: &TransformStorage,
+        msg: &TransformStamped,
+        cache_duration: Duration,
+    ) -> bool {
+        samples
+            .last()
+            .is_some_and(|newest| newest.header.stamp - msg.header.stamp > cache_duration)
+    }
+
+    fn prune_within_cache(samples: &mut TransformStorage, cache_duration: Duration) {
+        if let Some(newest_stamp) = samples.last().map(|x| x.header.stamp) {
+            if newest_stamp > Time::from_nanos(0) + cache_duration {
+                let time_to_keep = newest_stamp - cache_duration;
+                samples.remove_older_than(time_to_keep);
+            }
+        }
+    }
 
+    /// Drops every sample older than `time_to_keep`. Static transforms are never pruned.
+    pub fn prune_older_than(&mut self, time_to_keep: Time) {
         if self.static_tf {
-            return Ok(self.transform_chain.last().unwrap().clone());
+            self.dynamic_overrides.remove_older_than(time_to_keep);
+            return;
+        }
+        self.transform_chain.remove_older_than(time_to_keep);
+    }
+
+    fn no_data(&self) -> TfError {
+        TfError::NoDataForFrame(self.child_frame_id.clone())
+    }
+
+    /// Borrows the stored sample answering a lookup at `time`, if it needs no interpolation.
+    ///
+    /// This is the case for static transforms, the latest sample (zero time) and exact stamp matches.
+    pub fn get_closest_ref(&self, time: rosrust::Time) -> Option<&TransformStamped> {
+        match self.bracket(time) {
+            Ok(Bracket::Exact(sample)) => Some(sample),
+            _ => None,
+        }
+    }
+
+    /// Whether a lookup at `time` is answered by the static value.
+    ///
+    /// A static value applies at all times, except within the range of the dynamic samples overriding it.
+    fn uses_static_value(&self, time: rosrust::Time) -> bool {
+        self.static_tf && !self.dynamic_overrides.covers(time)
+    }
+
+    /// The samples answering a lookup at `time`.
+    fn samples_for(&self, time: rosrust::Time) -> &TransformStorage {
+        if self.static_tf && !self.uses_static_value(time) {
+            &self.dynamic_overrides
+        } else {
+            &self.transform_chain
+        }
+    }
+
+    /// Finds the stored samples a lookup at `time` is computed from.
+    pub fn bracket(&self, time: rosrust::Time) -> Result<Bracket<'_>, TfError> {
+        let samples = self.samples_for(time);
+        if time.nanos() == 0 || self.uses_static_value(time) {
+            return samples
+                .last()
+                .map(Bracket::Exact)
+                .ok_or_else(|| self.no_data());
         }
 
-        match binary_search_time(&self.transform_chain, time) {
-            Ok(x) => return Ok(self.transform_chain.get(x).unwrap().clone()),
+        match samples.binary_search_time(time) {
+            Ok(x) => Ok(Bracket::Exact(samples.get(x).unwrap())),
             Err(x) => {
-                if x == 0 {
-                    return Err(TfError::AttemptedLookupInPast(
-                        time,
-                        Box::new(self.transform_chain.first().unwrap().clone()),
-                    ));
+                let (first, last) = match (samples.first(), samples.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return Err(self.no_data()),
+                };
+                if x == 0 && first.header.stamp - time <= self.past_tolerance {
+                    return Ok(Bracket::Exact(first));
+                }
+                if x >= samples.len() && time - last.header.stamp <= self.future_tolerance {
+                    return Ok(Bracket::Exact(last));
+                }
+                if x >= samples.len() && self.extrapolates_to(samples, time) {
+                    let earlier = samples.get(samples.len() - 2).unwrap();
+                    let span =
+                        duration_as_ns_i64(&(last.header.stamp - earlier.header.stamp)) as f64;
+                    let ratio = duration_as_ns_i64(&(time - last.header.stamp)) as f64 / span;
+                    return Ok(Bracket::Extrapolated(earlier, last, ratio));
                 }
-                if x >= self.transform_chain.len() {
-                    return Err(TfError::AttemptedLookUpInFuture(
-                        Box::new(self.transform_chain.last().unwrap().clone()),
-                        time,
+                if samples.len() == 1 {
+                    return Err(TfError::InsufficientSamplesForInterpolation(
+                        self.child_frame_id.clone(),
+                        1,
                     ));
                 }
-                let tf1 = self.transform_chain.get(x - 1).unwrap().clone().transform;
-                let tf2 = self.transform_chain.get(x).unwrap().clone().transform;
-                let time1 = self.transform_chain.get(x - 1).unwrap().header.stamp;
-                let time2 = self.transform_chain.get(x).unwrap().header.stamp;
-                let header = self.transform_chain.get(x).unwrap().header.clone();
-                let child_frame = self.transform_chain.get(x).unwrap().child_frame_id.clone();
-                let total_duration = get_nanos(time2 - time1) as f64;
-                let desired_duration = get_nanos(time - time1) as f64;
-                let weight = 1.0 - desired_duration / total_duration;
-                let final_tf = interpolate(tf1, tf2, weight);
-                let ros_msg = to_transform_stamped(final_tf, header.frame_id, child_frame, time);
-                Ok(ros_msg)
+                if x == 0 {
+                    return Err(TfError::AttemptedLookupInPast {
+                        requested: time,
+                        oldest: Box::new(first.clone()),
+                        gap: first.header.stamp - time,
+                    });
+                }
+                if x >= samples.len() {
+                    return Err(TfError::AttemptedLookUpInFuture {
+                        newest: Box::new(last.clone()),
+                        requested: time,
+                        gap: time - last.header.stamp,
+                    });
+                }
+                let earlier = samples.get(x - 1).unwrap();
+                let later = samples.get(x).unwrap();
+                let time1 = earlier.header.stamp;
+                let time2 = later.header.stamp;
+                let total_duration = duration_as_ns_i64(&(time2 - time1)) as f64;
+                let desired_duration = duration_as_ns_i64(&(time - time1)) as f64;
+                match self.interpolation_mode {
+                    InterpolationMode::Linear => {
+                        let weight = 1.0 - desired_duration / total_duration;
+                        Ok(Bracket::Between(earlier, later, weight))
+                    }
+                    InterpolationMode::Nearest if desired_duration * 2.0 > total_duration => {
+                        Ok(Bracket::Exact(later))
+                    }
+                    InterpolationMode::Nearest | InterpolationMode::Previous => {
+                        Ok(Bracket::Exact(earlier))
+                    }
+                }
+            }
+        }
+    }
+
+    /// If timestamp is zero, return the latest transform.
+    ///
+    /// Alongside the transform, returns the stamps of the two samples it was computed from.
+    pub fn get_closest_transform(
+        &self,
+        time: rosrust::Time,
+    ) -> Result<(TransformStamped, (Time, Time)), TfError> {
+        match self.bracket(time)? {
+            Bracket::Exact(sample) => {
+                let stamp = sample.header.stamp;
+                Ok((sample.clone(), (stamp, stamp)))
+            }
+            Bracket::Between(earlier, later, weight) => {
+                let final_tf =
+                    interpolate(earlier.transform.clone(), later.transform.clone(), weight);
+                let ros_msg = to_transform_stamped(
+                    final_tf,
+                    later.header.frame_id.clone(),
+                    later.child_frame_id.clone(),
+                    time,
+                );
+                Ok((ros_msg, (earlier.header.stamp, later.header.stamp)))
             }
+            Bracket::Extrapolated(earlier, later, ratio) => {
+                let final_tf = extrapolate(&earlier.transform, &later.transform, ratio);
+                let ros_msg = to_transform_stamped(
+                    final_tf,
+                    later.header.frame_id.clone(),
+                    later.child_frame_id.clone(),
+                    time,
+                );
+                Ok((ros_msg, (earlier.header.stamp, later.header.stamp)))
+            }
+        }
+    }
+
+    /// The number of stored samples, including dynamic overrides of a static value.
+    pub fn sample_count(&self) -> usize {
+        self.transform_chain.len() + self.dynamic_overrides.len()
+    }
+
+    /// The stamps of the oldest and newest stored samples, including dynamic overrides of a static value.
+    pub fn stamp_range(&self) -> Option<(Time, Time)> {
+        [&self.transform_chain, &self.dynamic_overrides]
+            .into_iter()
+            .filter_map(|samples| {
+                Some((samples.first()?.header.stamp, samples.last()?.header.stamp))
+            })
+            .reduce(|(first, last), (other_first, other_last)| {
+                (first.min(other_first), last.max(other_last))
+            })
+    }
+
+    /// The stamps of the oldest and newest samples of a dynamic edge. Static edges are valid at all times.
+    pub fn dynamic_range(&self) -> Option<(Time, Time)> {
+        if self.static_tf {
+            return None;
+        }
+        Some((
+            self.transform_chain.first()?.header.stamp,
+            self.transform_chain.last()?.header.stamp,
+        ))
+    }
+
+    /// How far `time` lies before the oldest sample, if this chain cannot serve it for that reason.
+    pub fn shortfall(&self, time: rosrust::Time) -> Option<Duration> {
+        if self.static_tf || time.nanos() == 0 {
+            return None;
         }
+        let first = self.transform_chain.first()?;
+        (time < first.header.stamp).then(|| first.header.stamp - time)
     }
 
     pub fn has_valid_transform(&self, time: rosrust::Time) -> bool {
@@ -102,6 +541,18 @@ impl TfIndividualTransformChain {
         let first = self.transform_chain.first().unwrap();
         let last = self.transform_chain.last().unwrap();
 
-        time.nanos() == 0 || (time >= first.header.stamp && time <= last.header.stamp)
+        time.nanos() == 0
+            || ((time >= first.header.stamp || first.header.stamp - time <= self.past_tolerance)
+                && (time <= last.header.stamp
+                    || time - last.header.stamp <= self.future_tolerance
+                    || self.extrapolates_to(&self.transform_chain, time)))
+    }
+
+    /// Whether path searches for a lookup at `time` go through this edge.
+    ///
+    /// Besides edges with a valid transform, this keeps dynamic edges with a single sample, so that lookups off its
+    /// stamp fail with [`TfError::InsufficientSamplesForInterpolation`] rather than as if the edge did not exist.
+    pub fn is_traversable(&self, time: rosrust::Time) -> bool {
+        self.has_valid_transform(time) || (!self.static_tf && self.transform_chain.len() == 1)
     }
 }
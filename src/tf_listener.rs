@@ -1,9 +1,16 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    future, mem,
+    sync::{mpsc, Arc, Condvar, Mutex, PoisonError, RwLock, RwLockReadGuard, TryLockError},
+    task::{Poll, Waker},
+    thread,
+    time::Instant,
+};
 
 use crate::{
     tf_buffer::TfBuffer,
     tf_error::TfError,
     transforms::{geometry_msgs::TransformStamped, tf2_msgs::TFMessage},
+    utils::{ros_duration_to_std, TimePoint},
 };
 
 ///This struct tries to be the same as the C++ version of `TransformListener`. Use this struct to lookup transforms.
@@ -25,48 +32,174 @@ use crate::{
 /// ```
 /// Do note that unlike the C++ variant of the TfListener, only one TfListener can be created at a time. Like its C++ counterpart,
 /// it must be scoped to exist through the lifetime of the program. One way to do this is using an `Arc` or `RwLock`.
+///
+/// If a thread panics while writing to the buffer, the buffer is poisoned: lookups fail with
+/// [`TfError::CouldNotAcquireLock`] and received transforms are dropped until [`TfListener::clear`] is called.
 pub struct TfListener {
     buffer: Arc<RwLock<TfBuffer>>,
-    _static_subscriber: rosrust::Subscriber,
-    _dynamic_subscriber: rosrust::Subscriber,
+    waiters: Arc<TransformWaiters>,
+    new_frame_callbacks: Arc<NewFrameCallbacks>,
+    _static_subscriber: Option<rosrust::Subscriber>,
+    _dynamic_subscriber: Option<rosrust::Subscriber>,
+}
+
+/// Where a [`TfListener`] receives transforms from, and the buffer it stores them in.
+///
+/// ROS 1 has no QoS profiles: the subscriber side only chooses its queue size. Static transforms are published
+/// latched, so a listener created late still receives them.
+#[derive(Debug)]
+pub struct TfListenerOptions {
+    /// Topic of the dynamic transforms, `/tf` by default.
+    pub tf_topic: String,
+    /// Topic of the static transforms, `/tf_static` by default.
+    pub tf_static_topic: String,
+    /// Number of incoming dynamic messages queued before the oldest are dropped.
+    pub tf_queue_size: usize,
+    /// Number of incoming static messages queued before the oldest are dropped.
+    pub tf_static_queue_size: usize,
+    /// The buffer the received transforms are added to.
+    pub buffer: TfBuffer,
+    /// Prefix of the frame ids of received transforms, e.g. `robot1` in multi-robot setups. Replaces the prefix
+    /// `buffer` was built with, if any. See [`TfBuffer::set_tf_prefix`].
+    pub tf_prefix: Option<String>,
+}
+
+impl Default for TfListenerOptions {
+    fn default() -> Self {
+        Self {
+            tf_topic: "/tf".to_string(),
+            tf_static_topic: "/tf_static".to_string(),
+            tf_queue_size: 100,
+            tf_static_queue_size: 100,
+            buffer: TfBuffer::new(),
+            tf_prefix: None,
+        }
+    }
 }
 
 impl TfListener {
     /// Create a new TfListener
     pub fn new() -> Self {
-        Self::new_with_buffer(TfBuffer::new())
+        Self::new_with_options(TfListenerOptions::default())
     }
 
     pub fn new_with_buffer(tf_buffer: TfBuffer) -> Self {
-        let buff = RwLock::new(tf_buffer);
+        Self::new_with_options(TfListenerOptions {
+            buffer: tf_buffer,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a TfListener subscribing to the topics given in `options`, e.g. to follow remapped or namespaced tf.
+    pub fn new_with_options(options: TfListenerOptions) -> Self {
+        let mut buffer = options.buffer;
+        if let Some(tf_prefix) = &options.tf_prefix {
+            buffer.set_tf_prefix(Some(tf_prefix));
+        }
+        let buff = RwLock::new(buffer);
         let arc = Arc::new(buff);
+        let waiters = Arc::new(TransformWaiters::default());
+        let new_frame_callbacks = Arc::new(NewFrameCallbacks::default());
         let r1 = arc.clone();
-        let _dynamic_subscriber = rosrust::subscribe("tf", 100, move |v: TFMessage| {
-            r1.write().unwrap().handle_incoming_transforms(v, false);
-        })
+        let w1 = waiters.clone();
+        let c1 = new_frame_callbacks.clone();
+        let _dynamic_subscriber = rosrust::subscribe(
+            &options.tf_topic,
+            options.tf_queue_size,
+            move |v: TFMessage| receive(&r1, &w1, &c1, v, false),
+        )
         .unwrap();
 
         let r2 = arc.clone();
-        let _static_subscriber = rosrust::subscribe("tf_static", 100, move |v: TFMessage| {
-            r2.write().unwrap().handle_incoming_transforms(v, true);
-        })
+        let w2 = waiters.clone();
+        let c2 = new_frame_callbacks.clone();
+        let _static_subscriber = rosrust::subscribe(
+            &options.tf_static_topic,
+            options.tf_static_queue_size,
+            move |v: TFMessage| receive(&r2, &w2, &c2, v, true),
+        )
         .unwrap();
 
         TfListener {
             buffer: arc,
-            _static_subscriber,
-            _dynamic_subscriber,
+            waiters,
+            new_frame_callbacks,
+            _static_subscriber: Some(_static_subscriber),
+            _dynamic_subscriber: Some(_dynamic_subscriber),
+        }
+    }
+
+    /// Creates a TfListener storing into `tf_buffer` without subscribing to any topic, so that tests need no master.
+    #[cfg(test)]
+    fn new_unsubscribed(tf_buffer: TfBuffer) -> Self {
+        TfListener {
+            buffer: Arc::new(RwLock::new(tf_buffer)),
+            waiters: Arc::default(),
+            new_frame_callbacks: Arc::default(),
+            _static_subscriber: None,
+            _dynamic_subscriber: None,
         }
     }
 
     /// Looks up a transform within the tree at a given time.
+    ///
+    /// The time can be given as a `rosrust::Time`, as `i64` nanoseconds or as `f64` seconds.
     pub fn lookup_transform(
         &self,
         from: &str,
         to: &str,
-        time: rosrust::Time,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        read(&self.buffer)?.lookup_transform(from, to, time)
+    }
+
+    /// Looks up the most recent transform between `from` and `to`. See [`TfBuffer::lookup_latest_transform`].
+    pub fn lookup_latest_transform(
+        &self,
+        from: &str,
+        to: &str,
     ) -> Result<TransformStamped, TfError> {
-        self.buffer.read().unwrap().lookup_transform(from, to, time)
+        read(&self.buffer)?.lookup_latest_transform(from, to)
+    }
+
+    /// Like [`TfListener::lookup_transform`], but fails with [`TfError::CouldNotAcquireLock`] instead of waiting while
+    /// incoming transforms are being stored, e.g. in a real-time loop.
+    ///
+    /// A lock poisoned by a panicking thread also gives [`TfError::CouldNotAcquireLock`] rather than a panic.
+    pub fn try_lookup_transform(
+        &self,
+        from: &str,
+        to: &str,
+        time: impl Into<TimePoint>,
+    ) -> Result<TransformStamped, TfError> {
+        try_lookup_transform(&self.buffer, from, to, time.into())
+    }
+
+    /// Looks up several transforms at the same time under a single lock. See [`TfBuffer::lookup_transforms`].
+    pub fn lookup_transforms(
+        &self,
+        pairs: &[(&str, &str)],
+        time: &rosrust::Time,
+    ) -> Vec<Result<TransformStamped, TfError>> {
+        match read(&self.buffer) {
+            Ok(buffer) => buffer.lookup_transforms(pairs, time),
+            Err(err) => pairs.iter().map(|_| Err(err.clone())).collect(),
+        }
+    }
+
+    /// Whether a transform from `from` to `to` can be looked up at `time`. See [`TfBuffer::can_transform`].
+    pub fn can_transform(&self, from: &str, to: &str, time: &rosrust::Time) -> bool {
+        read(&self.buffer).is_ok_and(|buffer| buffer.can_transform(from, to, time))
+    }
+
+    /// Like [`TfListener::can_transform`], but returns the reason the transform cannot be looked up.
+    pub fn can_transform_with_error(
+        &self,
+        from: &str,
+        to: &str,
+        time: &rosrust::Time,
+    ) -> Result<(), TfError> {
+        read(&self.buffer)?.can_transform_with_error(from, to, time)
     }
 
     /// Looks up a transform within the tree at a given time.
@@ -78,15 +211,546 @@ impl TfListener {
         time2: rosrust::Time,
         fixed_frame: &str,
     ) -> Result<TransformStamped, TfError> {
+        read(&self.buffer)?.lookup_transform_with_time_travel(from, time1, to, time2, fixed_frame)
+    }
+
+    /// Waits until a transform can be looked up, then looks it up, failing once `timeout` has elapsed.
+    ///
+    /// With a zero `time`, this resolves as soon as the frames are connected and returns the latest transform. With a
+    /// specific stamp, it also waits for the samples covering that stamp to arrive. On timeout, the error of the last
+    /// attempt is returned: [`TfError::CouldNotFindTransform`] if the frames never got connected, or the usual
+    /// extrapolation errors otherwise.
+    ///
+    /// The task sleeps between attempts and is woken whenever transforms are received, so waiting does not hold the
+    /// buffer lock. It works with any executor.
+    pub async fn wait_for_transform(
+        &self,
+        from: &str,
+        to: &str,
+        time: rosrust::Time,
+        timeout: rosrust::Duration,
+    ) -> Result<TransformStamped, TfError> {
+        wait_for_transform(&self.buffer, &self.waiters, from, to, time, timeout).await
+    }
+
+    /// The most recent time at which every edge between `from` and `to` has data. See
+    /// [`TfBuffer::lookup_latest_common_time`].
+    pub fn lookup_latest_common_time(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<rosrust::Time, TfError> {
+        read(&self.buffer)?.lookup_latest_common_time(from, to)
+    }
+
+    /// Every known frame, sorted by name. See [`TfBuffer::frame_names`].
+    pub fn frame_names(&self) -> Vec<String> {
+        self.inspect().frame_names()
+    }
+
+    /// Whether `frame` is known. See [`TfBuffer::frame_exists`].
+    pub fn frame_exists(&self, frame: &str) -> bool {
+        self.inspect().frame_exists(frame)
+    }
+
+    /// Describes every frame with a published parent in YAML. See [`TfBuffer::all_frames_as_yaml`].
+    pub fn all_frames_as_yaml(&self) -> String {
+        self.inspect().all_frames_as_yaml()
+    }
+
+    /// Calls `callback` with the id of every frame that appears in the tree from now on.
+    ///
+    /// Each frame is reported once, when the first transform to or from it is received. The callback runs on the
+    /// subscriber thread after the buffer has been updated and unlocked, so it may look transforms up, but it must not
+    /// register further callbacks.
+    pub fn on_new_frame(&self, callback: impl Fn(&str) + Send + 'static) {
+        self.new_frame_callbacks.register(Box::new(callback));
+    }
+
+    /// Removes every transform received so far. See [`TfBuffer::clear`].
+    ///
+    /// This also recovers a buffer poisoned by a panic while it was being written to, see [`TfListener`].
+    pub fn clear(&self) {
+        self.buffer
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.buffer.clear_poison();
+    }
+
+    /// Returns a channel notified with the new topology revision whenever a new edge appears in the tree.
+    pub fn topology_changes(&self) -> mpsc::Receiver<u64> {
         self.buffer
-            .read()
-            .unwrap()
-            .lookup_transform_with_time_travel(from, time1, to, time2, fixed_frame)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .subscribe_topology_changes()
+    }
+
+    /// The buffer the listener stores received transforms in, shared with it, e.g. for a custom node to read from.
+    ///
+    /// The listener write-locks the buffer for every received message and read-locks it for every query, so guards
+    /// should be held briefly, and never while calling a method of the listener on the same thread: with a read guard,
+    /// [`TfListener::clear`] deadlocks, and lookups may too once a received message is waiting for the write lock.
+    pub fn buffer(&self) -> Arc<RwLock<TfBuffer>> {
+        self.buffer.clone()
+    }
+
+    /// Reads the buffer for introspection, even if it is poisoned.
+    fn inspect(&self) -> RwLockReadGuard<'_, TfBuffer> {
+        self.buffer.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Reads the buffer, failing with [`TfError::CouldNotAcquireLock`] if a panic while it was being written to poisoned
+/// it.
+fn read(buffer: &RwLock<TfBuffer>) -> Result<RwLockReadGuard<'_, TfBuffer>, TfError> {
+    buffer.read().map_err(|_| TfError::CouldNotAcquireLock)
+}
+
+/// The tasks waiting for transforms, woken whenever the buffer receives new data.
+///
+/// Tasks are also woken at their deadline by a single timer thread, which runs while any deadline is pending.
+#[derive(Debug, Default)]
+struct TransformWaiters {
+    wakers: Mutex<Vec<Waker>>,
+    timer: Mutex<Timer>,
+    /// Notified when a deadline is added, which may be earlier than the one the timer thread sleeps until.
+    timer_changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Timer {
+    deadlines: Vec<(Instant, Waker)>,
+    running: bool,
+}
+
+impl TransformWaiters {
+    fn register(&self, waker: &Waker) {
+        if let Ok(mut wakers) = self.wakers.lock() {
+            if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+                wakers.push(waker.clone());
+            }
+        }
+    }
+
+    fn wake_all(&self) {
+        let wakers = match self.wakers.lock() {
+            Ok(mut wakers) => mem::take(&mut *wakers),
+            Err(_) => return,
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Wakes `waker` once `deadline` has passed, starting the timer thread if it is not running.
+    fn wake_at(self: &Arc<Self>, deadline: Instant, waker: &Waker) {
+        let Ok(mut timer) = self.timer.lock() else {
+            return;
+        };
+        timer.deadlines.push((deadline, waker.clone()));
+        if timer.running {
+            self.timer_changed.notify_one();
+        } else {
+            timer.running = true;
+            let waiters = self.clone();
+            thread::spawn(move || waiters.run_timer());
+        }
+    }
+
+    /// Wakes the tasks whose deadline has passed, until none is pending.
+    fn run_timer(&self) {
+        loop {
+            let Ok(mut timer) = self.timer.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let (due, pending): (Vec<_>, Vec<_>) = mem::take(&mut timer.deadlines)
+                .into_iter()
+                .partition(|(deadline, _)| *deadline <= now);
+            timer.deadlines = pending;
+            if due.is_empty() {
+                let Some(next) = timer.deadlines.iter().map(|(deadline, _)| *deadline).min() else {
+                    timer.running = false;
+                    return;
+                };
+                let _ = self.timer_changed.wait_timeout(timer, next - now);
+                continue;
+            }
+            drop(timer);
+            due.into_iter().for_each(|(_, waker)| waker.wake());
+        }
+    }
+}
+
+type NewFrameCallback = Box<dyn Fn(&str) + Send>;
+
+/// The callbacks registered with [`TfListener::on_new_frame`].
+#[derive(Default)]
+struct NewFrameCallbacks(Mutex<Vec<NewFrameCallback>>);
+
+impl NewFrameCallbacks {
+    fn register(&self, callback: NewFrameCallback) {
+        if let Ok(mut callbacks) = self.0.lock() {
+            callbacks.push(callback);
+        }
+    }
+
+    fn notify(&self, frames: &[String]) {
+        if frames.is_empty() {
+            return;
+        }
+        if let Ok(callbacks) = self.0.lock() {
+            for frame in frames {
+                callbacks.iter().for_each(|callback| callback(frame));
+            }
+        }
+    }
+}
+
+/// Adds a received message to the buffer, then wakes the waiting tasks and reports the frames it introduced.
+fn receive(
+    buffer: &RwLock<TfBuffer>,
+    waiters: &TransformWaiters,
+    new_frame_callbacks: &NewFrameCallbacks,
+    message: TFMessage,
+    static_tf: bool,
+) {
+    let new_frames = {
+        // A poisoned buffer is not trusted with more data until it is cleared.
+        let Ok(mut buffer) = buffer.write() else {
+            return;
+        };
+        let mut new_frames: Vec<String> = Vec::new();
+        for transform in &message.transforms {
+            for frame in [&transform.header.frame_id, &transform.child_frame_id] {
+                let frame = buffer.prefixed_frame(frame);
+                if !buffer.frame_exists(&frame) && !new_frames.contains(&frame) {
+                    new_frames.push(frame);
+                }
+            }
+        }
+        buffer.handle_incoming_transforms(message, static_tf);
+        new_frames.retain(|frame| buffer.frame_exists(frame));
+        new_frames
+    };
+    waiters.wake_all();
+    new_frame_callbacks.notify(&new_frames);
+}
+
+fn try_lookup_transform(
+    buffer: &RwLock<TfBuffer>,
+    from: &str,
+    to: &str,
+    time: TimePoint,
+) -> Result<TransformStamped, TfError> {
+    match buffer.try_read() {
+        Ok(buffer) => buffer.lookup_transform(from, to, time),
+        Err(TryLockError::WouldBlock | TryLockError::Poisoned(_)) => {
+            Err(TfError::CouldNotAcquireLock)
+        }
     }
 }
 
+async fn wait_for_transform(
+    buffer: &RwLock<TfBuffer>,
+    waiters: &Arc<TransformWaiters>,
+    from: &str,
+    to: &str,
+    time: rosrust::Time,
+    timeout: rosrust::Duration,
+) -> Result<TransformStamped, TfError> {
+    let deadline = Instant::now() + ros_duration_to_std(&timeout);
+    let mut timer_started = false;
+    future::poll_fn(|cx| {
+        // Registering before looking up ensures that data received in between wakes the task.
+        waiters.register(cx.waker());
+        let result = read(buffer)?.lookup_transform(from, to, time);
+        if result.is_ok() || Instant::now() >= deadline {
+            return Poll::Ready(result);
+        }
+        if !timer_started {
+            timer_started = true;
+            waiters.wake_at(deadline, cx.waker());
+        }
+        Poll::Pending
+    })
+    .await
+}
+
 impl Default for TfListener {
     fn default() -> Self {
         TfListener::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::Arc,
+        task::{Context, Wake},
+        thread::Thread,
+    };
+
+    use super::*;
+    use crate::transforms::TransformStampedBuilder;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_wait_for_transform() {
+        let buffer = Arc::new(RwLock::new(TfBuffer::new()));
+        let waiters = Arc::new(TransformWaiters::default());
+        let publisher = {
+            let (buffer, waiters) = (buffer.clone(), waiters.clone());
+            thread::spawn(move || {
+                thread::sleep(std::time::Duration::from_millis(50));
+                let transform = TransformStampedBuilder::new()
+                    .parent("base_link")
+                    .child("camera")
+                    .translation(0.5, 0.0, 0.0)
+                    .build();
+                buffer.write().unwrap().handle_incoming_transforms(
+                    TFMessage {
+                        transforms: vec![transform],
+                    },
+                    true,
+                );
+                waiters.wake_all();
+            })
+        };
+        let result = block_on(wait_for_transform(
+            &buffer,
+            &waiters,
+            "base_link",
+            "camera",
+            rosrust::Time::new(),
+            rosrust::Duration::from_seconds(10),
+        ));
+        assert_eq!(result.unwrap().transform.translation.x, 0.5);
+        publisher.join().unwrap();
+    }
+
+    #[test]
+    fn test_try_lookup_transform() {
+        let buffer = Arc::new(RwLock::new(TfBuffer::new()));
+        buffer.write().unwrap().handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![TransformStampedBuilder::new()
+                    .parent("base_link")
+                    .child("camera")
+                    .build()],
+            },
+            true,
+        );
+        let lookup = |buffer: &RwLock<TfBuffer>| {
+            try_lookup_transform(buffer, "base_link", "camera", rosrust::Time::new().into())
+        };
+        assert!(lookup(&buffer).is_ok());
+
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let writer = {
+            let buffer = buffer.clone();
+            thread::spawn(move || {
+                let _guard = buffer.write().unwrap();
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            })
+        };
+        locked_rx.recv().unwrap();
+        assert!(matches!(lookup(&buffer), Err(TfError::CouldNotAcquireLock)));
+        release_tx.send(()).unwrap();
+        writer.join().unwrap();
+        assert!(lookup(&buffer).is_ok());
+
+        let poisoner = {
+            let buffer = buffer.clone();
+            thread::spawn(move || {
+                let _guard = buffer.write().unwrap();
+                panic!("poisoning the lock");
+            })
+        };
+        assert!(poisoner.join().is_err());
+        assert!(matches!(lookup(&buffer), Err(TfError::CouldNotAcquireLock)));
+    }
+
+    #[test]
+    fn test_poisoned_buffer() {
+        let buffer = Arc::new(RwLock::new(TfBuffer::new()));
+        let message = TFMessage {
+            transforms: vec![TransformStampedBuilder::new()
+                .parent("base_link")
+                .child("camera")
+                .build()],
+        };
+        let waiters = TransformWaiters::default();
+        let callbacks = NewFrameCallbacks::default();
+        receive(&buffer, &waiters, &callbacks, message.clone(), true);
+        let poisoner = {
+            let buffer = buffer.clone();
+            thread::spawn(move || {
+                let _guard = buffer.write().unwrap();
+                panic!("poisoning the lock");
+            })
+        };
+        assert!(poisoner.join().is_err());
+
+        assert!(matches!(
+            read(&buffer).and_then(|buffer| buffer.lookup_transform(
+                "base_link",
+                "camera",
+                rosrust::Time::new()
+            )),
+            Err(TfError::CouldNotAcquireLock)
+        ));
+        assert!(matches!(
+            block_on(wait_for_transform(
+                &buffer,
+                &Arc::new(waiters),
+                "base_link",
+                "camera",
+                rosrust::Time::new(),
+                rosrust::Duration::from_seconds(1),
+            )),
+            Err(TfError::CouldNotAcquireLock)
+        ));
+        // Receiving does not panic either.
+        receive(
+            &buffer,
+            &TransformWaiters::default(),
+            &callbacks,
+            message,
+            true,
+        );
+    }
+
+    #[test]
+    fn test_new_frame_callback() {
+        let buffer = RwLock::new(TfBuffer::new());
+        let waiters = TransformWaiters::default();
+        let callbacks = NewFrameCallbacks::default();
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let recorded = frames.clone();
+        callbacks.register(Box::new(move |frame| {
+            recorded.lock().unwrap().push(frame.to_string())
+        }));
+        let message = |parent: &str, child: &str| TFMessage {
+            transforms: vec![TransformStampedBuilder::new()
+                .parent(parent)
+                .child(child)
+                .build()],
+        };
+        receive(
+            &buffer,
+            &waiters,
+            &callbacks,
+            message("base_link", "camera"),
+            true,
+        );
+        receive(
+            &buffer,
+            &waiters,
+            &callbacks,
+            message("base_link", "camera"),
+            true,
+        );
+        receive(
+            &buffer,
+            &waiters,
+            &callbacks,
+            message("base_link", "lidar"),
+            true,
+        );
+        // Rejected transforms do not introduce frames.
+        receive(&buffer, &waiters, &callbacks, message("", "radar"), true);
+        assert_eq!(
+            *frames.lock().unwrap(),
+            vec!["base_link", "camera", "lidar"]
+        );
+    }
+
+    #[test]
+    fn test_wait_for_transform_timeout() {
+        let buffer = RwLock::new(TfBuffer::new());
+        let waiters = Arc::new(TransformWaiters::default());
+        let start = Instant::now();
+        let result = block_on(wait_for_transform(
+            &buffer,
+            &waiters,
+            "base_link",
+            "camera",
+            rosrust::Time::new(),
+            rosrust::Duration::from_nanos(50_000_000),
+        ));
+        assert!(matches!(result, Err(TfError::CouldNotFindTransform(..))));
+        assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+
+        // Concurrent waits share the timer thread, which stops once no deadline is pending.
+        let buffer = Arc::new(buffer);
+        let tasks: Vec<_> = (1..=4)
+            .map(|index| {
+                let (buffer, waiters) = (buffer.clone(), waiters.clone());
+                thread::spawn(move || {
+                    block_on(wait_for_transform(
+                        &buffer,
+                        &waiters,
+                        "base_link",
+                        "camera",
+                        rosrust::Time::new(),
+                        rosrust::Duration::from_nanos(index * 20_000_000),
+                    ))
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.join().unwrap().is_err());
+        }
+        let stopped = Instant::now() + std::time::Duration::from_secs(1);
+        while waiters.timer.lock().unwrap().running {
+            assert!(Instant::now() < stopped);
+            thread::yield_now();
+        }
+        assert!(waiters.timer.lock().unwrap().deadlines.is_empty());
+    }
+
+    #[test]
+    fn test_shared_buffer() {
+        let listener = TfListener::new_unsubscribed(TfBuffer::new());
+        let buffer = listener.buffer();
+        assert!(Arc::ptr_eq(&buffer, &listener.buffer()));
+        buffer.write().unwrap().handle_incoming_transforms(
+            TFMessage {
+                transforms: vec![TransformStampedBuilder::new()
+                    .parent("base_link")
+                    .child("camera")
+                    .translation(1.0, 0.0, 0.0)
+                    .build()],
+            },
+            true,
+        );
+        let transform = listener
+            .lookup_transform("base_link", "camera", rosrust::Time::new())
+            .unwrap();
+        assert_eq!(transform.transform.translation.x, 1.0);
+        assert!(listener.frame_exists("camera"));
+    }
+}
@@ -1,19 +1,115 @@
 pub use nalgebra;
-use nalgebra::geometry::{Isometry3, Translation3, UnitQuaternion};
+use nalgebra::{
+    geometry::{Isometry3, Translation3, UnitQuaternion},
+    RealField,
+};
 
 rosrust::rosmsg_include!(
     geometry_msgs / Transform,
+    geometry_msgs / Point,
+    geometry_msgs / PointStamped,
     geometry_msgs / Pose,
+    geometry_msgs / PoseStamped,
+    geometry_msgs / PoseWithCovariance,
+    geometry_msgs / PoseWithCovarianceStamped,
     geometry_msgs / Vector3,
+    geometry_msgs / Vector3Stamped,
     geometry_msgs / Quaternion,
     geometry_msgs / TransformStamped,
+    geometry_msgs / Twist,
     std_msgs / Header,
     tf2_msgs / TFMessage
 );
 
-use geometry_msgs::{Pose, Quaternion, Transform, TransformStamped, Vector3};
+use geometry_msgs::{
+    Point, Pose, PoseWithCovariance, Quaternion, Transform, TransformStamped, Vector3,
+};
 use std_msgs::Header;
 
+use crate::tf_error::TfError;
+
+/// Builds a [`TransformStamped`] without filling the nested message structs by hand.
+///
+/// The rotation defaults to the identity rather than the invalid zero quaternion of `TransformStamped::default()`.
+///
+/// ```
+/// use tf_rosrust::TransformStampedBuilder;
+///
+/// let tf = TransformStampedBuilder::new()
+///     .parent("map")
+///     .child("base_link")
+///     .stamp(rosrust::Time::from_seconds(1))
+///     .translation(1.0, 2.0, 0.0)
+///     .rotation_rpy(0.0, 0.0, std::f64::consts::FRAC_PI_2)
+///     .build();
+/// assert_eq!(tf.header.frame_id, "map");
+/// assert!((tf.transform.rotation.z - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TransformStampedBuilder {
+    transform: TransformStamped,
+}
+
+impl TransformStampedBuilder {
+    pub fn new() -> Self {
+        let mut transform = TransformStamped::default();
+        transform.transform.rotation.w = 1.0;
+        Self { transform }
+    }
+
+    /// The frame the transform is expressed in, i.e. `header.frame_id`.
+    pub fn parent(mut self, frame_id: &str) -> Self {
+        self.transform.header.frame_id = frame_id.to_string();
+        self
+    }
+
+    /// The frame being located, i.e. `child_frame_id`.
+    pub fn child(mut self, frame_id: &str) -> Self {
+        self.transform.child_frame_id = frame_id.to_string();
+        self
+    }
+
+    pub fn stamp(mut self, stamp: rosrust::Time) -> Self {
+        self.transform.header.stamp = stamp;
+        self
+    }
+
+    pub fn translation(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.transform.transform.translation = Vector3 { x, y, z };
+        self
+    }
+
+    /// Sets the rotation from a quaternion, which is normalized. A zero quaternion leaves the rotation unchanged.
+    pub fn rotation_quat(mut self, x: f64, y: f64, z: f64, w: f64) -> Self {
+        let quaternion = nalgebra::geometry::Quaternion::new(w, x, y, z);
+        if let Some(rotation) = UnitQuaternion::try_new(quaternion, f64::EPSILON) {
+            self.transform.transform.rotation = Quaternion {
+                x: rotation.i,
+                y: rotation.j,
+                z: rotation.k,
+                w: rotation.w,
+            };
+        }
+        self
+    }
+
+    /// Sets the rotation from roll, pitch and yaw in radians, applied about the fixed X, Y and Z axes in that order.
+    pub fn rotation_rpy(self, roll: f64, pitch: f64, yaw: f64) -> Self {
+        let rotation = quaternion_from_euler(roll, pitch, yaw);
+        self.rotation_quat(rotation.x, rotation.y, rotation.z, rotation.w)
+    }
+
+    pub fn build(self) -> TransformStamped {
+        self.transform
+    }
+}
+
+impl Default for TransformStampedBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn isometry_from_pose(pose: &Pose) -> Isometry3<f64> {
     let trans = Translation3::new(pose.position.x, pose.position.y, pose.position.z);
     let rot = UnitQuaternion::new_normalize(nalgebra::geometry::Quaternion::new(
@@ -54,6 +150,146 @@ pub fn isometry_to_transform(iso: Isometry3<f64>) -> Transform {
     }
 }
 
+impl From<&Transform> for Isometry3<f64> {
+    fn from(tf: &Transform) -> Self {
+        isometry_from_transform(tf)
+    }
+}
+
+impl From<Transform> for Isometry3<f64> {
+    fn from(tf: Transform) -> Self {
+        isometry_from_transform(&tf)
+    }
+}
+
+impl From<Isometry3<f64>> for Transform {
+    fn from(iso: Isometry3<f64>) -> Self {
+        isometry_to_transform(iso)
+    }
+}
+
+/// Expresses `point` in the parent frame of `tf`, given in its child frame: rotates it, then translates it.
+pub fn transform_point(tf: &Transform, point: &Point) -> Point {
+    let transformed =
+        isometry_from_transform(tf) * nalgebra::Point3::new(point.x, point.y, point.z);
+    Point {
+        x: transformed.x,
+        y: transformed.y,
+        z: transformed.z,
+    }
+}
+
+/// Expresses `pose` in the parent frame of `tf`, given in its child frame.
+pub fn transform_pose(tf: &Transform, pose: &Pose) -> Pose {
+    let pose_tf = Transform {
+        translation: Vector3 {
+            x: pose.position.x,
+            y: pose.position.y,
+            z: pose.position.z,
+        },
+        rotation: pose.orientation.clone(),
+    };
+    let composed = chain_transforms(&[tf.clone(), pose_tf]);
+    Pose {
+        position: Point {
+            x: composed.translation.x,
+            y: composed.translation.y,
+            z: composed.translation.z,
+        },
+        orientation: composed.rotation,
+    }
+}
+
+/// Expresses `pose` in the parent frame of `tf`, given in its child frame, along with its covariance.
+///
+/// The covariance is over `(x, y, z, rotation about X, rotation about Y, rotation about Z)`, so both its position and
+/// orientation blocks are rotated: `R * Cov * R^T` with `R` applying the rotation of `tf` to each block. The
+/// translation of `tf` does not change the uncertainty.
+pub fn transform_pose_with_covariance(
+    tf: &Transform,
+    pose: &PoseWithCovariance,
+) -> PoseWithCovariance {
+    let rotation = isometry_from_transform(tf)
+        .rotation
+        .to_rotation_matrix()
+        .into_inner();
+    let mut rotation6 = nalgebra::Matrix6::zeros();
+    rotation6.fixed_slice_mut::<3, 3>(0, 0).copy_from(&rotation);
+    rotation6.fixed_slice_mut::<3, 3>(3, 3).copy_from(&rotation);
+    let covariance = nalgebra::Matrix6::from_row_slice(&pose.covariance);
+    let rotated = rotation6 * covariance * rotation6.transpose();
+    let mut row_major = [0.0; 36];
+    row_major.copy_from_slice(rotated.transpose().as_slice());
+    PoseWithCovariance {
+        pose: transform_pose(tf, &pose.pose),
+        covariance: row_major,
+    }
+}
+
+/// Converts roll, pitch and yaw in radians into a quaternion, like `tf2::Quaternion::setRPY`.
+///
+/// The rotations are applied about the fixed X, Y and Z axes in that order, i.e. yaw, pitch and roll about the moving
+/// Z, Y and X axes.
+pub fn quaternion_from_euler(roll: f64, pitch: f64, yaw: f64) -> Quaternion {
+    let rotation = UnitQuaternion::from_euler_angles(roll, pitch, yaw);
+    Quaternion {
+        x: rotation.i,
+        y: rotation.j,
+        z: rotation.k,
+        w: rotation.w,
+    }
+}
+
+/// Converts a quaternion into roll, pitch and yaw in radians, the inverse of [`quaternion_from_euler`].
+///
+/// The quaternion is normalized first. Roll and yaw lie in `[-pi, pi]` and pitch in `[-pi/2, pi/2]`. At a pitch of
+/// +-90 degrees, only the sum or difference of roll and yaw is defined, so roll is set to zero and the whole rotation
+/// about the vertical axis is put into yaw.
+pub fn euler_from_quaternion(q: &Quaternion) -> (f64, f64, f64) {
+    let rotation =
+        UnitQuaternion::new_normalize(nalgebra::geometry::Quaternion::new(q.w, q.x, q.y, q.z))
+            .to_rotation_matrix();
+    let r = rotation.matrix();
+    let cos_pitch = r[(0, 0)].hypot(r[(1, 0)]);
+    let pitch = (-r[(2, 0)]).atan2(cos_pitch);
+    if cos_pitch < 1e-9 {
+        (0.0, pitch, (-r[(0, 1)]).atan2(r[(1, 1)]))
+    } else {
+        (
+            r[(2, 1)].atan2(r[(2, 2)]),
+            pitch,
+            r[(1, 0)].atan2(r[(0, 0)]),
+        )
+    }
+}
+
+/// Converts a transform into a row-major 4x4 homogeneous matrix: `matrix[row][column]`.
+pub fn transform_to_matrix(tf: &Transform) -> [[f64; 4]; 4] {
+    let homogeneous = isometry_from_transform(tf).to_homogeneous();
+    let mut matrix = [[0.0; 4]; 4];
+    for (row, values) in matrix.iter_mut().enumerate() {
+        for (column, value) in values.iter_mut().enumerate() {
+            *value = homogeneous[(row, column)];
+        }
+    }
+    matrix
+}
+
+/// Converts a transform into a flat column-major 4x4 homogeneous matrix, as expected by OpenGL and wgpu uniforms.
+///
+/// Element `column * 4 + row` holds `matrix[row][column]`, so the translation is stored in elements 12, 13 and 14.
+pub fn to_mat4_col_major(tf: &Transform) -> [f64; 16] {
+    let mut matrix = [0.0; 16];
+    matrix.copy_from_slice(isometry_from_transform(tf).to_homogeneous().as_slice());
+    matrix
+}
+
+/// Single precision variant of [`to_mat4_col_major`], with the same layout.
+pub fn to_mat4_col_major_f32(tf: &Transform) -> [f32; 16] {
+    to_mat4_col_major(tf).map(|value| value as f32)
+}
+
+/// The transform from the child frame of `trans` back to its parent frame, with the frame ids swapped.
 pub fn get_inverse(trans: &TransformStamped) -> TransformStamped {
     TransformStamped {
         header: Header {
@@ -69,6 +305,8 @@ pub fn get_inverse(trans: &TransformStamped) -> TransformStamped {
 }
 
 ///Chain multiple transforms together. Takes in a vector of transforms. The vector should be in order of desired transformations
+///
+/// An empty slice yields the identity transform.
 pub fn chain_transforms(transforms: &[Transform]) -> Transform {
     let mut final_transform = Isometry3::identity();
     for t in transforms {
@@ -78,61 +316,205 @@ pub fn chain_transforms(transforms: &[Transform]) -> Transform {
     isometry_to_transform(final_transform)
 }
 
-pub fn interpolate(t1: Transform, t2: Transform, weight: f64) -> Transform {
-    let r1 = nalgebra::geometry::Quaternion::new(
-        t1.rotation.w,
-        t1.rotation.x,
-        t1.rotation.y,
-        t1.rotation.z,
-    );
-    let r2 = nalgebra::geometry::Quaternion::new(
-        t2.rotation.w,
-        t2.rotation.x,
-        t2.rotation.y,
-        t2.rotation.z,
-    );
-    let r1 = UnitQuaternion::from_quaternion(r1);
-    let r2 = UnitQuaternion::from_quaternion(r2);
-    let res = r1.try_slerp(&r2, weight, 1e-9);
-    match res {
-        Some(qt) => Transform {
-            translation: Vector3 {
-                x: t1.translation.x * weight + t2.translation.x * (1.0 - weight),
-                y: t1.translation.y * weight + t2.translation.y * (1.0 - weight),
-                z: t1.translation.z * weight + t2.translation.z * (1.0 - weight),
-            },
-            rotation: Quaternion {
-                x: qt.coords[0],
-                y: qt.coords[1],
-                z: qt.coords[2],
-                w: qt.coords[3],
-            },
-        },
-        None => {
-            if weight > 0.5 {
-                Transform {
-                    translation: Vector3 {
-                        x: t1.translation.x * weight + t2.translation.x * (1.0 - weight),
-                        y: t1.translation.y * weight + t2.translation.y * (1.0 - weight),
-                        z: t1.translation.z * weight + t2.translation.z * (1.0 - weight),
-                    },
-                    rotation: t1.rotation,
-                }
-            } else {
-                Transform {
-                    translation: Vector3 {
-                        x: t1.translation.x * weight + t2.translation.x * (1.0 - weight),
-                        y: t1.translation.y * weight + t2.translation.y * (1.0 - weight),
-                        z: t1.translation.z * weight + t2.translation.z * (1.0 - weight),
-                    },
-                    rotation: t2.rotation,
-                }
-            }
+/// Axis convention of a coordinate frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Convention {
+    /// REP-103 body frame: X forward, Y left, Z up.
+    Body,
+    /// REP-103 optical frame (suffix `_optical`): Z forward, X right, Y down.
+    Optical,
+}
+
+impl Convention {
+    /// Rotation of a frame in this convention relative to a co-located body frame.
+    fn rotation_from_body(self) -> UnitQuaternion<f64> {
+        match self {
+            Convention::Body => UnitQuaternion::identity(),
+            // roll = -pi/2, pitch = 0, yaw = -pi/2, as used between `camera_link` and `camera_optical_frame`.
+            Convention::Optical => UnitQuaternion::new_normalize(
+                nalgebra::geometry::Quaternion::new(0.5, -0.5, 0.5, -0.5),
+            ),
         }
     }
 }
 
+/// Re-expresses a transform whose child frame uses `from_conv` so that its child frame uses `to_conv` instead.
+///
+/// The origin of the child frame is unchanged, only its axes are rotated following REP-103.
+pub fn convert_convention(tf: &Transform, from_conv: Convention, to_conv: Convention) -> Transform {
+    let correction = from_conv.rotation_from_body().inverse() * to_conv.rotation_from_body();
+    isometry_to_transform(
+        isometry_from_transform(tf) * Isometry3::from_parts(Translation3::identity(), correction),
+    )
+}
+
+/// Re-expresses a transform whose parent frame uses the ENU convention (X east, Y north, Z up, as REP-103 world
+/// frames do) in the NED convention (X north, Y east, Z down).
+///
+/// The parent axes are remapped with the fixed rotation `(x, y, z) -> (y, x, -z)`, a half turn about the
+/// north-east diagonal, i.e. the quaternion `(x, y, z, w) = (1/sqrt(2), 1/sqrt(2), 0, 0)`. The child frame's own axes
+/// are unchanged. Applying it twice gives back the original transform.
+pub fn enu_to_ned(tf: &Transform) -> Transform {
+    let enu_to_ned = UnitQuaternion::new_normalize(nalgebra::geometry::Quaternion::new(
+        0.0,
+        std::f64::consts::FRAC_1_SQRT_2,
+        std::f64::consts::FRAC_1_SQRT_2,
+        0.0,
+    ));
+    isometry_to_transform(
+        Isometry3::from_parts(Translation3::identity(), enu_to_ned) * isometry_from_transform(tf),
+    )
+}
+
+/// Finds the rigid transform that best maps `source_points` onto the corresponding `target_points`.
+///
+/// Implements the Kabsch algorithm, minimizing the sum of squared distances, e.g. to calibrate extrinsics from frame
+/// origins that should coincide. Needs at least three corresponding points, not all on one line, to be unambiguous.
+pub fn best_fit_transform(
+    source_points: &[Point],
+    target_points: &[Point],
+) -> Result<Transform, TfError> {
+    if source_points.len() != target_points.len() {
+        return Err(TfError::InvalidArgument(format!(
+            "{} source points but {} target points",
+            source_points.len(),
+            target_points.len()
+        )));
+    }
+    if source_points.len() < 3 {
+        return Err(TfError::InvalidArgument(format!(
+            "{} point pairs given, at least 3 are needed",
+            source_points.len()
+        )));
+    }
+    let to_vector = |point: &Point| nalgebra::Vector3::new(point.x, point.y, point.z);
+    let centroid = |points: &[Point]| {
+        points.iter().map(to_vector).sum::<nalgebra::Vector3<f64>>() / points.len() as f64
+    };
+    let source_centroid = centroid(source_points);
+    let target_centroid = centroid(target_points);
+    let covariance = source_points
+        .iter()
+        .zip(target_points)
+        .map(|(source, target)| {
+            (to_vector(source) - source_centroid)
+                * (to_vector(target) - target_centroid).transpose()
+        })
+        .sum::<nalgebra::Matrix3<f64>>();
+    let svd = covariance.svd(true, true);
+    let (Some(u), Some(v_t)) = (svd.u, svd.v_t) else {
+        return Err(TfError::InvalidArgument(
+            "could not decompose the point covariance".to_string(),
+        ));
+    };
+    // The singular values are not sorted, so rank the axes by variance before using them.
+    let mut axes = [0, 1, 2];
+    axes.sort_by(|&a, &b| svd.singular_values[b].total_cmp(&svd.singular_values[a]));
+    if svd.singular_values[axes[1]] <= f64::EPSILON * svd.singular_values[axes[0]].max(1.0) {
+        return Err(TfError::InvalidArgument(
+            "the points are collinear, the rotation is ambiguous".to_string(),
+        ));
+    }
+    // Flip the axis of least variance if needed, so that the result is a rotation rather than a reflection.
+    let mut correction = nalgebra::Vector3::new(1.0, 1.0, 1.0);
+    correction[axes[2]] = (v_t.transpose() * u.transpose()).determinant().signum();
+    let rotation = v_t.transpose() * nalgebra::Matrix3::from_diagonal(&correction) * u.transpose();
+    let rotation = UnitQuaternion::from_matrix(&rotation);
+    let translation = target_centroid - rotation * source_centroid;
+    Ok(isometry_to_transform(Isometry3::from_parts(
+        Translation3::from(translation),
+        rotation,
+    )))
+}
+
+/// Interpolates between `t1` and `t2`, linearly in translation and spherically in rotation.
+///
+/// `weight` is the weight of `t1`, not the fraction of the way to `t2`: `1.0` yields `t1` and `0.0` yields `t2`. To
+/// interpolate at `time` between samples stamped `time1` and `time2`, pass `1.0 - (time - time1) / (time2 - time1)`.
+///
+/// ```
+/// use tf_rosrust::{interpolate, transforms::geometry_msgs::Transform};
+///
+/// let mut t1 = Transform::default();
+/// t1.rotation.w = 1.0;
+/// let mut t2 = t1.clone();
+/// t2.translation.x = 4.0;
+///
+/// assert_eq!(interpolate(t1.clone(), t2.clone(), 0.75).translation.x, 1.0);
+/// assert_eq!(interpolate(t1, t2, 0.0).translation.x, 4.0);
+/// ```
+pub fn interpolate(t1: Transform, t2: Transform, weight: f64) -> Transform {
+    let iso1 = isometry_from_transform(&t1);
+    let iso2 = isometry_from_transform(&t2);
+    let translation = iso1.translation.vector * weight + iso2.translation.vector * (1.0 - weight);
+    let rotation = slerp(&iso1.rotation, &iso2.rotation, 1.0 - weight, 1e-9);
+    isometry_to_transform(Isometry3::from_parts(
+        Translation3::from(translation),
+        rotation,
+    ))
+}
+
+/// Spherical linear interpolation from `q1` (`t = 0`) to `q2` (`t = 1`) along the shortest arc.
+///
+/// `q` and `-q` are the same rotation, so `q2` is negated if that brings it closer to `q1`; otherwise the result would
+/// take the long way around. When both are within `epsilon` of each other, the sine the weights are divided by
+/// vanishes, so they are interpolated linearly and normalized instead.
+fn slerp<T: RealField + Copy>(
+    q1: &UnitQuaternion<T>,
+    q2: &UnitQuaternion<T>,
+    t: T,
+    epsilon: T,
+) -> UnitQuaternion<T> {
+    let mut dot = q1.coords.dot(&q2.coords);
+    let mut q2 = q2.coords;
+    if dot < T::zero() {
+        dot = -dot;
+        q2 = -q2;
+    }
+    let coords = if T::one() - dot < epsilon {
+        q1.coords * (T::one() - t) + q2 * t
+    } else {
+        let angle = dot.acos();
+        let sin = angle.sin();
+        q1.coords * (((T::one() - t) * angle).sin() / sin) + q2 * ((t * angle).sin() / sin)
+    };
+    UnitQuaternion::new_normalize(nalgebra::geometry::Quaternion::from(coords))
+}
+
+/// Continues the motion from `t1` to `t2` past `t2`, by `ratio` times that motion.
+///
+/// Translation is extrapolated linearly and rotation at the constant angular velocity taking `t1` to `t2`.
+pub fn extrapolate(t1: &Transform, t2: &Transform, ratio: f64) -> Transform {
+    let iso1 = isometry_from_transform(t1);
+    let iso2 = isometry_from_transform(t2);
+    let translation =
+        iso2.translation.vector + (iso2.translation.vector - iso1.translation.vector) * ratio;
+    let step = iso1.rotation.rotation_to(&iso2.rotation);
+    let rotation = step.powf(ratio) * iso2.rotation;
+    isometry_to_transform(Isometry3::from_parts(
+        Translation3::from(translation),
+        rotation,
+    ))
+}
+
+/// Single-precision counterpart of [`interpolate`], where `weight` is likewise the weight of `t1`.
+///
+/// `f32` only carries about 7 significant digits, so translations far from the origin lose sub-millimetre precision.
+pub fn interpolate_f32(t1: &Isometry3<f32>, t2: &Isometry3<f32>, weight: f32) -> Isometry3<f32> {
+    let translation = t1.translation.vector * weight + t2.translation.vector * (1.0 - weight);
+    let rotation = slerp(&t1.rotation, &t2.rotation, 1.0 - weight, 1e-6);
+    Isometry3::from_parts(Translation3::from(translation), rotation)
+}
+
+/// Single-precision counterpart of [`chain_transforms`]. Rounding errors accumulate along the chain.
+pub fn chain_transforms_f32(transforms: &[Isometry3<f32>]) -> Isometry3<f32> {
+    transforms
+        .iter()
+        .fold(Isometry3::identity(), |chained, tf| chained * tf)
+}
+
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod test {
     use super::*;
 
@@ -212,9 +594,442 @@ mod test {
         };
         assert_eq!(interpolate(tf1, tf2, 0.5), expected);
     }
+
+    #[test]
+    fn test_slerp_large_angle() {
+        let yaw = |angle: f64| {
+            TransformStampedBuilder::new()
+                .rotation_rpy(0.0, 0.0, angle.to_radians())
+                .build()
+                .transform
+        };
+        let expected = UnitQuaternion::from_euler_angles(0.0, 0.0, 85f64.to_radians());
+        let mut negated = yaw(170.0);
+        negated.rotation.x = -negated.rotation.x;
+        negated.rotation.y = -negated.rotation.y;
+        negated.rotation.z = -negated.rotation.z;
+        negated.rotation.w = -negated.rotation.w;
+        for t2 in [yaw(170.0), negated] {
+            let result = interpolate(yaw(0.0), t2, 0.5);
+            assert_eq!(result.translation, Vector3::default());
+            let rotation = isometry_from_transform(&result).rotation;
+            assert!(rotation.angle_to(&expected) < 1e-9);
+        }
+
+        // Nearly identical rotations fall back to a normalized linear interpolation.
+        let result = interpolate(yaw(0.0), yaw(1e-12), 0.5);
+        assert!((result.rotation.w - 1.0).abs() < 1e-12);
+        assert!(result.rotation.z.is_finite());
+    }
+
+    #[test]
+    fn test_euler_round_trip() {
+        let angles: [f64; 7] = [-3.0, -1.4, -0.5, 0.0, 0.2, 1.1, 3.0];
+        for roll in angles {
+            for pitch in angles.iter().filter(|pitch| pitch.abs() < 1.5) {
+                for yaw in angles {
+                    let q = quaternion_from_euler(roll, *pitch, yaw);
+                    let (r, p, y) = euler_from_quaternion(&q);
+                    assert!((r - roll).abs() < 1e-9, "{roll} {pitch} {yaw}");
+                    assert!((p - pitch).abs() < 1e-9, "{roll} {pitch} {yaw}");
+                    assert!((y - yaw).abs() < 1e-9, "{roll} {pitch} {yaw}");
+                }
+            }
+        }
+
+        // At gimbal lock, the angles differ but describe the same rotation.
+        for pitch in [std::f64::consts::FRAC_PI_2, -std::f64::consts::FRAC_PI_2] {
+            let q = quaternion_from_euler(0.3, pitch, 0.5);
+            let (r, p, y) = euler_from_quaternion(&q);
+            assert_eq!(r, 0.0);
+            assert!((p - pitch).abs() < 1e-9);
+            let expected = UnitQuaternion::from_euler_angles(0.3, pitch, 0.5);
+            let actual = UnitQuaternion::from_euler_angles(r, p, y);
+            assert!(actual.angle_to(&expected) < 1e-9);
+        }
+    }
+
+    /// Tests that the weight applies to the first transform for the rotation too.
+    #[test]
+    fn test_interpolation_weight() {
+        let sample = |x: f64, yaw: f64| {
+            TransformStampedBuilder::new()
+                .translation(x, 0.0, 0.0)
+                .rotation_rpy(0.0, 0.0, yaw)
+                .build()
+                .transform
+        };
+        let result = interpolate(sample(0.0, 0.0), sample(4.0, 0.8), 0.75);
+        let expected = sample(1.0, 0.2);
+        assert!((result.translation.x - expected.translation.x).abs() < 1e-9);
+        assert!((result.rotation.z - expected.rotation.z).abs() < 1e-9);
+        assert!((result.rotation.w - expected.rotation.w).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_f32_matches_f64() {
+        let tf1 = Transform {
+            translation: Vector3 {
+                x: 1.0,
+                y: -2.0,
+                z: 0.5,
+            },
+            rotation: Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: 0.38268343236,
+                w: 0.92387953251,
+            },
+        };
+        let tf2 = Transform {
+            translation: Vector3 {
+                x: 3.0,
+                y: 0.0,
+                z: -1.0,
+            },
+            rotation: Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0,
+            },
+        };
+        let iso1 = isometry_from_transform(&tf1);
+        let iso2 = isometry_from_transform(&tf2);
+
+        let chained = chain_transforms_f32(&[iso1.cast(), iso2.cast()]);
+        let expected = iso1 * iso2;
+        assert!((chained.cast::<f64>().to_homogeneous() - expected.to_homogeneous()).amax() < 1e-5);
+
+        let interpolated = interpolate_f32(&iso1.cast(), &iso2.cast(), 0.25);
+        let expected = Isometry3::from_parts(
+            Translation3::from(iso1.translation.vector * 0.25 + iso2.translation.vector * 0.75),
+            iso1.rotation.slerp(&iso2.rotation, 0.75),
+        );
+        assert!(
+            (interpolated.cast::<f64>().to_homogeneous() - expected.to_homogeneous()).amax() < 1e-5
+        );
+    }
+
+    #[test]
+    fn test_inverse_round_trip() {
+        let tf = TransformStamped {
+            header: Header {
+                frame_id: "a".to_string(),
+                ..Default::default()
+            },
+            child_frame_id: "b".to_string(),
+            transform: Transform {
+                translation: Vector3 {
+                    x: 1f64,
+                    y: 2f64,
+                    z: 3f64,
+                },
+                rotation: Quaternion {
+                    x: 0f64,
+                    y: 0f64,
+                    z: std::f64::consts::FRAC_1_SQRT_2,
+                    w: std::f64::consts::FRAC_1_SQRT_2,
+                },
+            },
+        };
+        let inverse = get_inverse(&tf);
+        assert_eq!(inverse.header.frame_id, "b");
+        assert_eq!(inverse.child_frame_id, "a");
+        for res in [
+            chain_transforms(&[tf.transform.clone(), inverse.transform.clone()]),
+            chain_transforms(&[inverse.transform, tf.transform]),
+        ] {
+            assert!(res.translation.x.abs() < 1e-9);
+            assert!(res.translation.y.abs() < 1e-9);
+            assert!(res.translation.z.abs() < 1e-9);
+            assert!(res.rotation.z.abs() < 1e-9);
+            assert!((res.rotation.w.abs() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_convert_convention() {
+        let identity = Transform {
+            translation: Vector3 {
+                x: 1f64,
+                y: 2f64,
+                z: 3f64,
+            },
+            rotation: Quaternion {
+                x: 0f64,
+                y: 0f64,
+                z: 0f64,
+                w: 1f64,
+            },
+        };
+        let body = convert_convention(&identity, Convention::Optical, Convention::Body);
+        assert_eq!(body.translation, identity.translation);
+        // The forward axis of the body frame is the Z axis of the optical frame,
+        // its left axis is -X and its up axis is -Y.
+        let rotation = isometry_from_transform(&body).rotation;
+        let forward = rotation * nalgebra::Vector3::x();
+        let left = rotation * nalgebra::Vector3::y();
+        let up = rotation * nalgebra::Vector3::z();
+        assert!((forward - nalgebra::Vector3::z()).norm() < 1e-9);
+        assert!((left + nalgebra::Vector3::x()).norm() < 1e-9);
+        assert!((up + nalgebra::Vector3::y()).norm() < 1e-9);
+
+        let optical = convert_convention(&body, Convention::Body, Convention::Optical);
+        assert!(optical.rotation.x.abs() < 1e-9);
+        assert!(optical.rotation.y.abs() < 1e-9);
+        assert!(optical.rotation.z.abs() < 1e-9);
+        assert!((optical.rotation.w.abs() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_enu_to_ned() {
+        let tf = Transform {
+            translation: Vector3 {
+                x: 1f64,
+                y: 2f64,
+                z: 3f64,
+            },
+            rotation: Quaternion {
+                x: 0f64,
+                y: 0f64,
+                z: 0f64,
+                w: 1f64,
+            },
+        };
+        let ned = enu_to_ned(&tf);
+        assert!((ned.translation.x - 2.0).abs() < 1e-9);
+        assert!((ned.translation.y - 1.0).abs() < 1e-9);
+        assert!((ned.translation.z + 3.0).abs() < 1e-9);
+        // The child's axes, expressed in NED: its X (east) is NED's Y, its Y (north) is NED's X and its Z (up) is -Z.
+        let rotation = isometry_from_transform(&ned).rotation;
+        assert!((rotation * nalgebra::Vector3::x() - nalgebra::Vector3::y()).norm() < 1e-9);
+        assert!((rotation * nalgebra::Vector3::y() - nalgebra::Vector3::x()).norm() < 1e-9);
+        assert!((rotation * nalgebra::Vector3::z() + nalgebra::Vector3::z()).norm() < 1e-9);
+
+        let enu = enu_to_ned(&ned);
+        assert!((enu.translation.x - 1.0).abs() < 1e-9);
+        assert!((enu.translation.y - 2.0).abs() < 1e-9);
+        assert!((enu.translation.z - 3.0).abs() < 1e-9);
+        assert!((enu.rotation.w.abs() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_best_fit_transform() {
+        let expected = Isometry3::from_parts(
+            Translation3::new(0.3, -1.2, 2.0),
+            UnitQuaternion::from_euler_angles(0.4, -0.2, 2.5),
+        );
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 2000) as f64 / 100.0 - 10.0
+        };
+        let source: Vec<_> = (0..20)
+            .map(|_| Point {
+                x: random(),
+                y: random(),
+                z: random(),
+            })
+            .collect();
+        let target: Vec<_> = source
+            .iter()
+            .map(|point| {
+                let moved = expected * nalgebra::Point3::new(point.x, point.y, point.z);
+                Point {
+                    x: moved.x,
+                    y: moved.y,
+                    z: moved.z,
+                }
+            })
+            .collect();
+
+        let fitted = isometry_from_transform(&best_fit_transform(&source, &target).unwrap());
+        assert!((fitted.to_homogeneous() - expected.to_homogeneous()).amax() < 1e-9);
+
+        assert!(matches!(
+            best_fit_transform(&source, &target[1..]),
+            Err(TfError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            best_fit_transform(&source[..2], &target[..2]),
+            Err(TfError::InvalidArgument(_))
+        ));
+        let line: Vec<_> = (0..5)
+            .map(|i| Point {
+                x: f64::from(i),
+                y: 0.0,
+                z: 0.0,
+            })
+            .collect();
+        assert!(matches!(
+            best_fit_transform(&line, &line),
+            Err(TfError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_best_fit_transform_planar() {
+        let expected = Isometry3::from_parts(
+            Translation3::new(1.5, 0.2, -0.7),
+            UnitQuaternion::from_euler_angles(-0.3, 0.6, 1.1),
+        );
+        let rectangle = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0), (1.0, 0.5)];
+        let planes: [fn(f64, f64) -> Point; 3] = [
+            |a, b| Point { x: a, y: b, z: 0.0 },
+            |a, b| Point { x: a, y: 0.0, z: b },
+            |a, b| Point { x: 0.0, y: a, z: b },
+        ];
+        for plane in planes {
+            let source: Vec<_> = rectangle.iter().map(|&(a, b)| plane(a, b)).collect();
+            let target: Vec<_> = source
+                .iter()
+                .map(|point| {
+                    let moved = expected * nalgebra::Point3::new(point.x, point.y, point.z);
+                    Point {
+                        x: moved.x,
+                        y: moved.y,
+                        z: moved.z,
+                    }
+                })
+                .collect();
+
+            let fitted = isometry_from_transform(&best_fit_transform(&source, &target).unwrap());
+            assert!((fitted.to_homogeneous() - expected.to_homogeneous()).amax() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_transform_stamped_builder() {
+        let tf = TransformStampedBuilder::new()
+            .parent("a")
+            .child("b")
+            .build();
+        assert_eq!(tf.header.frame_id, "a");
+        assert_eq!(tf.child_frame_id, "b");
+        assert_eq!(
+            tf.transform.rotation,
+            Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0
+            }
+        );
+        assert_eq!(tf.transform.translation, Vector3::default());
+
+        let tf = TransformStampedBuilder::new()
+            .rotation_quat(0.0, 0.0, 0.0, 0.0)
+            .build();
+        assert_eq!(tf.transform.rotation.w, 1.0);
+        let tf = TransformStampedBuilder::new()
+            .rotation_quat(0.0, 0.0, 2.0, 2.0)
+            .build();
+        assert!((tf.transform.rotation.z - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_isometry_round_trip() {
+        let tf = TransformStampedBuilder::new()
+            .translation(1.5, -2.0, 0.25)
+            .rotation_rpy(0.3, -0.7, 2.1)
+            .build()
+            .transform;
+        let iso = Isometry3::from(&tf);
+        // nalgebra stores quaternions as (i, j, k, w), geometry_msgs as (x, y, z, w).
+        assert_eq!(iso.rotation.w, tf.rotation.w);
+        assert_eq!(iso.rotation.i, tf.rotation.x);
+        assert_eq!(iso.translation.vector.z, tf.translation.z);
+        let back: Transform = iso.into();
+        for (expected, actual) in [
+            (tf.translation.x, back.translation.x),
+            (tf.translation.y, back.translation.y),
+            (tf.translation.z, back.translation.z),
+            (tf.rotation.x, back.rotation.x),
+            (tf.rotation.y, back.rotation.y),
+            (tf.rotation.z, back.rotation.z),
+            (tf.rotation.w, back.rotation.w),
+        ] {
+            assert!((expected - actual).abs() < 1e-12);
+        }
+
+        let iso = Isometry3::new(
+            nalgebra::Vector3::new(-3.0, 0.5, 4.0),
+            nalgebra::Vector3::new(0.4, 0.2, -1.3),
+        );
+        let round_trip = Isometry3::from(Transform::from(iso));
+        assert!((round_trip.translation.vector - iso.translation.vector).norm() < 1e-12);
+        assert!(round_trip.rotation.angle_to(&iso.rotation) < 1e-12);
+    }
+
+    #[test]
+    fn test_transform_point() {
+        let tf = TransformStampedBuilder::new()
+            .translation(1.0, 2.0, 3.0)
+            .rotation_rpy(0.0, 0.0, std::f64::consts::FRAC_PI_2)
+            .build()
+            .transform;
+        let point = transform_point(
+            &tf,
+            &Point {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        );
+        assert!((point.x - 1.0).abs() < 1e-9);
+        assert!((point.y - 3.0).abs() < 1e-9);
+        assert!((point.z - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        let sample = |x: f64, yaw: f64| {
+            TransformStampedBuilder::new()
+                .translation(x, 0.0, 0.0)
+                .rotation_rpy(0.0, 0.0, yaw)
+                .build()
+                .transform
+        };
+        let result = extrapolate(&sample(1.0, 0.1), &sample(2.0, 0.3), 0.5);
+        let expected = sample(2.5, 0.4);
+        assert!((result.translation.x - expected.translation.x).abs() < 1e-9);
+        assert!((result.rotation.z - expected.rotation.z).abs() < 1e-9);
+        assert!((result.rotation.w - expected.rotation.w).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_mat4_col_major() {
+        let tf = Transform {
+            translation: Vector3 {
+                x: 1f64,
+                y: 2f64,
+                z: 3f64,
+            },
+            rotation: Quaternion {
+                x: 0f64,
+                y: 0f64,
+                z: std::f64::consts::FRAC_1_SQRT_2,
+                w: std::f64::consts::FRAC_1_SQRT_2,
+            },
+        };
+        let row_major = transform_to_matrix(&tf);
+        let col_major = to_mat4_col_major(&tf);
+        let col_major_f32 = to_mat4_col_major_f32(&tf);
+        for (row, values) in row_major.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                assert_eq!(col_major[column * 4 + row], *value);
+                assert!((f64::from(col_major_f32[column * 4 + row]) - value).abs() < 1e-6);
+            }
+        }
+        assert_eq!(&col_major[12..16], &[1.0, 2.0, 3.0, 1.0]);
+    }
 }
 
-pub(crate) fn to_transform_stamped(
+/// Wraps `tf` into a message stamped with `time`, from frame `from` to frame `to`.
+pub fn to_transform_stamped(
     tf: Transform,
     from: std::string::String,
     to: std::string::String,
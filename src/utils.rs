@@ -0,0 +1,316 @@
+//! Helpers for working with ROS time types.
+
+use std::cmp::Ordering;
+
+use rosrust::{Duration, Time};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// A point in time accepted by lookups, convertible from a ROS `Time`, `i64` nanoseconds or `f64` seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimePoint(pub Time);
+
+impl From<Time> for TimePoint {
+    fn from(time: Time) -> Self {
+        Self(time)
+    }
+}
+
+impl From<&Time> for TimePoint {
+    fn from(time: &Time) -> Self {
+        Self(*time)
+    }
+}
+
+impl From<i64> for TimePoint {
+    /// Nanoseconds since the epoch, saturating like [`time_from_nanosec`].
+    fn from(nanos: i64) -> Self {
+        Self(time_from_nanosec(nanos))
+    }
+}
+
+impl From<f64> for TimePoint {
+    /// Seconds since the epoch, rounded to the nearest nanosecond and saturating like [`time_from_nanosec`].
+    fn from(seconds: f64) -> Self {
+        Self(time_from_nanosec(
+            (seconds * NANOS_PER_SECOND as f64).round() as i64,
+        ))
+    }
+}
+
+impl From<TimePoint> for Time {
+    fn from(time: TimePoint) -> Self {
+        time.0
+    }
+}
+
+/// Returns the time as nanoseconds since the epoch.
+pub fn time_as_ns_i64(time: &Time) -> i64 {
+    time.nanos()
+}
+
+/// Creates a time from nanoseconds since the epoch.
+///
+/// Negative values saturate to zero and values past the largest representable time saturate to it.
+pub fn time_from_nanosec(nanos: i64) -> Time {
+    let nanos = nanos.clamp(
+        0,
+        i64::from(u32::MAX) * NANOS_PER_SECOND + NANOS_PER_SECOND - 1,
+    );
+    Time::from_nanos(nanos)
+}
+
+/// Orders two times, earliest first.
+pub fn cmp_time(a: &Time, b: &Time) -> Ordering {
+    a.cmp(b)
+}
+
+/// Restricts `time` to the range from `min` to `max`, e.g. the stamps covered by a buffer.
+///
+/// Panics if `min` is after `max`, like [`Ord::clamp`].
+pub fn clamp_time(time: &Time, min: &Time, max: &Time) -> Time {
+    (*time).clamp(*min, *max)
+}
+
+/// Returns the duration as signed nanoseconds.
+pub fn duration_as_ns_i64(duration: &Duration) -> i64 {
+    duration.nanos()
+}
+
+/// Creates a duration from signed nanoseconds, saturating at the largest representable durations.
+///
+/// Seconds and nanoseconds of a negative duration are both negative, e.g. -1.5s is `{ sec: -1, nsec: -500_000_000 }`.
+pub fn duration_from_nanosec(nanos: i64) -> Duration {
+    let nanos = nanos.clamp(
+        i64::from(i32::MIN) * NANOS_PER_SECOND - (NANOS_PER_SECOND - 1),
+        i64::from(i32::MAX) * NANOS_PER_SECOND + NANOS_PER_SECOND - 1,
+    );
+    Duration::from_nanos(nanos)
+}
+
+/// Creates a duration from signed seconds, rounded to the nearest nanosecond and saturating like
+/// [`duration_from_nanosec`]. NaN gives a zero duration.
+pub fn duration_from_secs_f64(seconds: f64) -> Duration {
+    duration_from_nanosec((seconds * NANOS_PER_SECOND as f64).round() as i64)
+}
+
+/// Converts a `std::time::Duration` into a ROS `Duration`, saturating at the largest representable ROS duration.
+pub fn std_duration_to_ros(duration: &std::time::Duration) -> Duration {
+    match i32::try_from(duration.as_secs()) {
+        Ok(sec) => Duration {
+            sec,
+            nsec: duration.subsec_nanos() as i32,
+        },
+        Err(_) => Duration {
+            sec: i32::MAX,
+            nsec: 999_999_999,
+        },
+    }
+}
+
+/// Converts a ROS `Duration` into a `std::time::Duration`. Negative durations saturate to zero.
+pub fn ros_duration_to_std(duration: &Duration) -> std::time::Duration {
+    u64::try_from(duration_as_ns_i64(duration))
+        .map(std::time::Duration::from_nanos)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_time_nanosec() {
+        assert_eq!(
+            time_from_nanosec(1_500_000_000),
+            Time {
+                sec: 1,
+                nsec: 500_000_000
+            }
+        );
+        assert_eq!(
+            time_as_ns_i64(&Time {
+                sec: 1,
+                nsec: 500_000_000
+            }),
+            1_500_000_000
+        );
+        assert_eq!(time_from_nanosec(-1), Time::new());
+        assert_eq!(
+            time_from_nanosec(i64::MAX),
+            Time {
+                sec: u32::MAX,
+                nsec: 999_999_999
+            }
+        );
+    }
+
+    #[test]
+    fn test_time_point() {
+        let expected = Time {
+            sec: 1,
+            nsec: 250_000_000,
+        };
+        assert_eq!(TimePoint::from(expected).0, expected);
+        assert_eq!(TimePoint::from(&expected).0, expected);
+        assert_eq!(TimePoint::from(1_250_000_000_i64).0, expected);
+        assert_eq!(TimePoint::from(1.25).0, expected);
+    }
+
+    #[test]
+    fn test_duration_nanosec() {
+        for nanos in [
+            0,
+            1,
+            -1,
+            999_999_999,
+            -999_999_999,
+            1_000_000_000,
+            -1_500_000_000,
+        ] {
+            assert_eq!(duration_as_ns_i64(&duration_from_nanosec(nanos)), nanos);
+        }
+        assert_eq!(
+            duration_from_nanosec(-1_500_000_000),
+            Duration {
+                sec: -1,
+                nsec: -500_000_000
+            }
+        );
+        assert_eq!(
+            duration_as_ns_i64(&Duration {
+                sec: -2,
+                nsec: 500_000_000
+            }),
+            -1_500_000_000
+        );
+        assert_eq!(
+            duration_from_nanosec(i64::MAX),
+            Duration {
+                sec: i32::MAX,
+                nsec: 999_999_999
+            }
+        );
+        assert_eq!(
+            duration_from_nanosec(i64::MIN),
+            Duration {
+                sec: i32::MIN,
+                nsec: -999_999_999
+            }
+        );
+    }
+
+    #[test]
+    fn test_duration_from_secs_f64() {
+        assert_eq!(
+            duration_from_secs_f64(1.25),
+            Duration {
+                sec: 1,
+                nsec: 250_000_000
+            }
+        );
+        assert_eq!(
+            duration_from_secs_f64(-1.5),
+            Duration {
+                sec: -1,
+                nsec: -500_000_000
+            }
+        );
+        assert_eq!(
+            duration_from_secs_f64(-0.25),
+            Duration {
+                sec: 0,
+                nsec: -250_000_000
+            }
+        );
+        assert_eq!(duration_as_ns_i64(&duration_from_secs_f64(1e-9)), 1);
+        assert_eq!(duration_from_secs_f64(f64::NAN), Duration::new());
+        assert_eq!(
+            duration_from_secs_f64(f64::INFINITY),
+            Duration {
+                sec: i32::MAX,
+                nsec: 999_999_999
+            }
+        );
+        assert_eq!(
+            duration_from_secs_f64(f64::NEG_INFINITY),
+            Duration {
+                sec: i32::MIN,
+                nsec: -999_999_999
+            }
+        );
+    }
+
+    #[test]
+    fn test_cmp_and_clamp_time() {
+        let early = Time::from_nanos(1_500_000_000);
+        let late = Time::from_nanos(2_250_000_000);
+        assert_eq!(cmp_time(&early, &late), Ordering::Less);
+        assert_eq!(cmp_time(&late, &early), Ordering::Greater);
+        assert_eq!(cmp_time(&early, &early), Ordering::Equal);
+        // Nanoseconds only break ties between equal seconds.
+        assert_eq!(
+            cmp_time(
+                &Time { sec: 2, nsec: 0 },
+                &Time {
+                    sec: 1,
+                    nsec: 999_999_999
+                }
+            ),
+            Ordering::Greater
+        );
+
+        assert_eq!(clamp_time(&Time::from_seconds(1), &early, &late), early);
+        assert_eq!(clamp_time(&Time::from_seconds(3), &early, &late), late);
+        assert_eq!(
+            clamp_time(&Time::from_seconds(2), &early, &late),
+            Time::from_seconds(2)
+        );
+        // Negative durations move times backwards.
+        assert_eq!(
+            clamp_time(&(late + duration_from_secs_f64(-0.5)), &early, &late),
+            Time::from_nanos(1_750_000_000)
+        );
+    }
+
+    #[test]
+    fn test_std_duration_to_ros() {
+        assert_eq!(
+            std_duration_to_ros(&std::time::Duration::from_millis(250)),
+            Duration {
+                sec: 0,
+                nsec: 250_000_000
+            }
+        );
+        assert_eq!(
+            std_duration_to_ros(&std::time::Duration::from_nanos(3_000_000_001)),
+            Duration { sec: 3, nsec: 1 }
+        );
+        assert_eq!(
+            std_duration_to_ros(&std::time::Duration::from_secs(u64::MAX)),
+            Duration {
+                sec: i32::MAX,
+                nsec: 999_999_999
+            }
+        );
+    }
+
+    #[test]
+    fn test_ros_duration_to_std() {
+        assert_eq!(
+            ros_duration_to_std(&Duration {
+                sec: 0,
+                nsec: 250_000_000
+            }),
+            std::time::Duration::from_millis(250)
+        );
+        assert_eq!(
+            ros_duration_to_std(&Duration::from_nanos(3_000_000_001)),
+            std::time::Duration::from_nanos(3_000_000_001)
+        );
+        assert_eq!(
+            ros_duration_to_std(&Duration::from_nanos(-1_500_000_000)),
+            std::time::Duration::ZERO
+        );
+    }
+}